@@ -0,0 +1,198 @@
+//! How many bytes (and how long) it takes to get typical dashboard frames out to the terminal.
+//!
+//! Run with `cargo bench`. Before timing anything, each workload prints how many bytes a frame took on
+//! average, which is what the renderer's output optimizations bring down.
+
+#[macro_use]
+extern crate criterion;
+extern crate rcurses;
+#[cfg(unix)]
+extern crate termios;
+
+use std::cell::Cell;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+use criterion::Criterion;
+#[cfg(unix)]
+use termios::Termios;
+use rcurses::acs::LineDrawing;
+use rcurses::backend::Backend;
+use rcurses::chart;
+use rcurses::rect::Rect;
+use rcurses::screen::Screen;
+use rcurses::style::{Attr, Color, ColorDepth, Style};
+use rcurses::window::Window;
+
+const HEIGHT: u16 = 40;
+const WIDTH: u16 = 120;
+// How many frames the bytes per frame are averaged over
+const FRAMES: u64 = 200;
+
+/// A terminal of a fixed size that takes no input.
+struct Headless;
+
+impl Backend for Headless {
+	fn size(&self) -> Option<(u16, u16)> {
+		Some((HEIGHT, WIDTH))
+	}
+	fn set_raw(&mut self) -> Option<()> {
+		Some(())
+	}
+	fn set_signals(&mut self, _on: bool) -> Option<()> {
+		Some(())
+	}
+	fn reset(&mut self) -> Option<()> {
+		Some(())
+	}
+	fn suspend(&mut self) -> Option<()> {
+		Some(())
+	}
+	fn resume(&mut self) -> Option<()> {
+		Some(())
+	}
+	fn read(&mut self, _buf: &mut [u8]) -> Option<usize> {
+		Some(0)
+	}
+	fn wait(&mut self, _timeout: i32) -> Option<bool> {
+		Some(false)
+	}
+	#[cfg(unix)]
+	fn input_fd(&self) -> RawFd {
+		-1
+	}
+	#[cfg(unix)]
+	fn original(&self) -> Option<(RawFd, Termios)> {
+		None
+	}
+}
+
+/// Throws the output away, counting the bytes.
+struct Counter(Rc<Cell<u64>>);
+
+impl Write for Counter {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.set(self.0.get() + buf.len() as u64);
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// A screen with a fixed color depth and line drawing, so the numbers don't depend on where it's run,
+/// and the count of bytes written to it.
+fn screen() -> (Screen, Rc<Cell<u64>>) {
+	let bytes = Rc::new(Cell::new(0));
+	let mut screen = Screen::with_backend(Counter(bytes.clone()), Box::new(Headless)).unwrap();
+	screen.set_color_depth(ColorDepth::TrueColor);
+	screen.set_line_drawing(LineDrawing::Unicode);
+	screen.set_link_support(false);
+	(screen, bytes)
+}
+
+/// Internal: A made up reading that wanders about from one tick to the next
+fn reading(tick: u64, seed: u64) -> f64 {
+	let x = (tick.wrapping_mul(2654435761) ^ seed.wrapping_mul(40503)).wrapping_mul(2246822519) >> 7;
+	((tick as f64 / 9.0 + seed as f64).sin() * 0.4 + 0.5 + (x % 100) as f64 / 1000.0).clamp(0.0, 1.0)
+}
+
+/// A system monitor: a title bar with a clock, a table of processes with colored load and striped
+/// rows, gauges and sparklines.
+fn dashboard(win: &mut Window, tick: u64) {
+	let title = Style::new().fg(Color::Black).bg(Color::Cyan).attrs(Attr::BOLD);
+	win.fill(Rect::new(0, 0, 1, WIDTH), ' ', title);
+	win.set_style(title);
+	win.print(0, 1, "monitor");
+	win.print(0, WIDTH - 9, &format!("{:02}:{:02}:{:02}", tick / 3600 % 24, tick / 60 % 60, tick % 60));
+
+	let header = Style::new().attrs(Attr::BOLD | Attr::UNDERLINE);
+	win.set_style(header);
+	win.print(2, 1, &format!("{:>6}  {:<20} {:>6} {:>8}", "PID", "NAME", "CPU%", "MEM"));
+	for row in 0..30u16 {
+		let seed = row as u64;
+		let cpu = reading(tick, seed) * 100.0;
+		let stripe = if row % 2 == 0 { Color::Default } else { Color::Rgb(30, 30, 36) };
+		let base = Style::new().bg(stripe);
+		win.set_style(base);
+		win.print(3 + row, 1, &format!("{:>6}  {:<20} ", 1000 + row * 37, format!("worker-{}", row)));
+		let load = if cpu > 80.0 { Color::Red } else if cpu > 50.0 { Color::Yellow } else { Color::Green };
+		win.set_style(base.fg(load));
+		win.print(3 + row, 31, &format!("{:>6.1}", cpu));
+		win.set_style(base);
+		win.print(3 + row, 37, &format!(" {:>7}M", 100 + (reading(tick / 4, seed + 50) * 900.0) as u32));
+	}
+
+	for (i, name) in ["cpu", "mem", "swap", "disk"].iter().enumerate() {
+		let y = 3 + i as u16 * 4;
+		win.set_style(Style::new().fg(Color::Rgb(200, 200, 200)));
+		win.print(y, 52, name);
+		win.set_style(Style::new().fg(Color::Rgb(80, 200, 120)).bg(Color::Rgb(40, 40, 40)));
+		let ratio = reading(tick, 100 + i as u64);
+		chart::gauge(win, Rect::new(y + 1, 52, 1, 30), ratio, &format!("{:.0}%", ratio * 100.0));
+		let history: Vec<f64> = (0..60).map(|t| reading((tick + t).saturating_sub(60), 100 + i as u64)).collect();
+		win.set_style(Style::new().fg(Color::Rgb(120, 160, 255)));
+		chart::draw_sparkline(win, y + 1, 86, 30, &history, Some((0.0, 1.0)));
+	}
+}
+
+/// A log that scrolls up a line each frame, with the level of each line colored.
+fn log(win: &mut Window, tick: u64) {
+	let levels = [("INFO ", Color::Green), ("WARN ", Color::Yellow), ("ERROR", Color::Red), ("DEBUG", Color::Indexed(244))];
+	for y in 0..HEIGHT {
+		let n = tick + y as u64;
+		let (level, color) = levels[(n * 7 % 11 % 4) as usize];
+		win.set_style(Style::new().fg(Color::Indexed(240)));
+		win.print(y, 0, &format!("{:>8} ", n));
+		win.set_style(Style::new().fg(color).attrs(Attr::BOLD));
+		win.print(y, 9, level);
+		win.set_style(Style::new());
+		let text = format!(" request {} took {}ms from 10.0.{}.{}", n * 13 % 997, n * 31 % 400, n % 256, n * 3 % 256);
+		win.print(y, 15, &format!("{:<1$}", text, (WIDTH - 15) as usize));
+	}
+}
+
+/// Draws frame number `tick` with `draw` and sends it out, redrawing every cell if `full`.
+fn frame(screen: &mut Screen, win: &mut Window, tick: u64, full: bool, draw: fn(&mut Window, u64)) {
+	if full {
+		screen.invalidate_rect(Rect::new(0, 0, HEIGHT, WIDTH));
+	}
+	win.clear();
+	draw(win, tick);
+	screen.draw(win);
+	screen.refresh();
+}
+
+fn workload(c: &mut Criterion, name: &str, full: bool, draw: fn(&mut Window, u64)) {
+	let (mut screen, bytes) = screen();
+	let mut win = Window::new(0, 0, HEIGHT, WIDTH);
+	// The first frame is the same either way, so it's left out of the average
+	frame(&mut screen, &mut win, 0, false, draw);
+	bytes.set(0);
+	for tick in 1..=FRAMES {
+		frame(&mut screen, &mut win, tick, full, draw);
+	}
+	println!("{}: {} bytes/frame", name, bytes.get() / FRAMES);
+
+	let mut tick = FRAMES;
+	c.bench_function(name, |b| b.iter(|| {
+		tick += 1;
+		frame(&mut screen, &mut win, tick, full, draw);
+	}));
+}
+
+fn dashboard_repaint(c: &mut Criterion) {
+	workload(c, "dashboard_repaint", true, dashboard);
+}
+
+fn dashboard_update(c: &mut Criterion) {
+	workload(c, "dashboard_update", false, dashboard);
+}
+
+fn log_scroll(c: &mut Criterion) {
+	workload(c, "log_scroll", false, log);
+}
+
+criterion_group!(benches, dashboard_repaint, dashboard_update, log_scroll);
+criterion_main!(benches);
@@ -0,0 +1,115 @@
+//! Line drawing, for terminals that can't show Unicode's box drawing characters.
+//!
+//! Everything is drawn with the Unicode characters (see `Glyph`), and the Screen swaps them for the
+//! VT100 alternate character set or plain ASCII when drawing, depending on `LineDrawing`.
+
+/// The line drawing pieces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Glyph {
+	HLine,
+	VLine,
+	TopLeft,
+	TopRight,
+	BottomLeft,
+	BottomRight,
+	/// ├
+	TeeLeft,
+	/// ┤
+	TeeRight,
+	/// ┬
+	TeeTop,
+	/// ┴
+	TeeBottom,
+	Cross,
+}
+impl Glyph {
+	/// The Unicode box drawing character, which is what gets put in cells.
+	pub fn ch(&self) -> char {
+		match *self {
+			Glyph::HLine => '─',
+			Glyph::VLine => '│',
+			Glyph::TopLeft => '┌',
+			Glyph::TopRight => '┐',
+			Glyph::BottomLeft => '└',
+			Glyph::BottomRight => '┘',
+			Glyph::TeeLeft => '├',
+			Glyph::TeeRight => '┤',
+			Glyph::TeeTop => '┬',
+			Glyph::TeeBottom => '┴',
+			Glyph::Cross => '┼',
+		}
+	}
+}
+
+/// How line drawing characters get drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineDrawing {
+	/// As they are
+	Unicode,
+	/// With the VT100 alternate character set (`ESC ( 0`), which nearly every terminal has
+	Acs,
+	/// With `+`, `-`, and `|`
+	Ascii,
+}
+impl LineDrawing {
+	/// Unicode if the locale is UTF-8, ASCII on dumb terminals, and the alternate character set otherwise.
+	pub fn detect() -> LineDrawing {
+		let var = |name: &str| ::std::env::var(name).unwrap_or_default();
+		if var("TERM") == "dumb" {
+			return LineDrawing::Ascii;
+		}
+		// The first of these that's set is the one that counts
+		let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter().map(|v| var(v)).find(|v| !v.is_empty()).unwrap_or_default().to_lowercase();
+		if locale.contains("utf-8") || locale.contains("utf8") {
+			LineDrawing::Unicode
+		} else {
+			LineDrawing::Acs
+		}
+	}
+}
+
+/// The character for `ch` in the VT100 alternate character set, if it's a line drawing character.
+pub fn to_acs(ch: char) -> Option<char> {
+	Some(match ch {
+		'─' | '━' | '═' => 'q',
+		'│' | '┃' | '║' => 'x',
+		'┌' | '┏' | '╔' | '╭' => 'l',
+		'┐' | '┓' | '╗' | '╮' => 'k',
+		'└' | '┗' | '╚' | '╰' => 'm',
+		'┘' | '┛' | '╝' | '╯' => 'j',
+		'├' | '┣' | '╠' => 't',
+		'┤' | '┫' | '╣' => 'u',
+		'┬' | '┳' | '╦' => 'w',
+		'┴' | '┻' | '╩' => 'v',
+		'┼' | '╋' | '╬' => 'n',
+		_ => return None,
+	})
+}
+
+/// The line drawing character that `ch` stands for in the VT100 alternate character set, the other
+/// way around from `to_acs`.
+pub fn from_acs(ch: char) -> Option<char> {
+	Some(match ch {
+		'q' => '─',
+		'x' => '│',
+		'l' => '┌',
+		'k' => '┐',
+		'm' => '└',
+		'j' => '┘',
+		't' => '├',
+		'u' => '┤',
+		'w' => '┬',
+		'v' => '┴',
+		'n' => '┼',
+		_ => return None,
+	})
+}
+
+/// The closest ASCII character to `ch`, if it's a line drawing character.
+pub fn to_ascii(ch: char) -> Option<char> {
+	Some(match to_acs(ch)? {
+		'q' => '-',
+		'x' => '|',
+		_ => '+',
+	})
+}
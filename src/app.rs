@@ -0,0 +1,212 @@
+//! The usual main loop of an app, see `run_app`.
+
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use ::event::{Event, Key};
+use ::screen::{ModalEffects, ModeState, MouseMode, Screen, SignalPolicy};
+use ::style::{Attr, Style};
+use ::text::{self, WrapMode};
+use ::window::Window;
+
+// The id of the timer `AppConfig::tick` sets up
+pub const TICK_TIMER: usize = usize::MAX;
+
+/// Something that went wrong in an app.
+pub type AppError = Box<dyn Error>;
+
+/// What the app wants to happen after handling an event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+	Continue,
+	Quit,
+}
+
+/// An app for `run_app` to run.
+pub trait App {
+	/// Called once before the first draw, e.g. for setting up timers or filters.
+	fn init(&mut self, screen: &mut Screen) -> Result<(), AppError> {
+		let _ = screen;
+		Ok(())
+	}
+	/// Draws the app, the screen gets refreshed right after.
+	fn draw(&mut self, screen: &mut Screen);
+	/// Handles an event. Errors (and panics) are shown on an error screen, which lets the user quit or
+	/// carry on.
+	fn handle(&mut self, screen: &mut Screen, event: Event) -> Result<Flow, AppError>;
+}
+
+/// How `run_app` sets up the screen.
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+	/// Puts the terminal in raw mode, so every key gets through right away (default)
+	pub raw: bool,
+	/// What Ctrl-C does, see `Screen::set_signal_policy`. None leaves it to the mode
+	pub signals: Option<SignalPolicy>,
+	pub mouse: MouseMode,
+	/// Sends `Event::FocusGained` and `Event::FocusLost`, see `Screen::set_focus_reporting`
+	pub focus: bool,
+	/// Sends `Event::Tick(TICK_TIMER)` this often
+	pub tick: Option<Duration>,
+	/// Quits straight away when pressed, without the app seeing it
+	pub quit_key: Option<Key>,
+	/// Shows panics in the app on the error screen, instead of crashing (default)
+	pub catch_panics: bool,
+}
+
+impl Default for AppConfig {
+	fn default() -> AppConfig {
+		AppConfig {
+			raw: true,
+			signals: None,
+			mouse: MouseMode::Off,
+			focus: false,
+			tick: None,
+			quit_key: None,
+			catch_panics: true,
+		}
+	}
+}
+
+/// Runs `app` until it quits: sets up the screen, then draws and hands it events in a loop, and puts
+/// the terminal back afterwards (see `Screen::shutdown`).
+///
+/// When the app returns an error or panics, an error screen pops up over it with the message, where
+/// the user can quit or carry on as if the event never happened.
+/// Returns the app, or the error the user quit on. If the screen can't be set up, returns an error too
+///
+/// NOTE: While panics are being caught the panic hook is replaced (for every thread), so nothing gets
+/// printed over the screen. The old one is put back before returning.
+pub fn run_app<A: App>(config: AppConfig, mut app: A) -> Result<A, AppError> {
+	let mut screen = match Screen::new() {
+		Some(screen) => screen,
+		None if Screen::in_use() => return Err("another Screen has the terminal".into()),
+		None => return Err("the terminal couldn't be set up".into()),
+	};
+	if config.raw {
+		screen.set_mode(ModeState::Raw).ok_or("the terminal couldn't be put in raw mode")?;
+	}
+	if let Some(policy) = config.signals {
+		screen.set_signal_policy(policy).ok_or("the terminal's settings couldn't be changed")?;
+	}
+	screen.set_mouse(config.mouse);
+	if config.focus {
+		screen.set_focus_reporting(true);
+	}
+	if let Some(every) = config.tick {
+		screen.set_timer(every, TICK_TIMER);
+	}
+
+	let panics = if config.catch_panics { Some(PanicCatcher::install()) } else { None };
+	let result = run_loop(&config, &mut app, &mut screen, panics.as_ref());
+	if let Some(panics) = panics {
+		panics.uninstall();
+	}
+	screen.shutdown();
+	result.map(|_| app)
+}
+
+/// Internal: Draws and handles events until the app quits
+fn run_loop<A: App>(config: &AppConfig, app: &mut A, screen: &mut Screen, panics: Option<&PanicCatcher>) -> Result<(), AppError> {
+	if let Err(err) = app.init(screen) {
+		if error_screen(screen, &err.to_string()) == Flow::Quit {
+			return Err(err);
+		}
+	}
+	loop {
+		let drawn = guard(panics, || app.draw(screen));
+		if let Err(message) = drawn {
+			if error_screen(screen, &message) == Flow::Quit {
+				return Err(message.into());
+			}
+			continue;
+		}
+		screen.refresh();
+
+		let event = match screen.read_event() {
+			Some(event) => event,
+			None => return Ok(()),
+		};
+		if let Event::Key(key) = event {
+			if config.quit_key == Some(key) {
+				return Ok(());
+			}
+		}
+		match guard(panics, || app.handle(screen, event)) {
+			Ok(Ok(Flow::Continue)) => {},
+			Ok(Ok(Flow::Quit)) => return Ok(()),
+			Ok(Err(err)) => if error_screen(screen, &err.to_string()) == Flow::Quit {
+				return Err(err);
+			},
+			Err(message) => if error_screen(screen, &message) == Flow::Quit {
+				return Err(message.into());
+			},
+		}
+	}
+}
+
+/// Internal: Runs `f`, catching a panic if `panics` is set.
+/// Returns what `f` did, or what the panic said
+fn guard<T, F: FnOnce() -> T>(panics: Option<&PanicCatcher>, f: F) -> Result<T, String> {
+	match panics {
+		Some(panics) => panic::catch_unwind(AssertUnwindSafe(f)).map_err(|_| panics.take()),
+		None => Ok(f()),
+	}
+}
+
+/// Internal: Shows `message` in a popup until the user picks quit (q or Esc) or continue (c or Enter)
+fn error_screen(screen: &mut Screen, message: &str) -> Flow {
+	let (height, width) = screen.terminal_size();
+	let inner = width.saturating_sub(8).clamp(1, 70);
+	let lines = text::wrap(message, inner, WrapMode::Word);
+	let rows = (lines.len() as u16).min(height.saturating_sub(6));
+	let (h, w) = (rows + 4, inner + 4);
+	let mut win = Window::new(height.saturating_sub(h) / 2, width.saturating_sub(w) / 2, h, w);
+	win.border();
+	win.set_style(Style::new().attrs(Attr::BOLD));
+	win.print(0, 2, " Error ");
+	win.set_style(Style::new());
+	for (i, line) in lines.iter().take(rows as usize).enumerate() {
+		win.print(1 + i as u16, 2, line);
+	}
+	win.print(h - 2, 2, "[q] Quit  [c] Continue");
+	let popup = screen.show_popup(win, ModalEffects { dim: true, shadow: true });
+	screen.refresh();
+
+	let flow = loop {
+		match screen.read_event() {
+			Some(Event::Key(Key::Char('q'))) | Some(Event::Key(Key::Esc)) | None => break Flow::Quit,
+			Some(Event::Key(Key::Char('c'))) | Some(Event::Key(Key::Enter)) => break Flow::Continue,
+			_ => {},
+		}
+	};
+	screen.dismiss_popup(popup);
+	flow
+}
+
+/// Internal: Keeps the message of the last panic instead of printing it, see `run_app`
+struct PanicCatcher {
+	message: Arc<Mutex<Option<String>>>,
+	previous: Box<dyn Fn(&panic::PanicHookInfo) + Sync + Send>,
+}
+
+impl PanicCatcher {
+	fn install() -> PanicCatcher {
+		let previous = panic::take_hook();
+		let message = Arc::new(Mutex::new(None));
+		let slot = message.clone();
+		panic::set_hook(Box::new(move |info| {
+			*slot.lock().unwrap_or_else(|e| e.into_inner()) = Some(info.to_string());
+		}));
+		PanicCatcher { message, previous }
+	}
+	/// Puts back the panic hook from before.
+	fn uninstall(self) {
+		panic::set_hook(self.previous);
+	}
+	/// What the last panic said.
+	fn take(&self) -> String {
+		self.message.lock().unwrap_or_else(|e| e.into_inner()).take().unwrap_or_else(|| "the app panicked".to_string())
+	}
+}
@@ -0,0 +1,68 @@
+//! What it takes to talk to a terminal on each platform, see `Backend`.
+//!
+//! Drawing, colors, the mouse and so on are all escape sequences, which are the same everywhere. What
+//! isn't is putting the terminal into raw mode, finding out how big it is, and reading input, so that's
+//! all a backend does.
+
+#[cfg(unix)]
+mod unix;
+#[cfg(windows)]
+mod windows;
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+#[cfg(unix)]
+use ::termios::Termios;
+
+#[cfg(unix)]
+pub use self::unix::UnixBackend;
+#[cfg(windows)]
+pub use self::windows::WindowsBackend;
+
+/// The platform specific side of a terminal: its settings, its size and its input.
+///
+/// Settings changed through a backend can be put back the way they were when it was made, for good with
+/// `reset` or just for a while with `suspend` and `resume`.
+pub trait Backend {
+	/// The size of the terminal, as (height, width).
+	/// If it can't be told, returns None
+	fn size(&self) -> Option<(u16, u16)>;
+	/// Switches to raw mode: input comes in as it's typed without being echoed, and output goes out as
+	/// it is. Ctrl-C and the like get read as keys, see `set_signals`.
+	/// If the settings can't be changed, returns None
+	fn set_raw(&mut self) -> Option<()>;
+	/// Whether Ctrl-C (and Ctrl-Z, ...) send signals, or get read as keys.
+	/// If the settings can't be changed, returns None
+	fn set_signals(&mut self, on: bool) -> Option<()>;
+	/// Puts the settings back the way they were when the backend was made, forgetting the changes.
+	/// If the settings can't be changed, returns None
+	fn reset(&mut self) -> Option<()>;
+	/// Puts the settings back the way they were when the backend was made until `resume`, e.g. while
+	/// the process is stopped.
+	fn suspend(&mut self) -> Option<()>;
+	/// Changes the settings back after `suspend`.
+	fn resume(&mut self) -> Option<()>;
+	/// Whether there's a terminal behind the backend at all. Screens without one (e.g. with a
+	/// `TestBackend`) leave the terminal and signals alone, so any number of them can be alive at once.
+	fn is_terminal(&self) -> bool {
+		true
+	}
+	/// Says the terminal's settings have been put back for good, by the Screen that had it. Until then,
+	/// backends made later for the same terminal put back the settings from before the first one, in case
+	/// the Screen before them never cleaned up.
+	fn release(&mut self) {}
+	/// Reads whatever input is there, waiting for some if there isn't any.
+	/// Returns how many bytes were read (0 at the end of input), or None if reading failed
+	fn read(&mut self, buf: &mut [u8]) -> Option<usize>;
+	/// Waits up to `timeout` milliseconds (forever if negative) for input.
+	/// Returns whether there's input, or None if waiting failed
+	fn wait(&mut self, timeout: i32) -> Option<bool>;
+	/// The descriptor input is read from, so it can be waited on along with other things.
+	#[cfg(unix)]
+	fn input_fd(&self) -> RawFd;
+	/// The terminal's descriptor and its settings from when the backend was made, for the crash guard
+	/// to put back (see `Screen::spawn_crash_guard`).
+	/// If there's no terminal behind the backend, returns None
+	#[cfg(unix)]
+	fn original(&self) -> Option<(RawFd, Termios)>;
+}
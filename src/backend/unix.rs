@@ -0,0 +1,137 @@
+//! The backend for Unix ttys, with termios and ioctls.
+
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::sync::Mutex;
+use ::termios::{Termios, tcsetattr, cfmakeraw, ISIG};
+use super::Backend;
+
+const TCSANOW: i32 = 0;
+
+// The settings of the terminal a backend was made for (by device number), from before anything changed
+// them, until the backend is released. Backends made for it in the meantime put these back instead of
+// whatever they find, which could be what an earlier Screen that never cleaned up left behind
+static FIRST: Mutex<Option<(::libc::dev_t, Termios)>> = Mutex::new(None);
+
+/// A Unix terminal, set up through the descriptor of its output and read from another one (often the
+/// same one, or stdin).
+pub struct UnixBackend {
+	fd: RawFd,
+	input: RawFd,
+	device: Option<::libc::dev_t>,
+	original: Termios,
+	settings: Termios,
+	/// Whether `suspend` put the original settings back for now
+	suspended: bool,
+}
+
+impl UnixBackend {
+	/// A backend for the terminal behind `fd`, reading input from `input`. Both have to stay open for as
+	/// long as the backend is alive.
+	/// If `fd` isn't a terminal, returns None
+	pub fn new(fd: RawFd, input: RawFd) -> Option<UnixBackend> {
+		if unsafe { ::libc::isatty(fd) } == 0 {
+			return None;
+		}
+		let current = Termios::from_fd(fd).ok()?;
+		let device = device(fd);
+		let mut first = FIRST.lock().unwrap_or_else(|e| e.into_inner());
+		let original = match (device, *first) {
+			(Some(device), Some((other, settings))) if other == device => settings,
+			(Some(device), None) => {
+				*first = Some((device, current));
+				current
+			},
+			_ => current,
+		};
+		Some(UnixBackend {
+			fd,
+			input,
+			device,
+			original,
+			settings: original,
+			suspended: false,
+		})
+	}
+	/// Internal: Sets the terminal to `settings`
+	fn apply(&self, settings: &Termios) -> Option<()> {
+		tcsetattr(self.fd, TCSANOW, settings).ok()
+	}
+	/// Internal: Sets the terminal to the changed settings, unless it's suspended
+	fn update(&mut self) -> Option<()> {
+		if self.suspended {
+			return Some(());
+		}
+		self.apply(&self.settings)
+	}
+}
+
+impl Backend for UnixBackend {
+	fn size(&self) -> Option<(u16, u16)> {
+		let mut ws = ::libc::winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+		if unsafe { ::libc::ioctl(self.fd, ::libc::TIOCGWINSZ, &mut ws) } < 0 {
+			return None;
+		}
+		Some((ws.ws_row, ws.ws_col))
+	}
+	fn set_raw(&mut self) -> Option<()> {
+		cfmakeraw(&mut self.settings);
+		self.update()
+	}
+	fn set_signals(&mut self, on: bool) -> Option<()> {
+		if on {
+			self.settings.c_lflag |= ISIG;
+		} else {
+			self.settings.c_lflag &= !ISIG;
+		}
+		self.update()
+	}
+	fn reset(&mut self) -> Option<()> {
+		self.settings = self.original;
+		self.update()
+	}
+	fn suspend(&mut self) -> Option<()> {
+		self.suspended = true;
+		self.apply(&self.original)
+	}
+	fn resume(&mut self) -> Option<()> {
+		self.suspended = false;
+		self.update()
+	}
+	fn release(&mut self) {
+		let mut first = FIRST.lock().unwrap_or_else(|e| e.into_inner());
+		if self.device.is_some() && first.map(|(device, _)| device) == self.device {
+			*first = None;
+		}
+	}
+	fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+		let n = unsafe { ::libc::read(self.input, buf.as_mut_ptr() as *mut ::libc::c_void, buf.len()) };
+		if n < 0 {
+			return None;
+		}
+		Some(n as usize)
+	}
+	fn wait(&mut self, timeout: i32) -> Option<bool> {
+		let mut fds = ::libc::pollfd { fd: self.input, events: ::libc::POLLIN, revents: 0 };
+		let n = unsafe { ::libc::poll(&mut fds, 1, timeout) };
+		if n < 0 {
+			return None;
+		}
+		Some(n > 0)
+	}
+	fn input_fd(&self) -> RawFd {
+		self.input
+	}
+	fn original(&self) -> Option<(RawFd, Termios)> {
+		Some((self.fd, self.original))
+	}
+}
+
+/// Internal: The device number of the terminal behind `fd`
+fn device(fd: RawFd) -> Option<::libc::dev_t> {
+	let mut stat: ::libc::stat = unsafe { mem::zeroed() };
+	if unsafe { ::libc::fstat(fd, &mut stat) } < 0 {
+		return None;
+	}
+	Some(stat.st_rdev)
+}
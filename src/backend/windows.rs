@@ -0,0 +1,240 @@
+//! The backend for the Windows console (Windows 10 and later), which understands the same escape
+//! sequences as everything else once its virtual terminal modes are on.
+
+use std::os::raw::c_void;
+use std::ptr;
+use std::sync::Mutex;
+use std::time::Instant;
+use super::Backend;
+
+type Handle = *mut c_void;
+
+const STD_INPUT_HANDLE: u32 = -10i32 as u32;
+const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+const INVALID_HANDLE_VALUE: Handle = -1isize as Handle;
+const INFINITE: u32 = 0xffff_ffff;
+const WAIT_OBJECT_0: u32 = 0;
+const WAIT_TIMEOUT: u32 = 0x102;
+const KEY_EVENT: u16 = 1;
+const UTF8: u32 = 65001;
+
+// Input modes
+const ENABLE_PROCESSED_INPUT: u32 = 0x1;
+const ENABLE_LINE_INPUT: u32 = 0x2;
+const ENABLE_ECHO_INPUT: u32 = 0x4;
+const ENABLE_QUICK_EDIT_MODE: u32 = 0x40;
+const ENABLE_EXTENDED_FLAGS: u32 = 0x80;
+const ENABLE_VIRTUAL_TERMINAL_INPUT: u32 = 0x200;
+// Output modes
+const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x4;
+const DISABLE_NEWLINE_AUTO_RETURN: u32 = 0x8;
+
+#[repr(C)]
+#[derive(Default)]
+struct Coord {
+	x: i16,
+	y: i16,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct SmallRect {
+	left: i16,
+	top: i16,
+	right: i16,
+	bottom: i16,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct ScreenBufferInfo {
+	size: Coord,
+	cursor: Coord,
+	attributes: u16,
+	window: SmallRect,
+	max_window: Coord,
+}
+
+/// Internal: An INPUT_RECORD, laid out as a key event since that's the only kind that gets looked at.
+/// The other kinds are no bigger
+#[repr(C)]
+#[derive(Default)]
+struct InputRecord {
+	event_type: u16,
+	key_down: i32,
+	repeat: u16,
+	key_code: u16,
+	scan_code: u16,
+	ch: u16,
+	control: u32,
+}
+
+extern "system" {
+	fn GetStdHandle(which: u32) -> Handle;
+	fn GetConsoleMode(console: Handle, mode: *mut u32) -> i32;
+	fn SetConsoleMode(console: Handle, mode: u32) -> i32;
+	fn GetConsoleCP() -> u32;
+	fn SetConsoleCP(page: u32) -> i32;
+	fn GetConsoleOutputCP() -> u32;
+	fn SetConsoleOutputCP(page: u32) -> i32;
+	fn GetConsoleScreenBufferInfo(console: Handle, info: *mut ScreenBufferInfo) -> i32;
+	fn WaitForSingleObject(handle: Handle, millis: u32) -> u32;
+	fn PeekConsoleInputW(console: Handle, records: *mut InputRecord, len: u32, read: *mut u32) -> i32;
+	fn ReadConsoleInputW(console: Handle, records: *mut InputRecord, len: u32, read: *mut u32) -> i32;
+	fn ReadFile(file: Handle, buf: *mut c_void, len: u32, read: *mut u32, overlapped: *mut c_void) -> i32;
+}
+
+// The console's settings from before the first backend changed them, which later ones put back too
+// until it's released
+static FIRST: Mutex<Option<Settings>> = Mutex::new(None);
+
+/// The console's settings: its input and output modes, and code pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Settings {
+	input: u32,
+	output: u32,
+	input_cp: u32,
+	output_cp: u32,
+}
+
+/// The console this process is attached to, through its standard handles.
+///
+/// Escape sequences get turned on as soon as it's made, and text goes both ways as UTF-8.
+pub struct WindowsBackend {
+	input: Handle,
+	output: Handle,
+	original: Settings,
+	settings: Settings,
+	/// Whether `suspend` put the original settings back for now
+	suspended: bool,
+}
+
+impl WindowsBackend {
+	/// A backend for the console behind stdin and stdout.
+	/// If either isn't a console, or it's too old for escape sequences (before Windows 10), returns None
+	pub fn new() -> Option<WindowsBackend> {
+		let (input, output) = unsafe { (GetStdHandle(STD_INPUT_HANDLE), GetStdHandle(STD_OUTPUT_HANDLE)) };
+		if input.is_null() || input == INVALID_HANDLE_VALUE || output.is_null() || output == INVALID_HANDLE_VALUE {
+			return None;
+		}
+		let (mut input_mode, mut output_mode) = (0, 0);
+		if unsafe { GetConsoleMode(input, &mut input_mode) == 0 || GetConsoleMode(output, &mut output_mode) == 0 } {
+			return None;
+		}
+		let original = *FIRST.lock().unwrap_or_else(|e| e.into_inner()).get_or_insert_with(|| Settings {
+			input: input_mode,
+			output: output_mode,
+			input_cp: unsafe { GetConsoleCP() },
+			output_cp: unsafe { GetConsoleOutputCP() },
+		});
+		let mut out = WindowsBackend {
+			input,
+			output,
+			original,
+			settings: original,
+			suspended: false,
+		};
+		out.settings.output |= ENABLE_VIRTUAL_TERMINAL_PROCESSING;
+		out.settings.input_cp = UTF8;
+		out.settings.output_cp = UTF8;
+		// Consoles that don't know the mode refuse it
+		out.update()?;
+		Some(out)
+	}
+	/// Internal: Sets the console to `settings`
+	fn apply(&self, settings: &Settings) -> Option<()> {
+		unsafe {
+			if SetConsoleMode(self.input, settings.input) == 0 || SetConsoleMode(self.output, settings.output) == 0 {
+				return None;
+			}
+			SetConsoleCP(settings.input_cp);
+			SetConsoleOutputCP(settings.output_cp);
+		}
+		Some(())
+	}
+	/// Internal: Sets the console to the changed settings, unless it's suspended
+	fn update(&mut self) -> Option<()> {
+		if self.suspended {
+			return Some(());
+		}
+		self.apply(&self.settings)
+	}
+}
+
+impl Backend for WindowsBackend {
+	fn size(&self) -> Option<(u16, u16)> {
+		let mut info = ScreenBufferInfo::default();
+		if unsafe { GetConsoleScreenBufferInfo(self.output, &mut info) } == 0 {
+			return None;
+		}
+		// The part that's showing, not the whole scrollback
+		let height = info.window.bottom - info.window.top + 1;
+		let width = info.window.right - info.window.left + 1;
+		Some((height.max(0) as u16, width.max(0) as u16))
+	}
+	fn set_raw(&mut self) -> Option<()> {
+		self.settings.input &= !(ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT | ENABLE_PROCESSED_INPUT | ENABLE_QUICK_EDIT_MODE);
+		// Quick edit can only be turned off along with the extended flags
+		self.settings.input |= ENABLE_VIRTUAL_TERMINAL_INPUT | ENABLE_EXTENDED_FLAGS;
+		self.settings.output |= DISABLE_NEWLINE_AUTO_RETURN;
+		self.update()
+	}
+	fn set_signals(&mut self, on: bool) -> Option<()> {
+		if on {
+			self.settings.input |= ENABLE_PROCESSED_INPUT;
+		} else {
+			self.settings.input &= !ENABLE_PROCESSED_INPUT;
+		}
+		self.update()
+	}
+	fn reset(&mut self) -> Option<()> {
+		self.settings = self.original;
+		self.update()
+	}
+	fn suspend(&mut self) -> Option<()> {
+		self.suspended = true;
+		self.apply(&self.original)
+	}
+	fn resume(&mut self) -> Option<()> {
+		self.suspended = false;
+		self.update()
+	}
+	fn release(&mut self) {
+		*FIRST.lock().unwrap_or_else(|e| e.into_inner()) = None;
+	}
+	fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+		let mut n = 0;
+		let len = buf.len().min(u32::MAX as usize) as u32;
+		if unsafe { ReadFile(self.input, buf.as_mut_ptr() as *mut c_void, len, &mut n, ptr::null_mut()) } == 0 {
+			return None;
+		}
+		Some(n as usize)
+	}
+	fn wait(&mut self, timeout: i32) -> Option<bool> {
+		let start = Instant::now();
+		loop {
+			let left = if timeout < 0 { INFINITE } else { (timeout as i64 - start.elapsed().as_millis() as i64).max(0) as u32 };
+			match unsafe { WaitForSingleObject(self.input, left) } {
+				WAIT_OBJECT_0 => {},
+				WAIT_TIMEOUT => return Some(false),
+				_ => return None,
+			}
+			// The console also gets woken by focus changes and the like, which reading skips over and
+			// then blocks. Only a key press turns into something to read, the rest get thrown away
+			let mut record = InputRecord::default();
+			let mut n = 0;
+			if unsafe { PeekConsoleInputW(self.input, &mut record, 1, &mut n) } == 0 {
+				return None;
+			}
+			if n == 0 {
+				continue;
+			}
+			if record.event_type == KEY_EVENT && record.key_down != 0 && record.ch != 0 {
+				return Some(true);
+			}
+			if unsafe { ReadConsoleInputW(self.input, &mut record, 1, &mut n) } == 0 {
+				return None;
+			}
+		}
+	}
+}
@@ -0,0 +1,286 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use ::rect::Rect;
+use ::style::{Link, Style};
+
+/// What the cell after a double width character holds, since the character covers it.
+pub const CONTINUATION: char = '\0';
+
+/// A single character cell.
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub struct Cell {
+	/// The character shown in the cell
+	pub ch: char,
+	/// How the character looks
+	pub style: Style,
+}
+impl Default for Cell {
+	fn default() -> Cell {
+		Cell { ch: ' ', style: Style::default() }
+	}
+}
+
+// Hands out the ids that tell buffers apart, see `Buffer::generation`
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A grid of cells, stored row by row.
+#[derive(Debug)]
+pub struct Buffer {
+	height: u16,
+	width: u16,
+	cells: Vec<Cell>,
+	/// Different for every buffer, clones included
+	id: u64,
+	/// Goes up whenever a cell changes (or might have, through `get_mut`)
+	changes: u64,
+	/// For each line, whether a cell on it might have changed since `mark_clean`
+	dirty: Vec<bool>,
+}
+impl Clone for Buffer {
+	fn clone(&self) -> Buffer {
+		Buffer {
+			height: self.height,
+			width: self.width,
+			cells: self.cells.clone(),
+			id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+			changes: 0,
+			dirty: vec![true; self.height as usize],
+		}
+	}
+}
+impl PartialEq for Buffer {
+	fn eq(&self, other: &Buffer) -> bool {
+		self.height == other.height && self.width == other.width && self.cells == other.cells
+	}
+}
+
+impl Buffer {
+	/// Creates a buffer filled with blank cells.
+	pub fn new(height: u16, width: u16) -> Buffer {
+		Buffer {
+			height,
+			width,
+			cells: vec![Cell::default(); height as usize * width as usize],
+			id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+			changes: 0,
+			dirty: vec![true; height as usize],
+		}
+	}
+	#[inline]
+	/// Get the height (amount of lines) of the buffer
+	pub fn get_height(&self) -> u16 {
+		self.height
+	}
+	#[inline]
+	/// Get the width (amount of columns) of the buffer
+	pub fn get_width(&self) -> u16 {
+		self.width
+	}
+	/// Gets the cell at the given position, or None if it's outside of the buffer.
+	pub fn get(&self, y: u16, x: u16) -> Option<&Cell> {
+		match self.index(y, x) {
+			Some(i) => Some(&self.cells[i]),
+			None => None
+		}
+	}
+	/// Mutable version of `get`.
+	pub fn get_mut(&mut self, y: u16, x: u16) -> Option<&mut Cell> {
+		match self.index(y, x) {
+			Some(i) => {
+				self.changes += 1;
+				self.dirty[y as usize] = true;
+				Some(&mut self.cells[i])
+			},
+			None => None
+		}
+	}
+	/// Sets the cell at the given position.
+	/// If it's outside of the buffer, returns None
+	pub fn set(&mut self, y: u16, x: u16, cell: Cell) -> Option<()> {
+		let i = self.index(y, x)?;
+		if self.cells[i] != cell {
+			self.changes += 1;
+			self.dirty[y as usize] = true;
+			self.cells[i] = cell;
+		}
+		Some(())
+	}
+	/// Copies all of `src` onto this buffer, with its top-left corner at (y, x).
+	/// Anything hanging off of the edge is cut off.
+	pub fn copy_from(&mut self, src: &Buffer, y: u16, x: u16) {
+		for sy in 0..src.height {
+			for sx in 0..src.width {
+				let cell = *src.get(sy, sx).unwrap();
+				self.set(y.saturating_add(sy), x.saturating_add(sx), cell);
+			}
+		}
+	}
+	/// Like `copy_from`, but leaves the cells inside of any of `masked` (in this buffer's coordinates) alone.
+	pub fn copy_from_except(&mut self, src: &Buffer, y: u16, x: u16, masked: &[Rect]) {
+		for sy in 0..src.height {
+			for sx in 0..src.width {
+				let (dy, dx) = (y.saturating_add(sy), x.saturating_add(sx));
+				if !masked.iter().any(|r| r.contains(dy, dx)) {
+					self.set(dy, dx, *src.get(sy, sx).unwrap());
+				}
+			}
+		}
+	}
+	/// Changes the style of every cell inside of `rect`, leaving the characters alone.
+	pub fn restyle<F: FnMut(&mut Style)>(&mut self, rect: Rect, mut f: F) {
+		for y in rect.y..rect.bottom().min(self.height) {
+			for x in rect.x..rect.right().min(self.width) {
+				let i = self.index(y, x).unwrap();
+				let mut style = self.cells[i].style;
+				f(&mut style);
+				if style != self.cells[i].style {
+					self.changes += 1;
+					self.dirty[y as usize] = true;
+					self.cells[i].style = style;
+				}
+			}
+		}
+	}
+	/// Copies `cells` into `rect`, a row at a time: the first `rect.width` of them go on its top line, the
+	/// next ones on the line below and so on. Much faster than setting them one by one, for widgets that
+	/// redraw lots of cells every frame. Anything hanging off of the edge is cut off.
+	/// If there aren't enough cells to cover `rect`, returns None and nothing is copied
+	pub fn blit(&mut self, cells: &[Cell], rect: Rect) -> Option<()> {
+		if cells.len() < rect.height as usize * rect.width as usize {
+			return None;
+		}
+		let area = match rect.intersect(&Rect::new(0, 0, self.height, self.width)) {
+			Some(area) => area,
+			None => return Some(()),
+		};
+		for y in area.y..area.bottom() {
+			let from = (y - rect.y) as usize * rect.width as usize + (area.x - rect.x) as usize;
+			let to = self.index(y, area.x).unwrap();
+			let len = area.width as usize;
+			if self.cells[to..to + len] != cells[from..from + len] {
+				self.changes += 1;
+				self.dirty[y as usize] = true;
+				self.cells[to..to + len].copy_from_slice(&cells[from..from + len]);
+			}
+		}
+		Some(())
+	}
+	/// Sets every cell inside of `rect` to `cell`, a row at a time.
+	pub fn fill(&mut self, rect: Rect, cell: Cell) {
+		let area = match rect.intersect(&Rect::new(0, 0, self.height, self.width)) {
+			Some(area) => area,
+			None => return,
+		};
+		for y in area.y..area.bottom() {
+			let start = self.index(y, area.x).unwrap();
+			let row = &mut self.cells[start..start + area.width as usize];
+			if row.iter().any(|c| *c != cell) {
+				self.changes += 1;
+				self.dirty[y as usize] = true;
+				row.iter_mut().for_each(|c| *c = cell);
+			}
+		}
+	}
+	/// Resets every cell to a blank one.
+	pub fn clear(&mut self) {
+		self.fill(Rect::new(0, 0, self.height, self.width), Cell::default());
+	}
+	/// Blanks out line `y`.
+	pub fn clear_line(&mut self, y: u16) {
+		self.clear_to_eol(y, 0);
+	}
+	/// Blanks out line `y` from column `x` to the end of the line.
+	pub fn clear_to_eol(&mut self, y: u16, x: u16) {
+		if y >= self.height || x >= self.width {
+			return;
+		}
+		self.fill(Rect::new(y, x, 1, self.width - x), Cell::default());
+	}
+	/// Blanks out everything from (y, x) to the end of the buffer.
+	pub fn clear_to_eos(&mut self, y: u16, x: u16) {
+		self.clear_to_eol(y, x);
+		if y < self.height {
+			self.fill(Rect::new(y + 1, 0, self.height - y - 1, self.width), Cell::default());
+		}
+	}
+	/// The characters in the buffer, a line of text for each row with the blanks at the end left off.
+	/// Handy for snapshot tests, since it's stable and readable in a diff.
+	pub fn to_text(&self) -> String {
+		let lines: Vec<String> = (0..self.height).map(|y| {
+			let line: String = self.row(y).iter().map(|c| c.ch).filter(|&ch| ch != CONTINUATION).collect();
+			line.trim_end_matches(' ').to_string()
+		}).collect();
+		lines.join("\n")
+	}
+	/// Like `to_text`, but with escape sequences for the styles and links, so it looks the same when
+	/// printed to a terminal (e.g. with `cat`). Every line starts and ends unstyled.
+	pub fn to_ansi(&self) -> String {
+		let mut lines = Vec::with_capacity(self.height as usize);
+		for y in 0..self.height {
+			let row = self.row(y);
+			// Blanks at the end only matter if they're styled
+			let len = row.iter().rposition(|c| c.ch != ' ' || c.style != Style::default()).map_or(0, |i| i + 1);
+			let mut line = String::new();
+			let mut style = Style::default();
+			for cell in &row[..len] {
+				if cell.ch == CONTINUATION {
+					continue;
+				}
+				if cell.style != style {
+					if cell.style.link != style.link {
+						line.push_str(&cell.style.link.map_or_else(|| Link::end().to_string(), |l| l.start()));
+					}
+					line.push_str(&cell.style.sgr());
+					style = cell.style;
+				}
+				line.push(cell.ch);
+			}
+			if style.link.is_some() {
+				line.push_str(Link::end());
+			}
+			if style != Style::default() {
+				line.push_str("\x1b[0m");
+			}
+			lines.push(line);
+		}
+		lines.join("\n")
+	}
+	/// Identifies what's in the buffer: it's different after any change, and for any other buffer, so
+	/// if it's the same as before then so are the contents. Lets a frame with nothing new be skipped
+	/// without comparing every cell.
+	pub(crate) fn generation(&self) -> (u64, u64) {
+		(self.id, self.changes)
+	}
+	/// Whether anything on line `y` might have changed since the last `mark_clean`.
+	pub(crate) fn is_dirty(&self, y: u16) -> bool {
+		self.dirty.get(y as usize).cloned().unwrap_or(false)
+	}
+	/// Forgets which lines changed, see `is_dirty`.
+	pub(crate) fn mark_clean(&mut self) {
+		self.dirty.iter_mut().for_each(|d| *d = false);
+	}
+	/// A hash of everything on line `y`, so lines can be compared without going through every cell.
+	/// If `y` is outside of the buffer, returns None
+	pub fn line_hash(&self, y: u16) -> Option<u64> {
+		if y >= self.height {
+			return None;
+		}
+		let mut hasher = DefaultHasher::new();
+		self.row(y).hash(&mut hasher);
+		Some(hasher.finish())
+	}
+	/// Internal: The cells on row `y`, which has to be inside of the buffer
+	pub(crate) fn row(&self, y: u16) -> &[Cell] {
+		let start = y as usize * self.width as usize;
+		&self.cells[start..start + self.width as usize]
+	}
+	/// Internal: Converts a position into an index into `cells`
+	fn index(&self, y: u16, x: u16) -> Option<usize> {
+		if y < self.height && x < self.width {
+			Some(y as usize * self.width as usize + x as usize)
+		} else {
+			None
+		}
+	}
+}
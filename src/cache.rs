@@ -0,0 +1,146 @@
+//! On-disk cache for terminal capabilities that are slow to find out.
+//!
+//! Probing the terminal means waiting on a round-trip for each query, so the answers get stored in
+//! `$XDG_CACHE_HOME/rcurses/`, one file per emulator, and reused on the next launch. `Screen` keeps
+//! whether synchronized output (`sync`), the kitty keyboard protocol (`kitty-keyboard`), hyperlinks
+//! (`osc8`), curly underlines (`curly`) and background color queries (`osc11`) are supported, as `yes`
+//! or `no`.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Bump this whenever the meaning of a stored entry changes, so old files get thrown away
+const CACHE_VERSION: u32 = 1;
+// Emulators get updated without changing how they identify themselves, so don't trust old entries forever
+const MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+
+/// Cached capabilities for one terminal emulator.
+#[derive(Debug, Clone)]
+pub struct CapCache {
+	path: Option<PathBuf>,
+	identity: String,
+	entries: HashMap<String, String>,
+	dirty: bool,
+}
+
+impl CapCache {
+	/// Loads the cache for the terminal we're running in.
+	/// If nothing usable is stored, the cache starts out empty.
+	pub fn load() -> CapCache {
+		let identity = identity();
+		let path = cache_dir().map(|dir| dir.join(file_name(&identity)));
+		let mut out = CapCache {
+			path,
+			identity,
+			entries: HashMap::new(),
+			dirty: false,
+		};
+		out.read().unwrap_or(());
+		out
+	}
+	/// The identity of the emulator this cache belongs to.
+	pub fn identity(&self) -> &str {
+		&self.identity
+	}
+	/// Gets a cached value.
+	pub fn get(&self, key: &str) -> Option<&str> {
+		self.entries.get(key).map(|e| &e[..])
+	}
+	/// Every cached key and value, in no particular order.
+	pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.entries.iter().map(|(k, v)| (&k[..], &v[..]))
+	}
+	/// Stores a value, it's written out on the next `save`.
+	pub fn set(&mut self, key: &str, value: &str) {
+		if key.contains('=') || key.contains('\n') || value.contains('\n') {
+			// Can't be stored in the file, so just don't cache it
+			return;
+		}
+		if self.get(key) != Some(value) {
+			self.entries.insert(key.to_string(), value.to_string());
+			self.dirty = true;
+		}
+	}
+	/// Forgets everything, so all capabilities get probed again.
+	pub fn invalidate(&mut self) {
+		self.entries.clear();
+		self.dirty = true;
+	}
+	/// Writes the cache to disk if anything changed.
+	/// If it fails, returns None
+	pub fn save(&mut self) -> Option<()> {
+		if !self.dirty {
+			return Some(());
+		}
+		let path = self.path.clone()?;
+		fs::create_dir_all(path.parent()?).ok()?;
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+		// Write to a temporary file and rename it, so a second instance never reads half a file
+		let tmp = path.with_extension("tmp");
+		{
+			let mut file = File::create(&tmp).ok()?;
+			writeln!(file, "rcurses-caps {}", CACHE_VERSION).ok()?;
+			writeln!(file, "identity {}", self.identity).ok()?;
+			writeln!(file, "time {}", now).ok()?;
+			for (key, value) in &self.entries {
+				writeln!(file, "{}={}", key, value).ok()?;
+			}
+		}
+		fs::rename(&tmp, &path).ok()?;
+		self.dirty = false;
+		Some(())
+	}
+	/// Internal: Reads the entries from disk, leaving them empty if the file is stale
+	fn read(&mut self) -> Option<()> {
+		let file = File::open(self.path.as_ref()?).ok()?;
+		let mut lines = BufReader::new(file).lines();
+
+		if lines.next()?.ok()? != format!("rcurses-caps {}", CACHE_VERSION) {
+			return None;
+		}
+		if lines.next()?.ok()? != format!("identity {}", self.identity) {
+			return None;
+		}
+		let time = lines.next()?.ok()?;
+		let time: u64 = time.trim_start_matches("time ").parse().ok()?;
+		let age = SystemTime::now().duration_since(UNIX_EPOCH + Duration::from_secs(time)).ok()?;
+		if age > MAX_AGE {
+			return None;
+		}
+
+		for line in lines {
+			let line = line.ok()?;
+			if let Some(i) = line.find('=') {
+				self.entries.insert(line[..i].to_string(), line[i + 1..].to_string());
+			}
+		}
+		Some(())
+	}
+}
+
+/// Describes the terminal emulator we're running in, as well as can be told from the environment.
+pub fn identity() -> String {
+	let vars = ["TERM", "TERM_PROGRAM", "TERM_PROGRAM_VERSION", "VTE_VERSION"];
+	let parts: Vec<String> = vars.iter().map(|v| env::var(v).unwrap_or_default()).collect();
+	parts.join(";")
+}
+
+/// Internal: Where the cache files go
+fn cache_dir() -> Option<PathBuf> {
+	let base = match env::var_os("XDG_CACHE_HOME") {
+		Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+		_ => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+	};
+	Some(base.join("rcurses"))
+}
+
+/// Internal: Turns an identity into something that's safe to use as a file name
+fn file_name(identity: &str) -> String {
+	let name: String = identity.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' }).collect();
+	format!("{}.caps", name)
+}
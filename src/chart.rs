@@ -0,0 +1,151 @@
+//! Drawing numbers as pictures: sparklines, gauges, bar charts and line plots, made out of Unicode's
+//! block elements and braille dots. Everything is drawn in the window's current style.
+//!
+//! Where there's a `bounds` argument, it's the values that go at the bottom and the top, or None to fit
+//! them to the data. Values that aren't numbers (NaN) are left out.
+
+use ::layout::Direction;
+use ::rect::Rect;
+use ::text::{self, str_width};
+use ::window::Window;
+
+// Blocks filled from the bottom, in eighths, empty first
+const RISING: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+// Blocks filled from the left, in eighths, empty first
+const GROWING: [char; 9] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+// A cell of braille is 2 dots wide and 4 high. The bit for each dot, by row and then column
+const BRAILLE: u32 = 0x2800;
+const DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+/// The values as a line of blocks of different heights, one character each, like `▁▃▅█▂`.
+pub fn sparkline(values: &[f64], bounds: Option<(f64, f64)>) -> String {
+	let (low, high) = bounds.unwrap_or_else(|| fit(values));
+	values.iter().map(|&v| if v.is_nan() { ' ' } else { RISING[(scale(v, low, high) * 8.0).round().max(1.0) as usize] }).collect()
+}
+
+/// Draws a sparkline (see `sparkline`) at (y, x), `width` cells wide. If there are more values than
+/// fit, only the latest ones are drawn.
+pub fn draw_sparkline(win: &mut Window, y: u16, x: u16, width: u16, values: &[f64], bounds: Option<(f64, f64)>) {
+	let values = &values[values.len().saturating_sub(width as usize)..];
+	let bounds = bounds.unwrap_or_else(|| fit(values));
+	win.print(y, x, &sparkline(values, Some(bounds)));
+}
+
+/// Draws a bar across `rect` that's filled `ratio` of the way (from 0 to 1), to the eighth of a cell,
+/// with `label` in the middle of it. E.g. for progress, or how full a disk is.
+pub fn gauge(win: &mut Window, rect: Rect, ratio: f64, label: &str) {
+	let ratio = if ratio.is_nan() { 0.0 } else { ratio.clamp(0.0, 1.0) };
+	let line = blocks(ratio * rect.width as f64, rect.width);
+	for y in rect.y..rect.bottom() {
+		win.print(y, rect.x, &line);
+	}
+	let label = text::truncate(label, rect.width);
+	let x = rect.x + text::align_offset(str_width(&label), rect.width, text::Align::Center);
+	win.print(rect.y + rect.height / 2, x, &label);
+}
+
+/// Draws a bar for each of `bars` (a label and a value) in `rect`, from zero up to the biggest value
+/// (or `max`, if it's set).
+///
+/// Vertical bars stand side by side with their labels under them, each as wide as the widest label.
+/// Horizontal ones are stacked a row each, with their labels on the left.
+pub fn bar_chart(win: &mut Window, rect: Rect, bars: &[(&str, f64)], direction: Direction, max: Option<f64>) {
+	let max = max.unwrap_or_else(|| bars.iter().map(|b| b.1).filter(|v| !v.is_nan()).fold(0.0, f64::max));
+	let ratio = |v: f64| if v.is_nan() { 0.0 } else { scale(v, 0.0, max) };
+	match direction {
+		Direction::Horizontal => {
+			let label_w = (bars.iter().map(|b| str_width(b.0)).max().unwrap_or(0) as u16).min(rect.width / 2);
+			let bar_x = rect.x + label_w + if label_w > 0 { 1 } else { 0 };
+			let bar_w = rect.right().saturating_sub(bar_x);
+			for (&(label, value), y) in bars.iter().zip(rect.y..rect.bottom()) {
+				win.print(y, rect.x, &text::truncate(label, label_w));
+				win.print(y, bar_x, &blocks(ratio(value) * bar_w as f64, bar_w));
+			}
+		},
+		Direction::Vertical => {
+			let bar_w = bars.iter().map(|b| str_width(b.0)).max().unwrap_or(0).max(1) as u16;
+			let bar_h = rect.height.saturating_sub(1);
+			let xs = (rect.x..rect.right()).step_by(bar_w as usize + 1);
+			for (&(label, value), x) in bars.iter().zip(xs) {
+				let width = bar_w.min(rect.right() - x);
+				// In eighths of a cell
+				let mut left = (ratio(value) * bar_h as f64 * 8.0).round() as usize;
+				for y in (rect.y..rect.y + bar_h).rev() {
+					let piece = RISING[left.min(8)];
+					left = left.saturating_sub(8);
+					win.print(y, x, &piece.to_string().repeat(width as usize));
+				}
+				win.print(rect.y + bar_h, x, &text::truncate(label, width));
+			}
+		},
+	}
+}
+
+/// Plots the values as a line across `rect` in braille dots, two values to a cell, so there's four
+/// times as much detail up and down as with blocks. If there are more values than fit, only the
+/// latest ones are plotted.
+pub fn line_plot(win: &mut Window, rect: Rect, values: &[f64], bounds: Option<(f64, f64)>) {
+	let (cols, rows) = (rect.width as usize * 2, rect.height as usize * 4);
+	if cols == 0 || rows == 0 {
+		return;
+	}
+	let values = &values[values.len().saturating_sub(cols)..];
+	let (low, high) = bounds.unwrap_or_else(|| fit(values));
+	let mut dots = vec![0u32; rect.width as usize * rect.height as usize];
+	let mut set = |x: usize, y: usize| dots[y / 4 * rect.width as usize + x / 2] |= DOTS[y % 4][x % 2];
+	let row = |v: f64| ((1.0 - scale(v, low, high)) * (rows - 1) as f64).round() as usize;
+	let mut last: Option<(usize, usize)> = None;
+	for (x, &value) in values.iter().enumerate() {
+		if value.is_nan() {
+			last = None;
+			continue;
+		}
+		let y = row(value);
+		// Join it up with the one before, the half of the way nearer to each in its column
+		match last {
+			Some((prev_x, prev)) => {
+				let middle = (prev + y) / 2;
+				for dy in prev.min(y)..=prev.max(y) {
+					let nearer_prev = if prev <= y { dy <= middle } else { dy > middle };
+					set(if nearer_prev { prev_x } else { x }, dy);
+				}
+			},
+			None => set(x, y),
+		}
+		last = Some((x, y));
+	}
+	for y in 0..rect.height {
+		let line: String = (0..rect.width).map(|x| {
+			let bits = dots[y as usize * rect.width as usize + x as usize];
+			if bits == 0 { ' ' } else { ::std::char::from_u32(BRAILLE + bits).unwrap_or(' ') }
+		}).collect();
+		win.print(rect.y + y, rect.x, &line);
+	}
+}
+
+/// Internal: Full blocks for `cells` cells (fractions included), then blanks up to `width`
+fn blocks(cells: f64, width: u16) -> String {
+	let eighths = (cells * 8.0).round() as usize;
+	(0..width as usize).map(|i| GROWING[eighths.saturating_sub(i * 8).min(8)]).collect()
+}
+
+/// Internal: The lowest and highest values, with some room if they're all the same
+fn fit(values: &[f64]) -> (f64, f64) {
+	let numbers = values.iter().cloned().filter(|v| !v.is_nan());
+	let (low, high) = numbers.fold((f64::INFINITY, f64::NEG_INFINITY), |(l, h), v| (l.min(v), h.max(v)));
+	if low > high {
+		(0.0, 1.0)
+	} else if low == high {
+		(low - 1.0, high)
+	} else {
+		(low, high)
+	}
+}
+
+/// Internal: Where `value` is between `low` and `high`, from 0 to 1
+fn scale(value: f64, low: f64, high: f64) -> f64 {
+	if high <= low {
+		return 0.0;
+	}
+	((value - low) / (high - low)).clamp(0.0, 1.0)
+}
@@ -0,0 +1,118 @@
+//! The user's clipboard, through the terminal (OSC 52).
+//!
+//! The terminal does the copying, so this works over SSH too, where there's no other way of reaching
+//! the clipboard of the machine the user is sitting at.
+
+use ::quirks::Multiplexer;
+use ::screen::Screen;
+
+// Key in the capability cache for whether the terminal handles OSC 52
+const CAP_KEY: &str = "osc52";
+
+impl Screen {
+	/// Puts `text` on the user's clipboard.
+	/// If the terminal isn't known to support it (see `set_clipboard_support`), returns None
+	///
+	/// NOTE: There's no way of telling whether it worked. Some terminals also cap how much they accept,
+	/// often at around 100KB.
+	pub fn copy_to_clipboard(&mut self, text: &str) -> Option<()> {
+		if !self.supports_clipboard() {
+			return None;
+		}
+		let seq = format!("\x1b]52;c;{}\x07", encode(text.as_bytes()));
+		// tmux sets the clipboard itself, GNU screen has to pass it on
+		match self.quirks().multiplexer {
+			Multiplexer::Screen => self.write_passthrough(&seq),
+			_ => self.write_raw(&seq),
+		}
+		self.flush();
+		Some(())
+	}
+	/// Asks the terminal for what's on the clipboard.
+	/// If the terminal doesn't support it, or doesn't answer (many refuse for security), returns None
+	pub fn request_clipboard(&mut self) -> Option<String> {
+		// GNU screen has no way of passing the answer back
+		if !self.supports_clipboard() || self.quirks().multiplexer == Multiplexer::Screen {
+			return None;
+		}
+		let data = self.query("\x1b]52;c;?\x07", find_reply)?;
+		String::from_utf8(decode(&data)?).ok()
+	}
+	/// Whether the terminal is thought to support the clipboard.
+	///
+	/// Terminals can't be asked, so unless `set_clipboard_support` was called this is a guess from the
+	/// environment, based on which terminals (and multiplexers, see `Screen::quirks`) are known to support it.
+	pub fn supports_clipboard(&mut self) -> bool {
+		match self.caps().get(CAP_KEY) {
+			Some(value) => value == "yes",
+			None => guess_support() && self.quirks().clipboard,
+		}
+	}
+	/// Overrides whether the terminal supports the clipboard. This is remembered for the terminal
+	/// across runs, in the capability cache.
+	pub fn set_clipboard_support(&mut self, supported: bool) {
+		self.caps().set(CAP_KEY, if supported { "yes" } else { "no" });
+	}
+}
+
+/// Internal: Whether the environment looks like a terminal that's known to support OSC 52
+fn guess_support() -> bool {
+	if ::std::env::var_os("TMUX").is_some() {
+		return true;
+	}
+	let env = format!("{} {}",
+		::std::env::var("TERM").unwrap_or_default(),
+		::std::env::var("TERM_PROGRAM").unwrap_or_default(),
+	).to_lowercase();
+	["kitty", "alacritty", "foot", "wezterm", "iterm", "contour", "ghostty", "tmux"].iter().any(|t| env.contains(t))
+}
+
+/// Internal: Finds the answer to a clipboard request (`ESC ] 52 ; c ; data` ended by BEL or `ESC \`)
+/// Returns where it starts and ends, and the (still base64) data
+fn find_reply(buf: &[u8]) -> Option<(usize, usize, Vec<u8>)> {
+	let start = buf.windows(5).position(|w| w == b"\x1b]52;")?;
+	let rest = &buf[start + 5..];
+	let data_start = rest.iter().position(|&b| b == b';')? + 1;
+	let (len, term) = match rest.iter().position(|&b| b == 7 || b == 0x1b)? {
+		i if rest[i] == 7 => (i, 1),
+		i if rest.get(i + 1) == Some(&b'\\') => (i, 2),
+		_ => return None,
+	};
+	let data = rest.get(data_start..len)?.to_vec();
+	Some((start, start + 5 + len + term, data))
+}
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Internal: Base64 encodes `bytes`
+fn encode(bytes: &[u8]) -> String {
+	let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+	for chunk in bytes.chunks(3) {
+		let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+		for i in 0..4 {
+			if i <= chunk.len() {
+				out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+			} else {
+				out.push('=');
+			}
+		}
+	}
+	out
+}
+
+/// Internal: Base64 decodes `text`.
+/// If it isn't valid base64, returns None
+fn decode(text: &[u8]) -> Option<Vec<u8>> {
+	let mut out = Vec::with_capacity(text.len() / 4 * 3);
+	let (mut n, mut bits) = (0u32, 0);
+	for &b in text.iter().filter(|&&b| b != b'=') {
+		let v = ALPHABET.iter().position(|&a| a == b)? as u32;
+		n = (n << 6 | v) & 0xffff;
+		bits += 6;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((n >> bits) as u8);
+		}
+	}
+	Some(out)
+}
@@ -0,0 +1,136 @@
+//! Gathering up what's useful in a bug report about an app, see `bundle`.
+
+use std::ascii;
+use std::env;
+use std::fmt::Write;
+use ::event::{Event, Key, Modifiers};
+use ::screen::Screen;
+
+// The environment variables that say what the locale is
+const LOCALE_VARS: [&str; 3] = ["LC_ALL", "LC_CTYPE", "LANG"];
+
+/// Describes the terminal, what rcurses found out about it, the locale, the last few events that were
+/// read (and input that wasn't understood, if it's being logged) and what's on the screen, as one
+/// block of text that users can paste into a bug report.
+///
+/// Text is redacted, so nothing the user typed or had on screen gets out: letters turn into `x` and
+/// digits into `0`, while punctuation, spacing and line drawing stay so the layout can still be seen.
+/// Keys typed with Ctrl or Alt are kept as they are, since they're shortcuts rather than text.
+pub fn bundle(screen: &Screen) -> String {
+	let mut out = String::new();
+	let (height, width) = screen.terminal_size();
+	let quirks = screen.quirks();
+	let var = |name: &str| env::var(name).unwrap_or_default();
+
+	writeln!(out, "[rcurses]").unwrap_or(());
+	writeln!(out, "version = {}", env!("CARGO_PKG_VERSION")).unwrap_or(());
+
+	writeln!(out, "\n[terminal]").unwrap_or(());
+	writeln!(out, "identity = {}", screen.cap_cache().identity()).unwrap_or(());
+	writeln!(out, "terminfo = {}", var("TERM")).unwrap_or(());
+	writeln!(out, "multiplexer = {:?}", quirks.multiplexer).unwrap_or(());
+	writeln!(out, "size = {}x{}", width, height).unwrap_or(());
+
+	writeln!(out, "\n[capabilities]").unwrap_or(());
+	writeln!(out, "color_depth = {:?}", screen.color_depth()).unwrap_or(());
+	writeln!(out, "links = {}", screen.supports_links()).unwrap_or(());
+	writeln!(out, "curly_underline = {}", screen.supports_curly_underline()).unwrap_or(());
+	writeln!(out, "line_drawing = {:?}", screen.line_drawing()).unwrap_or(());
+	writeln!(out, "keyboard_protocol = {:?}", screen.keyboard_protocol()).unwrap_or(());
+	writeln!(out, "quirks = {:?}", quirks).unwrap_or(());
+	let mut cached: Vec<(&str, &str)> = screen.cap_cache().entries().collect();
+	cached.sort();
+	for (key, value) in cached {
+		writeln!(out, "cached.{} = {}", key, value).unwrap_or(());
+	}
+
+	if let Some(stats) = screen.latency() {
+		writeln!(out, "\n[latency]").unwrap_or(());
+		writeln!(out, "{}", stats.summary()).unwrap_or(());
+	}
+
+	writeln!(out, "\n[locale]").unwrap_or(());
+	for name in LOCALE_VARS.iter() {
+		writeln!(out, "{} = {}", name, var(name)).unwrap_or(());
+	}
+
+	writeln!(out, "\n[events]").unwrap_or(());
+	for event in screen.recent_events() {
+		writeln!(out, "{:?}", redact_event(event.clone())).unwrap_or(());
+	}
+
+	let unknown: Vec<&[u8]> = screen.unknown_input().collect();
+	if !unknown.is_empty() {
+		writeln!(out, "\n[unknown input]").unwrap_or(());
+		for bytes in unknown {
+			let escaped: String = redact_bytes(bytes).into_iter().flat_map(ascii::escape_default).map(char::from).collect();
+			writeln!(out, "{}", escaped).unwrap_or(());
+		}
+	}
+
+	writeln!(out, "\n[frame]").unwrap_or(());
+	for line in screen.snapshot().lines() {
+		writeln!(out, "{}", redact(line)).unwrap_or(());
+	}
+	out
+}
+
+/// Blanks out the letters and digits in `text`, the way `bundle` does.
+pub fn redact(text: &str) -> String {
+	text.chars().map(redact_char).collect()
+}
+
+/// Internal: A letter as `x`, a digit as `0`, and anything else as it is
+fn redact_char(c: char) -> char {
+	if c.is_numeric() {
+		'0'
+	} else if c.is_alphabetic() {
+		'x'
+	} else {
+		c
+	}
+}
+
+/// Internal: `bytes` with letters and digits blanked out like `redact_char` (and anything past ASCII,
+/// which is part of some character), except for what tells escape sequences apart: the ESC, what comes
+/// right after it in a CSI or SS3 sequence, and the final byte. Parameters keep their punctuation
+fn redact_bytes(bytes: &[u8]) -> Vec<u8> {
+	let redact = |b: u8| match b {
+		b'0'..=b'9' => b'0',
+		b'a'..=b'z' | b'A'..=b'Z' | 0x80..=0xff => b'x',
+		_ => b,
+	};
+	// 0 is text, 1 is right after an ESC, 2 is inside a CSI sequence and 3 is before the key in SS3
+	let mut state = 0;
+	bytes.iter().map(|&b| {
+		let (out, next) = match (state, b) {
+			(_, 0x1b) => (b, 1),
+			(1, b'[') => (b, 2),
+			(1, b'O') => (b, 3),
+			// Anything else after an ESC is a key typed with Alt
+			(1, _) | (0, _) => (redact(b), 0),
+			(2, 0x40..=0x7e) | (3, _) => (b, 0),
+			_ => (redact(b), state),
+		};
+		state = next;
+		out
+	}).collect()
+}
+
+/// Internal: `event` with the character typed blanked out, if it was text
+fn redact_event(event: Event) -> Event {
+	match event {
+		Event::Key(Key::Char(c)) => Event::Key(Key::Char(redact_char(c))),
+		Event::Keyboard(mut key) => {
+			if let Key::Char(c) = key.key {
+				// Shifted is still text
+				if key.mods | Modifiers::SHIFT == Modifiers::SHIFT {
+					key.key = Key::Char(redact_char(c));
+				}
+			}
+			Event::Keyboard(key)
+		},
+		Event::Unknown(bytes) => Event::Unknown(redact_bytes(&bytes)),
+		_ => event,
+	}
+}
@@ -0,0 +1,239 @@
+use std::ops::{BitOr, BitOrAssign};
+use ::rect::Rect;
+use ::window::Window;
+
+/// Something that happened on the terminal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+	/// A key was pressed
+	Key(Key),
+	/// A key event with everything the terminal said about it. Only sent while the kitty keyboard protocol
+	/// is on (see `Screen::set_keyboard_protocol`), for repeats and releases (with `KeyboardFlags::EVENT_TYPES`)
+	/// and for modifiers that `Key` can't hold, e.g. Ctrl+Up. Everything else still comes as a `Key`
+	Keyboard(KeyEvent),
+	/// The mouse was used, only sent while mouse reporting is on (see `Screen::set_mouse`)
+	Mouse(MouseEvent),
+	/// The mouse was used inside of a region registered with `Window::register_clickable`
+	Region {
+		/// The id the region was registered with
+		id: usize,
+		/// What happened inside of it
+		kind: RegionKind,
+	},
+	/// A timer set with `Screen::set_timer` went off, with the timer's id
+	Tick(usize),
+	/// The terminal changed size, to `height` rows and `width` columns (of the logical grid, if one is set)
+	Resize {
+		height: u16,
+		width: u16,
+	},
+	/// Ctrl-C was pressed (or the process got SIGINT some other way), with `SignalPolicy::Interrupt`
+	Interrupt,
+	/// The terminal got focus back, only sent while focus reporting is on (see `Screen::set_focus_reporting`)
+	FocusGained,
+	/// The terminal lost focus, e.g. the user switched to another window
+	FocusLost,
+	/// Events came in faster than they were read, and `dropped` of them had to be thrown away
+	/// (see `Screen::set_event_queue`)
+	Overflow {
+		dropped: usize,
+	},
+	/// Input that didn't make sense as any key or report, only sent in strict mode (see
+	/// `Screen::set_strict_input`). Otherwise it's dropped
+	Unknown(Vec<u8>),
+}
+impl From<Key> for Event {
+	fn from(key: Key) -> Event {
+		Event::Key(key)
+	}
+}
+
+/// A key on the keyboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Key {
+	/// A normal character
+	Char(char),
+	/// A character typed while holding ctrl, always lowercase
+	Ctrl(char),
+	/// A character typed while holding alt
+	Alt(char),
+	Enter,
+	Tab,
+	/// Shift+tab
+	BackTab,
+	Backspace,
+	Esc,
+	Up,
+	Down,
+	Left,
+	Right,
+	Home,
+	End,
+	PageUp,
+	PageDown,
+	Insert,
+	Delete,
+	/// A function key, F1 is `F(1)`
+	F(u8),
+}
+
+/// A key with the modifiers that were held, see `Event::Keyboard`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+	/// The key, never `Ctrl` or `Alt` since those are in `mods`. Characters are the ones on the key
+	/// without shift, unless the terminal said what shift made of it
+	pub key: Key,
+	pub mods: Modifiers,
+	pub kind: KeyKind,
+}
+
+/// A set of modifier keys, combined with `|`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+impl Modifiers {
+	pub const NONE: Modifiers = Modifiers(0);
+	pub const SHIFT: Modifiers = Modifiers(1);
+	pub const ALT: Modifiers = Modifiers(1 << 1);
+	pub const CTRL: Modifiers = Modifiers(1 << 2);
+	pub const SUPER: Modifiers = Modifiers(1 << 3);
+	pub const HYPER: Modifiers = Modifiers(1 << 4);
+	pub const META: Modifiers = Modifiers(1 << 5);
+
+	/// Makes a set from the bits the kitty keyboard protocol uses, which are the same as the constants.
+	/// Caps Lock and Num Lock are left out.
+	pub fn from_bits(bits: u8) -> Modifiers {
+		Modifiers(bits & 0b11_1111)
+	}
+	/// Whether every modifier in `other` is held.
+	pub fn contains(&self, other: Modifiers) -> bool {
+		self.0 & other.0 == other.0
+	}
+	/// Whether no modifiers are held.
+	pub fn is_empty(&self) -> bool {
+		self.0 == 0
+	}
+}
+impl BitOr for Modifiers {
+	type Output = Modifiers;
+	fn bitor(self, other: Modifiers) -> Modifiers {
+		Modifiers(self.0 | other.0)
+	}
+}
+impl BitOrAssign for Modifiers {
+	fn bitor_assign(&mut self, other: Modifiers) {
+		self.0 |= other.0;
+	}
+}
+
+/// What happened to a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyKind {
+	Press,
+	/// Held down long enough that it's being repeated
+	Repeat,
+	Release,
+}
+
+/// A mouse action at a cell of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+	pub kind: MouseKind,
+	/// The line of the screen it happened on
+	pub y: u16,
+	/// The column of the screen it happened on
+	pub x: u16,
+}
+
+/// The possible mouse actions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseKind {
+	Press(MouseButton),
+	Release(MouseButton),
+	/// The mouse moved while the button was held down
+	Drag(MouseButton),
+	/// The mouse moved with no buttons held down
+	Move,
+	ScrollUp,
+	ScrollDown,
+	/// Pressed again at the same place soon after the last press, right after the second `Press`.
+	/// Only sent while gestures are on, like the rest below (see `Screen::set_gestures`)
+	DoubleClick(MouseButton),
+	/// The mouse moved far enough with the button held down to be a drag, at where the button was pressed
+	DragStart(MouseButton),
+	/// The mouse moved during a drag, instead of `Drag`
+	DragMove(MouseButton),
+	/// The button was let go of at the end of a drag, instead of `Release`
+	DragEnd(MouseButton),
+	/// The wheel turned, by this many notches after speeding up, negative is up. Instead of `ScrollUp` and `ScrollDown`
+	Scroll(i16),
+}
+
+/// The buttons of the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+	Left,
+	Middle,
+	Right,
+}
+
+/// What happened inside of a clickable region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+	/// It was clicked
+	Click(MouseButton),
+	/// The mouse moved into it, needs `MouseMode::Motion`
+	Hover,
+}
+
+/// Keeps track of the clickable regions that are on screen, see `Window::register_clickable`.
+#[derive(Debug, Default, Clone)]
+pub struct Regions {
+	/// In screen coordinates, the last one is on top
+	list: Vec<(Rect, usize)>,
+	/// The region the mouse is currently over
+	hovered: Option<usize>,
+}
+
+impl Regions {
+	pub fn new() -> Regions {
+		Regions::default()
+	}
+	/// Adds the regions of a window that was just drawn.
+	pub fn add_window(&mut self, win: &Window) {
+		// The window covers up the regions that were under it, and cuts the ones it overlaps down to
+		// what can still be seen
+		let area = win.rect();
+		self.list = self.list.iter().flat_map(|&(r, id)| r.subtract(&area).into_iter().map(move |r| (r, id))).collect();
+		for &(r, id) in win.regions() {
+			self.list.push((r.offset(win.get_y(), win.get_x()), id));
+		}
+	}
+	/// Removes every region.
+	pub fn clear(&mut self) {
+		self.list.clear();
+		self.hovered = None;
+	}
+	/// The id of the top-most region at (y, x).
+	pub fn at(&self, y: u16, x: u16) -> Option<usize> {
+		self.list.iter().rev().find(|&&(r, _)| r.contains(y, x)).map(|&(_, id)| id)
+	}
+	/// Turns mouse events inside of regions into `Event::Region`s, anything else is returned as-is.
+	pub fn check(&mut self, event: Event) -> Event {
+		let mouse = match event {
+			Event::Mouse(mouse) => mouse,
+			_ => return event,
+		};
+		let id = self.at(mouse.y, mouse.x);
+		match (mouse.kind, id) {
+			(MouseKind::Press(button), Some(id)) => Event::Region { id, kind: RegionKind::Click(button) },
+			(MouseKind::Move, _) if id != self.hovered => {
+				self.hovered = id;
+				match id {
+					Some(id) => Event::Region { id, kind: RegionKind::Hover },
+					None => event,
+				}
+			},
+			_ => event,
+		}
+	}
+}
@@ -0,0 +1,80 @@
+//! Looking at events before the app gets them, e.g. for global shortcuts.
+
+use std::fmt;
+use ::event::{Event, Key};
+
+/// Identifies a filter so it can be removed again, see `Filters::add`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FilterId(usize);
+
+/// A function that looks at an event, see `Filters`.
+pub type Filter = Box<dyn FnMut(Event) -> Option<Event>>;
+
+/// A chain of functions every event goes through before `Screen::read_event` returns it.
+///
+/// Each filter gets the event and either passes it on (maybe changed) by returning it, or consumes
+/// it by returning None, in which case the filters after it never see it and neither does the app.
+pub struct Filters {
+	/// Sorted by order, then by when they were added
+	list: Vec<(FilterId, i32, Filter)>,
+	next_id: usize,
+}
+
+impl Filters {
+	pub fn new() -> Filters {
+		Filters {
+			list: Vec::new(),
+			next_id: 0,
+		}
+	}
+	/// Adds a filter. Filters with a lower `order` run first, ones with the same order run in the order
+	/// they were added.
+	pub fn add<F: FnMut(Event) -> Option<Event> + 'static>(&mut self, order: i32, filter: F) -> FilterId {
+		let id = FilterId(self.next_id);
+		self.next_id += 1;
+		let at = self.list.iter().position(|&(_, o, _)| o > order).unwrap_or(self.list.len());
+		self.list.insert(at, (id, order, Box::new(filter)));
+		id
+	}
+	/// Adds a filter that calls `action` and consumes the event whenever `key` is pressed.
+	pub fn add_shortcut<F: FnMut() + 'static>(&mut self, order: i32, key: Key, mut action: F) -> FilterId {
+		self.add(order, move |event| match event {
+			Event::Key(k) if k == key => {
+				action();
+				None
+			},
+			_ => Some(event),
+		})
+	}
+	/// Removes a filter.
+	/// Returns whether there was one with that id
+	pub fn remove(&mut self, id: FilterId) -> bool {
+		let len = self.list.len();
+		self.list.retain(|&(i, _, _)| i != id);
+		self.list.len() != len
+	}
+	/// Removes every filter.
+	pub fn clear(&mut self) {
+		self.list.clear();
+	}
+	pub fn is_empty(&self) -> bool {
+		self.list.is_empty()
+	}
+	/// Runs the event through every filter.
+	/// Returns what's left of it, or None if a filter consumed it
+	pub fn run(&mut self, event: Event) -> Option<Event> {
+		self.list.iter_mut().try_fold(event, |event, &mut (_, _, ref mut filter)| filter(event))
+	}
+}
+
+impl Default for Filters {
+	fn default() -> Filters {
+		Filters::new()
+	}
+}
+
+impl fmt::Debug for Filters {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Filters({})", self.list.len())
+	}
+}
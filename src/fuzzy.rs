@@ -0,0 +1,168 @@
+//! Fuzzy matching, for filtering lists as the user types.
+//!
+//! A pattern matches anything that contains its characters in order, e.g. "fb" matches "FooBar".
+//! Matching ignores case unless the pattern has an uppercase character in it.
+
+// What each matched character scores, and the bonuses/penalties on top of that
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 12;
+const BOUNDARY: i64 = 10;
+const CAMEL: i64 = 8;
+const GAP: i64 = 1;
+// Can't be beaten by any real score, and doesn't overflow when added to
+const NONE: i64 = i64::MIN / 2;
+
+/// A successful match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+	/// Higher is better
+	pub score: i64,
+	/// Indices of the characters (not bytes) of the candidate that matched, for highlighting
+	pub positions: Vec<usize>,
+}
+
+/// Matches `pattern` against `candidate`, finding the best scoring way to do it.
+/// If it doesn't match, returns None
+///
+/// Matches score higher when they're consecutive, or start at word boundaries (after a space, `_`, `-`,
+/// `/` or `.`, or a lowercase to uppercase change), and lower the more gaps they have.
+pub fn score(pattern: &str, candidate: &str) -> Option<Match> {
+	let pat: Vec<char> = pattern.chars().collect();
+	let cand: Vec<char> = candidate.chars().collect();
+	if pat.is_empty() {
+		return Some(Match { score: 0, positions: Vec::new() });
+	}
+	let exact_case = pat.iter().any(|c| c.is_uppercase());
+	let eq = |p: char, c: char| if exact_case { p == c } else { p.to_lowercase().eq(c.to_lowercase()) };
+	// Rule out most candidates before doing the real work
+	let mut rest = cand.iter();
+	if !pat.iter().all(|&p| rest.any(|&c| eq(p, c))) {
+		return None;
+	}
+
+	let (m, n) = (pat.len(), cand.len());
+	// best[i * n + j] is the best score for pat[..=i] with pat[i] matched to cand[j], and from[..] is
+	// where pat[i - 1] was matched for it
+	let mut best = vec![NONE; m * n];
+	let mut from = vec![0; m * n];
+	for i in 0..m {
+		// The best of best[i - 1][k] + GAP * k for every k far enough back to leave a gap
+		let mut run = (NONE, 0);
+		for j in 0..n {
+			if i > 0 && j >= 2 {
+				let k = j - 2;
+				let s = best[(i - 1) * n + k];
+				if s > NONE && s + GAP * k as i64 > run.0 {
+					run = (s + GAP * k as i64, k);
+				}
+			}
+			if !eq(pat[i], cand[j]) {
+				continue;
+			}
+			let here = MATCH + bonus(&cand, j);
+			if i == 0 {
+				best[j] = here - GAP * j as i64;
+				continue;
+			}
+			if j == 0 {
+				continue;
+			}
+			let adjacent = best[(i - 1) * n + j - 1];
+			let adjacent = if adjacent > NONE { adjacent + CONSECUTIVE } else { NONE };
+			let gapped = if run.0 > NONE { run.0 - GAP * (j - 1) as i64 } else { NONE };
+			let (prev, k) = if adjacent >= gapped { (adjacent, j - 1) } else { (gapped, run.1) };
+			if prev > NONE {
+				best[i * n + j] = prev + here;
+				from[i * n + j] = k;
+			}
+		}
+	}
+
+	let last = (m - 1) * n;
+	let (mut j, &score) = best[last..last + n].iter().enumerate().max_by_key(|&(j, s)| (*s, ::std::cmp::Reverse(j)))?;
+	if score <= NONE {
+		return None;
+	}
+	let mut positions = vec![0; m];
+	for i in (0..m).rev() {
+		positions[i] = j;
+		j = from[i * n + j];
+	}
+	Some(Match { score, positions })
+}
+
+/// Internal: The bonus for matching the character at `j`
+fn bonus(cand: &[char], j: usize) -> i64 {
+	if j == 0 {
+		return BOUNDARY;
+	}
+	let (prev, cur) = (cand[j - 1], cand[j]);
+	match prev {
+		' ' | '_' | '-' | '/' | '.' => BOUNDARY,
+		_ if prev.is_lowercase() && cur.is_uppercase() => CAMEL,
+		_ => 0,
+	}
+}
+
+/// Filters a list of items by a pattern, best matches first.
+///
+/// When the pattern only grows (the user typing more), only the items that matched before are checked again.
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+	items: Vec<String>,
+	pattern: String,
+	/// Indices into `items` and how they matched, sorted
+	matches: Vec<(usize, Match)>,
+}
+
+impl Matcher {
+	pub fn new(items: Vec<String>) -> Matcher {
+		let mut matcher = Matcher {
+			items,
+			pattern: String::new(),
+			matches: Vec::new(),
+		};
+		matcher.rematch(None);
+		matcher
+	}
+	/// Replaces the items, matching them against the current pattern.
+	pub fn set_items(&mut self, items: Vec<String>) {
+		self.items = items;
+		self.rematch(None);
+	}
+	/// Changes the pattern and updates the matches.
+	pub fn set_pattern(&mut self, pattern: &str) {
+		if pattern == self.pattern {
+			return;
+		}
+		// Anything matching the longer pattern also matched the shorter one
+		let narrowing = !self.pattern.is_empty() && pattern.starts_with(&self.pattern[..]);
+		self.pattern = pattern.to_string();
+		if narrowing {
+			let old: Vec<usize> = self.matches.iter().map(|&(i, _)| i).collect();
+			self.rematch(Some(old));
+		} else {
+			self.rematch(None);
+		}
+	}
+	pub fn pattern(&self) -> &str {
+		&self.pattern
+	}
+	pub fn items(&self) -> &[String] {
+		&self.items
+	}
+	/// The matching items as indices into `items` along with how they matched, best first.
+	/// Equally good matches keep their order from `items`.
+	pub fn matches(&self) -> &[(usize, Match)] {
+		&self.matches
+	}
+	/// Internal: Matches the items at `indices` (or all of them) against the pattern
+	fn rematch(&mut self, indices: Option<Vec<usize>>) {
+		let indices = indices.unwrap_or_else(|| (0..self.items.len()).collect());
+		let mut matches: Vec<(usize, Match)> = indices.into_iter()
+			.filter_map(|i| score(&self.pattern, &self.items[i]).map(|m| (i, m)))
+			.collect();
+		matches.sort_by(|a, b| b.1.score.cmp(&a.1.score).then(a.0.cmp(&b.0)));
+		self.matches = matches;
+	}
+}
@@ -0,0 +1,204 @@
+//! Turning raw mouse reports into gestures: double clicks, drags and an accelerating wheel, see
+//! `Screen::set_gestures`.
+
+use std::time::{Duration, Instant};
+use ::event::{MouseButton, MouseEvent, MouseKind};
+
+// How many notches in a row it takes for each one to count for one more
+const WHEEL_STEP: u16 = 3;
+
+/// The thresholds gestures are recognized by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GestureConfig {
+	/// How long apart two presses can be and still make a double click
+	pub double_click: Duration,
+	/// How many cells the mouse has to move with a button held down before it's a drag, so a shaky click
+	/// is still a click
+	pub drag_distance: u16,
+	/// How long apart turns of the wheel can be and still speed it up
+	pub wheel_window: Duration,
+	/// The most notches a turn of the wheel can count for, 1 turns acceleration off
+	pub wheel_max: u16,
+}
+
+impl Default for GestureConfig {
+	fn default() -> GestureConfig {
+		GestureConfig {
+			double_click: Duration::from_millis(400),
+			drag_distance: 1,
+			wheel_window: Duration::from_millis(80),
+			wheel_max: 5,
+		}
+	}
+}
+
+/// Recognizes gestures in a stream of mouse events.
+///
+/// Presses and releases go through as they are, with a `DoubleClick` after the press that makes one.
+/// Moving with a button held down turns into a `DragStart` (at where the button was pressed) once it's
+/// gone far enough, then `DragMove`s, and a `DragEnd` instead of the release. Moves that don't go far
+/// enough are dropped. Turns of the wheel turn into `Scroll`s.
+#[derive(Debug, Clone)]
+pub struct Gestures {
+	config: GestureConfig,
+	/// The last press that could be the first half of a double click
+	last_click: Option<(Instant, MouseButton, (u16, u16))>,
+	/// The button that's held down and where it was pressed
+	pressed: Option<(MouseButton, (u16, u16))>,
+	dragging: bool,
+	/// When the wheel last turned, which way (true is up), and how many turns in a row that was
+	wheel: Option<(Instant, bool, u16)>,
+}
+
+impl Gestures {
+	pub fn new(config: GestureConfig) -> Gestures {
+		Gestures {
+			config,
+			last_click: None,
+			pressed: None,
+			dragging: false,
+			wheel: None,
+		}
+	}
+	pub fn config(&self) -> GestureConfig {
+		self.config
+	}
+	/// Changes the thresholds, anything that's halfway through being recognized carries on.
+	pub fn set_config(&mut self, config: GestureConfig) {
+		self.config = config;
+	}
+	/// Takes in a mouse event that happened at `now`, and returns what it turns into (which can be
+	/// nothing, or more than one event).
+	pub fn feed(&mut self, mouse: MouseEvent, now: Instant) -> Vec<MouseEvent> {
+		let pos = (mouse.y, mouse.x);
+		let at = |kind: MouseKind, (y, x): (u16, u16)| MouseEvent { kind, y, x };
+		match mouse.kind {
+			MouseKind::Press(button) => {
+				self.pressed = Some((button, pos));
+				self.dragging = false;
+				match self.last_click {
+					Some((when, b, p)) if b == button && p == pos && now.duration_since(when) <= self.config.double_click => {
+						// A third press starts over rather than making another double click
+						self.last_click = None;
+						vec![mouse, at(MouseKind::DoubleClick(button), pos)]
+					},
+					_ => {
+						self.last_click = Some((now, button, pos));
+						vec![mouse]
+					},
+				}
+			},
+			MouseKind::Drag(button) => {
+				// If the press came before gestures were turned on, the drag starts here
+				let (origin, missed) = match self.pressed {
+					Some((b, origin)) if b == button => (origin, false),
+					_ => {
+						self.pressed = Some((button, pos));
+						(pos, true)
+					},
+				};
+				if self.dragging {
+					return vec![at(MouseKind::DragMove(button), pos)];
+				}
+				let distance = (pos.0 as i32 - origin.0 as i32).abs().max((pos.1 as i32 - origin.1 as i32).abs());
+				if distance < self.config.drag_distance as i32 && !missed {
+					return Vec::new();
+				}
+				self.dragging = true;
+				self.last_click = None;
+				let mut out = vec![at(MouseKind::DragStart(button), origin)];
+				if origin != pos {
+					out.push(at(MouseKind::DragMove(button), pos));
+				}
+				out
+			},
+			MouseKind::Release(button) => {
+				let pressed = self.pressed.take();
+				if !self.dragging {
+					return vec![mouse];
+				}
+				self.dragging = false;
+				// Some terminals don't say which button was let go of
+				vec![at(MouseKind::DragEnd(pressed.map_or(button, |p| p.0)), pos)]
+			},
+			MouseKind::ScrollUp | MouseKind::ScrollDown => {
+				let up = mouse.kind == MouseKind::ScrollUp;
+				let streak = match self.wheel {
+					Some((when, dir, n)) if dir == up && now.duration_since(when) <= self.config.wheel_window => n.saturating_add(1),
+					_ => 0,
+				};
+				self.wheel = Some((now, up, streak));
+				let notches = (1 + streak / WHEEL_STEP).min(self.config.wheel_max.max(1)) as i16;
+				vec![at(MouseKind::Scroll(if up { -notches } else { notches }), pos)]
+			},
+			_ => vec![mouse],
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn at(kind: MouseKind, y: u16, x: u16) -> MouseEvent {
+		MouseEvent { kind, y, x }
+	}
+	fn kinds(events: Vec<MouseEvent>) -> Vec<MouseKind> {
+		events.into_iter().map(|e| e.kind).collect()
+	}
+
+	#[test]
+	fn double_click() {
+		let (left, right) = (MouseButton::Left, MouseButton::Right);
+		let mut gestures = Gestures::new(GestureConfig::default());
+		let start = Instant::now();
+		let ms = |n: u64| start + Duration::from_millis(n);
+		assert_eq!(kinds(gestures.feed(at(MouseKind::Press(left), 1, 1), ms(0))), vec![MouseKind::Press(left)]);
+		assert_eq!(kinds(gestures.feed(at(MouseKind::Release(left), 1, 1), ms(50))), vec![MouseKind::Release(left)]);
+		assert_eq!(kinds(gestures.feed(at(MouseKind::Press(left), 1, 1), ms(300))), vec![MouseKind::Press(left), MouseKind::DoubleClick(left)]);
+		// A third press starts over
+		assert_eq!(kinds(gestures.feed(at(MouseKind::Press(left), 1, 1), ms(400))), vec![MouseKind::Press(left)]);
+		// Too slow, somewhere else, or another button
+		assert_eq!(kinds(gestures.feed(at(MouseKind::Press(left), 1, 1), ms(1000))), vec![MouseKind::Press(left)]);
+		assert_eq!(kinds(gestures.feed(at(MouseKind::Press(left), 1, 2), ms(1100))), vec![MouseKind::Press(left)]);
+		assert_eq!(kinds(gestures.feed(at(MouseKind::Press(right), 1, 2), ms(1200))), vec![MouseKind::Press(right)]);
+	}
+
+	#[test]
+	fn drag() {
+		let left = MouseButton::Left;
+		let mut gestures = Gestures::new(GestureConfig { drag_distance: 2, ..GestureConfig::default() });
+		let now = Instant::now();
+		gestures.feed(at(MouseKind::Press(left), 5, 5), now);
+		// Not far enough yet
+		assert_eq!(gestures.feed(at(MouseKind::Drag(left), 5, 6), now), vec![]);
+		assert_eq!(gestures.feed(at(MouseKind::Drag(left), 7, 6), now), vec![
+			at(MouseKind::DragStart(left), 5, 5),
+			at(MouseKind::DragMove(left), 7, 6),
+		]);
+		assert_eq!(gestures.feed(at(MouseKind::Drag(left), 7, 7), now), vec![at(MouseKind::DragMove(left), 7, 7)]);
+		assert_eq!(gestures.feed(at(MouseKind::Release(left), 7, 8), now), vec![at(MouseKind::DragEnd(left), 7, 8)]);
+		// A click that wobbled a bit is still a click
+		gestures.feed(at(MouseKind::Press(left), 5, 5), now);
+		assert_eq!(gestures.feed(at(MouseKind::Drag(left), 5, 6), now), vec![]);
+		assert_eq!(kinds(gestures.feed(at(MouseKind::Release(left), 5, 6), now)), vec![MouseKind::Release(left)]);
+		// A drag whose press was missed starts where it was first seen
+		assert_eq!(kinds(gestures.feed(at(MouseKind::Drag(MouseButton::Middle), 1, 1), now)), vec![MouseKind::DragStart(MouseButton::Middle)]);
+	}
+
+	#[test]
+	fn wheel_speeds_up() {
+		let mut gestures = Gestures::new(GestureConfig { wheel_max: 3, ..GestureConfig::default() });
+		let start = Instant::now();
+		let mut notches = Vec::new();
+		for i in 0..12 {
+			let scroll = gestures.feed(at(MouseKind::ScrollDown, 0, 0), start + Duration::from_millis(i * 10));
+			notches.extend(kinds(scroll));
+		}
+		let expected: Vec<MouseKind> = [1, 1, 1, 2, 2, 2, 3, 3, 3, 3, 3, 3].iter().map(|&n| MouseKind::Scroll(n)).collect();
+		assert_eq!(notches, expected);
+		// Turning the other way, or after a pause, starts slow again
+		assert_eq!(kinds(gestures.feed(at(MouseKind::ScrollUp, 0, 0), start + Duration::from_millis(120))), vec![MouseKind::Scroll(-1)]);
+		assert_eq!(kinds(gestures.feed(at(MouseKind::ScrollUp, 0, 0), start + Duration::from_secs(5))), vec![MouseKind::Scroll(-1)]);
+	}
+}
@@ -0,0 +1,72 @@
+//! A helper process that puts the terminal back if we die without cleaning up.
+
+use std::os::unix::io::RawFd;
+use ::termios::{Termios, tcsetattr};
+
+const TCSANOW: i32 = 0;
+
+/// Handle to the helper process, see `Screen::spawn_crash_guard`.
+///
+/// The helper waits on a pipe. If the pipe gets closed without `release` being called first (which is
+/// what happens when we get SIGKILLed or killed by the OOM killer), it restores the terminal itself.
+#[derive(Debug)]
+pub struct CrashGuard {
+	pid: ::libc::pid_t,
+	pipe: RawFd,
+}
+
+impl CrashGuard {
+	/// Forks the helper, which will set the terminal behind `fd` back to `original` and write `restore` to it.
+	/// If the fork fails, returns None
+	pub fn spawn(fd: RawFd, original: Termios, restore: Vec<u8>) -> Option<CrashGuard> {
+		let mut fds = [0; 2];
+		if unsafe { ::libc::pipe2(fds.as_mut_ptr(), ::libc::O_CLOEXEC) } < 0 {
+			return None;
+		}
+		match unsafe { ::libc::fork() } {
+			-1 => {
+				unsafe {
+					::libc::close(fds[0]);
+					::libc::close(fds[1]);
+				}
+				None
+			},
+			0 => {
+				// Only async-signal-safe things from here on, other threads might have been holding locks when we forked
+				unsafe {
+					::libc::close(fds[1]);
+					// Ctrl+C and friends are meant for the app, not for us. Ignoring SIGTTOU lets us
+					// fix the terminal even if we end up in the background.
+					for &sig in &[::libc::SIGINT, ::libc::SIGQUIT, ::libc::SIGTSTP, ::libc::SIGHUP, ::libc::SIGTERM, ::libc::SIGTTOU] {
+						::libc::signal(sig, ::libc::SIG_IGN);
+					}
+					let mut byte = 0u8;
+					let n = loop {
+						let n = ::libc::read(fds[0], &mut byte as *mut u8 as *mut ::libc::c_void, 1);
+						if n >= 0 || ::std::io::Error::last_os_error().kind() != ::std::io::ErrorKind::Interrupted {
+							break n;
+						}
+					};
+					if n == 0 {
+						// The pipe closed without a goodbye, so the app died
+						tcsetattr(fd, TCSANOW, &original).unwrap_or(());
+						::libc::write(fd, restore.as_ptr() as *const ::libc::c_void, restore.len());
+					}
+					::libc::_exit(0);
+				}
+			},
+			pid => {
+				unsafe { ::libc::close(fds[0]); }
+				Some(CrashGuard { pid, pipe: fds[1] })
+			}
+		}
+	}
+	/// Tells the helper that the terminal was restored normally, and waits for it to exit.
+	pub fn release(self) {
+		unsafe {
+			::libc::write(self.pipe, b"q".as_ptr() as *const ::libc::c_void, 1);
+			::libc::close(self.pipe);
+			::libc::waitpid(self.pid, ::std::ptr::null_mut(), 0);
+		}
+	}
+}
@@ -0,0 +1,581 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use ::event::{Event, Key, KeyEvent, KeyKind, Modifiers, MouseButton, MouseEvent, MouseKind};
+
+const ESC: u8 = 0x1b;
+
+// How long to wait for the rest of an escape sequence before deciding it was just the ESC key, unless
+// changed with `set_escape_timeout`
+const DEFAULT_ESCAPE_TIMEOUT_MS: u64 = 50;
+
+// How many pieces of input that couldn't be decoded are kept, while they're being logged
+const UNKNOWN_LOG: usize = 32;
+
+// Sequences that are always the same bytes, so they're looked up rather than parsed. Ones with
+// parameters (like modifiers) go through `parse_csi`
+const SEQUENCES: [(&[u8], Key); 15] = [
+	// SS3, which is what the arrows and such send in application mode
+	(b"\x1bOA", Key::Up),
+	(b"\x1bOB", Key::Down),
+	(b"\x1bOC", Key::Right),
+	(b"\x1bOD", Key::Left),
+	(b"\x1bOH", Key::Home),
+	(b"\x1bOF", Key::End),
+	(b"\x1bOP", Key::F(1)),
+	(b"\x1bOQ", Key::F(2)),
+	(b"\x1bOR", Key::F(3)),
+	(b"\x1bOS", Key::F(4)),
+	// The Linux console's F1 to F5
+	(b"\x1b[[A", Key::F(1)),
+	(b"\x1b[[B", Key::F(2)),
+	(b"\x1b[[C", Key::F(3)),
+	(b"\x1b[[D", Key::F(4)),
+	(b"\x1b[[E", Key::F(5)),
+];
+
+/// What ESC followed by another key means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscMode {
+	/// It's Alt with that key, the way most terminals send Alt (default)
+	Alt,
+	/// ESC is always a key of its own, so pressing it quickly before another key (like in vi) never turns
+	/// into Alt. Escape sequences for other keys still work
+	Key,
+}
+
+/// Turns the bytes read from the terminal into `Event`s.
+#[derive(Debug, Clone)]
+pub struct Decoder {
+	buf: Vec<u8>,
+	/// Decoded by `peek`, and handed out next
+	peeked: Option<Event>,
+	/// Whether the kitty keyboard protocol is on
+	kitty: bool,
+	escape_timeout: Duration,
+	esc_mode: EscMode,
+	/// Added with `register`, these are looked for before anything else
+	sequences: Vec<(Vec<u8>, Event)>,
+	/// Whether input that isn't understood comes out as `Event::Unknown`, see `set_strict`
+	strict: bool,
+	/// The latest input that wasn't understood, while it's being logged (see `set_log_unknown`)
+	unknown: Option<VecDeque<Vec<u8>>>,
+}
+
+impl Decoder {
+	pub fn new() -> Decoder {
+		Decoder {
+			buf: Vec::new(),
+			peeked: None,
+			kitty: false,
+			escape_timeout: Duration::from_millis(DEFAULT_ESCAPE_TIMEOUT_MS),
+			esc_mode: EscMode::Alt,
+			sequences: Vec::new(),
+			strict: false,
+			unknown: None,
+		}
+	}
+	/// A decoder with the same settings and registered sequences, that hasn't been fed anything.
+	pub fn blank(&self) -> Decoder {
+		Decoder {
+			buf: Vec::new(),
+			peeked: None,
+			sequences: self.sequences.clone(),
+			unknown: None,
+			..*self
+		}
+	}
+	/// Makes `seq` decode to `event` (a `Key`, or any other event), e.g. for a key that some terminal
+	/// sends a sequence for that isn't understood, or to read it differently than usual. Registered
+	/// sequences come before the built in ones, and the longest one that fits wins. Registering the same
+	/// sequence again replaces it.
+	///
+	/// NOTE: If `seq` starts with something that's a key on its own (anything but ESC), that key waits
+	/// for the rest of the sequence (or the escape timeout) before it's read, like vi's mappings.
+	pub fn register<E: Into<Event>>(&mut self, seq: &str, event: E) {
+		let event = event.into();
+		match self.sequences.iter_mut().find(|s| s.0 == seq.as_bytes()) {
+			Some(existing) => existing.1 = event,
+			None => self.sequences.push((seq.as_bytes().to_vec(), event)),
+		}
+	}
+	/// Forgets a sequence added with `register`.
+	pub fn unregister(&mut self, seq: &str) {
+		self.sequences.retain(|s| s.0 != seq.as_bytes());
+	}
+	/// Sets whether input that isn't understood comes out as `Event::Unknown` with its bytes, instead of
+	/// being dropped (the default).
+	pub fn set_strict(&mut self, strict: bool) {
+		self.strict = strict;
+	}
+	pub fn is_strict(&self) -> bool {
+		self.strict
+	}
+	/// Sets whether to keep the latest 32 pieces of input that weren't understood, see `unknown_input`.
+	/// Off by default. Turning it off forgets them
+	pub fn set_log_unknown(&mut self, on: bool) {
+		if on != self.unknown.is_some() {
+			self.unknown = if on { Some(VecDeque::new()) } else { None };
+		}
+	}
+	/// The latest input that wasn't understood, oldest first, while it's being logged (see
+	/// `set_log_unknown`). For bug reports about terminals that send something unexpected, see
+	/// `diagnostics::bundle`.
+	pub fn unknown_input(&self) -> impl Iterator<Item = &[u8]> {
+		self.unknown.iter().flatten().map(|bytes| &bytes[..])
+	}
+	/// Sets how long to wait for the rest of an escape sequence before a lone ESC is taken to be the ESC
+	/// key (50ms by default). Shorter makes Esc snappier, but over a slow connection the pieces of a
+	/// sequence can end up further apart than that, and come out as Esc followed by junk.
+	///
+	/// NOTE: The waiting is done by whatever reads the input (like `Screen`), this is just how long it waits.
+	pub fn set_escape_timeout(&mut self, timeout: Duration) {
+		self.escape_timeout = timeout;
+	}
+	pub fn escape_timeout(&self) -> Duration {
+		self.escape_timeout
+	}
+	/// Sets what ESC followed by another key means, see `EscMode`.
+	pub fn set_esc_mode(&mut self, mode: EscMode) {
+		self.esc_mode = mode;
+	}
+	pub fn esc_mode(&self) -> EscMode {
+		self.esc_mode
+	}
+	/// Sets whether the terminal is reporting keys with the kitty keyboard protocol (see
+	/// `Screen::set_keyboard_protocol`). Without it, keys with modifiers that `Key` can't hold come out as
+	/// plain `Key`s and releases are dropped, the way other terminals report them.
+	pub fn set_kitty_keyboard(&mut self, on: bool) {
+		self.kitty = on;
+	}
+	/// Adds bytes read from the terminal.
+	pub fn feed(&mut self, bytes: &[u8]) {
+		self.buf.extend_from_slice(bytes);
+	}
+	/// Decodes the next event.
+	/// If there's no complete event buffered, returns None
+	pub fn next_event(&mut self) -> Option<Event> {
+		self.peeked.take().or_else(|| self.decode(false))
+	}
+	/// Looks at the next event without taking it.
+	/// If there's no complete event buffered, returns None
+	pub fn peek(&mut self) -> Option<&Event> {
+		if self.peeked.is_none() {
+			self.peeked = self.decode(false);
+		}
+		self.peeked.as_ref()
+	}
+	/// Whether there are bytes that don't make up a complete event yet (e.g. a lone ESC).
+	pub fn has_pending(&self) -> bool {
+		!self.buf.is_empty()
+	}
+	/// Decodes the next event, even if it's incomplete.
+	///
+	/// Used once no more input shows up, so that a lone ESC gets reported as `Key::Esc`
+	/// instead of waiting on the rest of an escape sequence forever.
+	pub fn flush(&mut self) -> Option<Event> {
+		self.peeked.take().or_else(|| self.decode(true))
+	}
+	/// Internal: Pops events off the front of the buffer, skipping anything that isn't understood unless
+	/// in strict mode
+	fn decode(&mut self, force: bool) -> Option<Event> {
+		while !self.buf.is_empty() {
+			let registered = self.sequences.iter().map(|s| &s.0[..]);
+			if let Some((i, used)) = find_sequence(&self.buf, registered, force)? {
+				// Registered events come out just as they are
+				self.buf.drain(..used);
+				return Some(self.sequences[i].1.clone());
+			}
+			let (event, used) = parse(&self.buf, force, self.esc_mode)?;
+			let bytes: Vec<u8> = self.buf.drain(..used).collect();
+			match event {
+				Some(event) => if let Some(event) = self.simplify(event) {
+					return Some(event);
+				},
+				None => {
+					if let Some(ref mut log) = self.unknown {
+						if log.len() >= UNKNOWN_LOG {
+							log.pop_front();
+						}
+						log.push_back(bytes.clone());
+					}
+					if self.strict {
+						return Some(Event::Unknown(bytes));
+					}
+				},
+			}
+		}
+		None
+	}
+	/// Internal: Turns an `Event::Keyboard` into a plain `Key` if it can be, or if the protocol is off
+	/// (dropping releases). Other events are left alone
+	fn simplify(&self, event: Event) -> Option<Event> {
+		let ev = match event {
+			Event::Keyboard(ev) => ev,
+			other => return Some(other),
+		};
+		// Whoever asked for event types gets to tell repeats from presses
+		if self.kitty && ev.kind == KeyKind::Repeat {
+			return Some(Event::Keyboard(ev));
+		}
+		if let Some(key) = plain_key(&ev) {
+			return Some(Event::Key(key));
+		}
+		match ev.kind {
+			_ if self.kitty => Some(Event::Keyboard(ev)),
+			KeyKind::Release => None,
+			_ => Some(Event::Key(ev.key)),
+		}
+	}
+}
+
+impl Default for Decoder {
+	fn default() -> Decoder {
+		Decoder::new()
+	}
+}
+
+/// Internal: Which of `sequences` `buf` starts with, the longest one if there's more than one, as its
+/// index and length. If `buf` is the start of one that's longer, returns None to wait for the rest,
+/// unless `force` is set
+fn find_sequence<'a, I: Iterator<Item = &'a [u8]>>(buf: &[u8], sequences: I, force: bool) -> Option<Option<(usize, usize)>> {
+	let mut found: Option<(usize, usize)> = None;
+	for (i, seq) in sequences.enumerate() {
+		if buf.starts_with(seq) {
+			if found.is_none_or(|(_, len)| seq.len() > len) {
+				found = Some((i, seq.len()));
+			}
+		} else if !force && seq.starts_with(buf) {
+			return None;
+		}
+	}
+	Some(found)
+}
+
+/// Internal: The `Key` for a key press with modifiers, if it can say everything about it
+pub(crate) fn plain_key(ev: &KeyEvent) -> Option<Key> {
+	if ev.kind == KeyKind::Release {
+		return None;
+	}
+	let upper = |c: char| c.to_uppercase().next().unwrap_or(c);
+	let (shift, alt, ctrl) = (Modifiers::SHIFT, Modifiers::ALT, Modifiers::CTRL);
+	Some(match (ev.key, ev.mods) {
+		(key, m) if m.is_empty() => key,
+		(Key::Char(c), m) if m == shift => Key::Char(upper(c)),
+		(Key::Tab, m) if m == shift => Key::BackTab,
+		(Key::Char(c), m) if m == ctrl && c.is_ascii() => Key::Ctrl(c.to_ascii_lowercase()),
+		(Key::Char(c), m) if m == alt => Key::Alt(c),
+		(Key::Char(c), m) if m == alt | shift => Key::Alt(upper(c)),
+		_ => return None,
+	})
+}
+
+/// Internal: Parses a single event from the start of `buf`.
+/// Returns the event (None if the bytes weren't understood) and how many bytes it used up,
+/// or None if more bytes are needed and `force` isn't set.
+fn parse(buf: &[u8], force: bool, esc: EscMode) -> Option<(Option<Event>, usize)> {
+	if let Some((i, used)) = find_sequence(buf, SEQUENCES.iter().map(|s| s.0), force)? {
+		return Some((Some(Event::Key(SEQUENCES[i].1)), used));
+	}
+	match buf[0] {
+		ESC => parse_escape(buf, force, esc),
+		b'\r' | b'\n' => Some((Some(Event::Key(Key::Enter)), 1)),
+		b'\t' => Some((Some(Event::Key(Key::Tab)), 1)),
+		0x7f | 0x08 => Some((Some(Event::Key(Key::Backspace)), 1)),
+		0x00 => Some((Some(Event::Key(Key::Ctrl(' '))), 1)),
+		c @ 0x01..=0x1a => Some((Some(Event::Key(Key::Ctrl((c - 1 + b'a') as char))), 1)),
+		c @ 0x1c..=0x1f => Some((Some(Event::Key(Key::Ctrl((c - 0x1c + b'4') as char))), 1)),
+		_ => match parse_char(buf, force)? {
+			(Some(ch), used) => Some((Some(Event::Key(Key::Char(ch))), used)),
+			(None, used) => Some((None, used)),
+		}
+	}
+}
+
+/// Internal: Parses a single UTF-8 character from the start of `buf`
+fn parse_char(buf: &[u8], force: bool) -> Option<(Option<char>, usize)> {
+	let len = match buf[0] {
+		0x00..=0x7f => 1,
+		0xc0..=0xdf => 2,
+		0xe0..=0xef => 3,
+		0xf0..=0xf7 => 4,
+		_ => return Some((None, 1)),
+	};
+	if buf.len() < len {
+		return if force { Some((None, buf.len())) } else { None };
+	}
+	match ::std::str::from_utf8(&buf[..len]) {
+		Ok(s) => Some((s.chars().next(), len)),
+		Err(_) => Some((None, 1)),
+	}
+}
+
+/// Internal: Parses anything starting with ESC
+fn parse_escape(buf: &[u8], force: bool, esc: EscMode) -> Option<(Option<Event>, usize)> {
+	if buf.len() == 1 {
+		// Either the ESC key or the start of a sequence, only waiting can tell
+		return if force { Some((Some(Event::Key(Key::Esc)), 1)) } else { None };
+	}
+	match buf[1] {
+		b'[' => parse_csi(buf, force),
+		b'O' => {
+			if buf.len() < 3 {
+				return match (force, esc) {
+					(false, _) => None,
+					(true, EscMode::Alt) => Some((Some(Event::Key(Key::Alt('O'))), 2)),
+					(true, EscMode::Key) => Some((Some(Event::Key(Key::Esc)), 1)),
+				};
+			}
+			// The ones that mean something are in `SEQUENCES`
+			Some((None, 3))
+		},
+		ESC => Some((Some(Event::Key(Key::Esc)), 1)),
+		_ if esc == EscMode::Key => Some((Some(Event::Key(Key::Esc)), 1)),
+		_ => {
+			let (ch, used) = parse_char(&buf[1..], force)?;
+			Some((ch.map(|c| Event::Key(Key::Alt(c))), used + 1))
+		}
+	}
+}
+
+/// Internal: Parses a control sequence, `ESC [ params final`
+fn parse_csi(buf: &[u8], force: bool) -> Option<(Option<Event>, usize)> {
+	// The older mouse encoding, `ESC [ M` and three bytes, for when SGR mouse reports can't be used
+	if buf.get(2) == Some(&b'M') {
+		if buf.len() < 6 {
+			return if force { Some((None, buf.len())) } else { None };
+		}
+		return Some((parse_x10_mouse(buf[3], buf[4], buf[5]), 6));
+	}
+	let mut end = 2;
+	while end < buf.len() && !(0x40..=0x7e).contains(&buf[end]) {
+		end += 1;
+	}
+	if end == buf.len() {
+		return if force { Some((None, buf.len())) } else { None };
+	}
+	let params = &buf[2..end];
+	let used = end + 1;
+
+	if params.first() == Some(&b'<') {
+		return Some((parse_sgr_mouse(&params[1..], buf[end]), used));
+	}
+
+	// Parameters are split by `;`, and can have sub-parameters split by `:` (e.g. `1;5:3`)
+	let fields: Vec<Vec<u32>> = ::std::str::from_utf8(params).unwrap_or("")
+		.split(';')
+		.map(|f| f.split(':').map(|n| n.parse().unwrap_or(0)).collect())
+		.collect();
+	let num = |i: usize, j: usize| fields.get(i).and_then(|f| f.get(j)).cloned();
+	let mut key = match buf[end] {
+		// Focus reports, `ESC [ I` and `ESC [ O`
+		b'I' if params.is_empty() => return Some((Some(Event::FocusGained), used)),
+		b'O' if params.is_empty() => return Some((Some(Event::FocusLost), used)),
+		b'A' => Key::Up,
+		b'B' => Key::Down,
+		b'C' => Key::Right,
+		b'D' => Key::Left,
+		b'H' => Key::Home,
+		b'F' => Key::End,
+		b'Z' => Key::BackTab,
+		c @ b'P'..=b'S' => Key::F(c - b'P' + 1),
+		// The kitty keyboard protocol, `ESC [ code ; modifiers u`
+		b'u' => match num(0, 0).and_then(kitty_key) {
+			Some(key) => key,
+			None => return Some((None, used)),
+		},
+		b'~' => match num(0, 0).unwrap_or(0) {
+			1 | 7 => Key::Home,
+			2 => Key::Insert,
+			3 => Key::Delete,
+			4 | 8 => Key::End,
+			5 => Key::PageUp,
+			6 => Key::PageDown,
+			n @ 11..=15 => Key::F((n - 10) as u8),
+			n @ 17..=21 => Key::F((n - 11) as u8),
+			n @ 23..=24 => Key::F((n - 12) as u8),
+			// xterm's modifyOtherKeys, `ESC [ 27 ; modifiers ; code ~`
+			27 => match num(2, 0).and_then(kitty_key) {
+				Some(key) => key,
+				None => return Some((None, used)),
+			},
+			_ => return Some((None, used)),
+		},
+		_ => return Some((None, used)),
+	};
+	// Modifiers are sent plus one, so that 1 means none
+	let mods = Modifiers::from_bits(num(1, 0).unwrap_or(1).saturating_sub(1) as u8);
+	let kind = match num(1, 1) {
+		Some(2) => KeyKind::Repeat,
+		Some(3) => KeyKind::Release,
+		_ => KeyKind::Press,
+	};
+	if buf[end] == b'u' && mods.contains(Modifiers::SHIFT) {
+		// What shift makes of the key, if the terminal was asked to say
+		if let Some(shifted) = num(0, 1).filter(|&c| c != 0).and_then(kitty_key) {
+			key = shifted;
+		}
+	}
+	if mods.is_empty() && kind == KeyKind::Press {
+		return Some((Some(Event::Key(key)), used));
+	}
+	Some((Some(Event::Keyboard(KeyEvent { key, mods, kind })), used))
+}
+
+/// Internal: The key for a key code of the kitty keyboard protocol, which is the character it types
+/// or a code from Unicode's private use area. Codes for keys with no `Key`, like Caps Lock or the
+/// modifiers themselves, return None
+fn kitty_key(code: u32) -> Option<Key> {
+	Some(match code {
+		8 | 127 => Key::Backspace,
+		9 => Key::Tab,
+		13 => Key::Enter,
+		27 => Key::Esc,
+		57376..=57398 => Key::F((code - 57376 + 13) as u8),
+		// The keypad
+		57399..=57408 => Key::Char((b'0' + (code - 57399) as u8) as char),
+		57409 => Key::Char('.'),
+		57410 => Key::Char('/'),
+		57411 => Key::Char('*'),
+		57412 => Key::Char('-'),
+		57413 => Key::Char('+'),
+		57414 => Key::Enter,
+		57415 => Key::Char('='),
+		57417 => Key::Left,
+		57418 => Key::Right,
+		57419 => Key::Up,
+		57420 => Key::Down,
+		57421 => Key::PageUp,
+		57422 => Key::PageDown,
+		57423 => Key::Home,
+		57424 => Key::End,
+		57425 => Key::Insert,
+		57426 => Key::Delete,
+		57344..=63743 => return None,
+		c if c >= 0x20 => Key::Char(::std::char::from_u32(c)?),
+		_ => return None,
+	})
+}
+
+/// Internal: Parses the parameters of an SGR mouse report, `ESC [ < button ; x ; y M/m`
+fn parse_sgr_mouse(params: &[u8], last: u8) -> Option<Event> {
+	let params = ::std::str::from_utf8(params).ok()?;
+	let mut nums = params.split(';').map(|n| n.parse::<u16>());
+	let code = nums.next()?.ok()?;
+	let x = nums.next()?.ok()?;
+	let y = nums.next()?.ok()?;
+	Some(mouse_event(code, y, x, last == b'm'))
+}
+
+/// Internal: Parses the three bytes of an `ESC [ M` mouse report, which are all offset by 32
+fn parse_x10_mouse(code: u8, x: u8, y: u8) -> Option<Event> {
+	let code = code.checked_sub(32)? as u16;
+	let (x, y) = (x.checked_sub(32)? as u16, y.checked_sub(32)? as u16);
+	// Releases don't say which button it was, so they come out as the left one
+	let release = code & 0b11 == 3 && code & (32 | 64) == 0;
+	Some(mouse_event(if release { code & !0b11 } else { code }, y, x, release))
+}
+
+/// Internal: The mouse event for a button code, at a position counted from 1
+fn mouse_event(code: u16, y: u16, x: u16, release: bool) -> Event {
+	let button = match code & 0b11 {
+		0 => MouseButton::Left,
+		1 => MouseButton::Middle,
+		_ => MouseButton::Right,
+	};
+	let kind = if code & 64 != 0 {
+		if code & 1 == 0 { MouseKind::ScrollUp } else { MouseKind::ScrollDown }
+	} else if code & 32 != 0 {
+		if code & 0b11 == 3 { MouseKind::Move } else { MouseKind::Drag(button) }
+	} else if release {
+		MouseKind::Release(button)
+	} else {
+		MouseKind::Press(button)
+	};
+	// The terminal counts from 1
+	Event::Mouse(MouseEvent {
+		kind,
+		y: y.saturating_sub(1),
+		x: x.saturating_sub(1),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Internal: Everything `input` decodes to, once there's nothing more coming
+	fn decode_all(decoder: &mut Decoder, input: &[u8]) -> Vec<Event> {
+		decoder.feed(input);
+		let mut events = Vec::new();
+		while let Some(event) = decoder.next_event().or_else(|| decoder.flush()) {
+			events.push(event);
+		}
+		events
+	}
+
+	#[test]
+	fn fixed_sequences() {
+		let mut decoder = Decoder::new();
+		for &(seq, key) in &SEQUENCES {
+			assert_eq!(decode_all(&mut decoder, seq), vec![Event::Key(key)], "{:?}", seq);
+		}
+		// Half of one waits for the rest
+		decoder.feed(b"\x1b[[");
+		assert_eq!(decoder.next_event(), None);
+		decoder.feed(b"E");
+		assert_eq!(decoder.next_event(), Some(Event::Key(Key::F(5))));
+	}
+
+	#[test]
+	fn registered_sequences() {
+		let mut decoder = Decoder::new();
+		decoder.register("\x1b[1;5P", Key::F(13));
+		decoder.register("\x1b[1;5", Key::F(14));
+		assert_eq!(decode_all(&mut decoder, b"\x1b[1;5Px"), vec![Event::Key(Key::F(13)), Event::Key(Key::Char('x'))]);
+		// They come before the built in ones, and registering one again replaces it
+		decoder.register("\x1bOP", Event::Interrupt);
+		assert_eq!(decode_all(&mut decoder, b"\x1bOP"), vec![Event::Interrupt]);
+		decoder.register("\x1bOP", Key::F(20));
+		assert_eq!(decode_all(&mut decoder, b"\x1bOP"), vec![Event::Key(Key::F(20))]);
+		decoder.unregister("\x1bOP");
+		assert_eq!(decode_all(&mut decoder, b"\x1bOP"), vec![Event::Key(Key::F(1))]);
+		// A key that starts one waits to see if the rest comes
+		decoder.register("jk", Key::Esc);
+		decoder.feed(b"j");
+		assert_eq!(decoder.next_event(), None);
+		assert_eq!(decode_all(&mut decoder, b"k"), vec![Event::Key(Key::Esc)]);
+		assert_eq!(decode_all(&mut decoder, b"j"), vec![Event::Key(Key::Char('j'))]);
+		// And blank decoders keep them
+		assert_eq!(decode_all(&mut decoder.blank(), b"\x1b[1;5P"), vec![Event::Key(Key::F(13))]);
+	}
+
+	#[test]
+	fn strict_mode() {
+		let mut decoder = Decoder::new();
+		assert_eq!(decode_all(&mut decoder, b"\x1bOZa"), vec![Event::Key(Key::Char('a'))]);
+		decoder.set_strict(true);
+		decoder.set_log_unknown(true);
+		assert_eq!(decode_all(&mut decoder, b"\x1bOZa\xff"), vec![
+			Event::Unknown(b"\x1bOZ".to_vec()),
+			Event::Key(Key::Char('a')),
+			Event::Unknown(vec![0xff]),
+		]);
+		assert_eq!(decoder.unknown_input().collect::<Vec<_>>(), vec![&b"\x1bOZ"[..], &[0xff][..]]);
+		decoder.set_log_unknown(false);
+		assert_eq!(decoder.unknown_input().count(), 0);
+	}
+
+	#[test]
+	fn kitty_event_types() {
+		let mut decoder = Decoder::new();
+		let held = KeyEvent { key: Key::Char('a'), mods: Modifiers::default(), kind: KeyKind::Repeat };
+		// Repeats look like presses unless the protocol was asked for
+		assert_eq!(decode_all(&mut decoder, b"\x1b[97;1:2u\x1b[97;1:3u"), vec![Event::Key(Key::Char('a'))]);
+		decoder.set_kitty_keyboard(true);
+		assert_eq!(decode_all(&mut decoder, b"\x1b[97u\x1b[97;1:2u\x1b[97;1:3u"), vec![
+			Event::Key(Key::Char('a')),
+			Event::Keyboard(held),
+			Event::Keyboard(KeyEvent { kind: KeyKind::Release, ..held }),
+		]);
+	}
+}
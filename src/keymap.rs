@@ -0,0 +1,208 @@
+//! Mapping keys (and chords of keys) to actions.
+
+use std::time::{Duration, Instant};
+use ::event::{Event, Key, KeyEvent, KeyKind};
+use ::input::plain_key;
+
+/// How long the keys of a chord may be apart by default
+const CHORD_TIMEOUT_MS: u64 = 1000;
+
+/// Maps keys to actions of any type, e.g. an enum of the app's commands.
+///
+/// Bindings are written like `"ctrl-x ctrl-s"`: keys separated by spaces, each optionally prefixed by
+/// `ctrl-` or `alt-`. Special keys are named `enter`, `tab`, `backtab`, `backspace`, `esc`, `space`,
+/// `up`, `down`, `left`, `right`, `home`, `end`, `pageup`, `pagedown`, `insert`, `delete` and `f1`-`f12`.
+///
+/// A key that's held down comes as more presses on most terminals. With the kitty keyboard protocol's
+/// `KeyboardFlags::EVENT_TYPES`, repeats can be told apart: they run the binding again (see `set_repeat`),
+/// but never count as the next key of a chord, and releases are ignored.
+#[derive(Debug, Clone)]
+pub struct Keymap<A> {
+	bindings: Vec<(Vec<Key>, A)>,
+	/// The keys of a chord typed so far
+	pending: Vec<Key>,
+	last_key: Option<Instant>,
+	timeout: Duration,
+	/// Whether repeats of a held key run its binding again
+	repeat: bool,
+}
+
+impl<A: Clone> Keymap<A> {
+	pub fn new() -> Keymap<A> {
+		Keymap {
+			bindings: Vec::new(),
+			pending: Vec::new(),
+			last_key: None,
+			timeout: Duration::from_millis(CHORD_TIMEOUT_MS),
+			repeat: true,
+		}
+	}
+	/// Binds `keys` to `action`, replacing whatever it was bound to before.
+	/// If `keys` can't be parsed, returns None
+	///
+	/// NOTE: If a binding is also the start of a longer one (e.g. "ctrl-x" and "ctrl-x ctrl-s"), the shorter one wins.
+	pub fn bind(&mut self, keys: &str, action: A) -> Option<()> {
+		let keys = parse_keys(keys)?;
+		self.bindings.retain(|(k, _)| *k != keys);
+		self.bindings.push((keys, action));
+		Some(())
+	}
+	/// Removes the binding for `keys`, if there is one.
+	pub fn unbind(&mut self, keys: &str) {
+		if let Some(keys) = parse_keys(keys) {
+			self.bindings.retain(|(k, _)| *k != keys);
+		}
+	}
+	/// Sets how long the keys of a chord may be apart before it's given up on.
+	pub fn set_timeout(&mut self, timeout: Duration) {
+		self.timeout = timeout;
+	}
+	/// Sets whether a held key runs its binding again each time it repeats. On by default.
+	///
+	/// NOTE: Only repeats the terminal reports as such (see `KeyboardFlags::EVENT_TYPES`) can be left out.
+	pub fn set_repeat(&mut self, on: bool) {
+		self.repeat = on;
+	}
+	/// Whether part of a chord has been typed.
+	pub fn is_pending(&self) -> bool {
+		!self.pending.is_empty()
+	}
+	/// Forgets any partly typed chord.
+	pub fn reset(&mut self) {
+		self.pending.clear();
+	}
+	/// Feeds an event through the keymap.
+	/// Returns the action if the event finished a binding, otherwise None (including for anything that isn't a key)
+	pub fn process(&mut self, event: &Event) -> Option<A> {
+		let key = match *event {
+			Event::Key(key) => key,
+			Event::Keyboard(ev) => match ev.kind {
+				KeyKind::Press => plain_key(&ev)?,
+				// Holding the key down partway through a chord doesn't type it again
+				KeyKind::Repeat if self.repeat && self.pending.is_empty() => {
+					plain_key(&KeyEvent { kind: KeyKind::Press, ..ev })?
+				},
+				_ => return None,
+			},
+			_ => return None,
+		};
+		let now = Instant::now();
+		if self.last_key.is_some_and(|last| now.duration_since(last) > self.timeout) {
+			self.pending.clear();
+		}
+		self.last_key = Some(now);
+
+		self.pending.push(key);
+		self.check()
+	}
+	/// Internal: Checks the pending keys against the bindings
+	fn check(&mut self) -> Option<A> {
+		loop {
+			if let Some((_, action)) = self.bindings.iter().find(|(k, _)| *k == self.pending) {
+				let action = action.clone();
+				self.pending.clear();
+				return Some(action);
+			}
+			if self.bindings.iter().any(|(k, _)| k.starts_with(&self.pending)) {
+				// Part of a chord, wait for the rest
+				return None;
+			}
+			// Nothing matches. If this key broke off a chord, it might still start something by itself.
+			if self.pending.len() > 1 {
+				let key = self.pending.pop().unwrap();
+				self.pending = vec![key];
+				continue;
+			}
+			self.pending.clear();
+			return None;
+		}
+	}
+}
+
+impl<A: Clone> Default for Keymap<A> {
+	fn default() -> Keymap<A> {
+		Keymap::new()
+	}
+}
+
+/// Parses space separated keys, e.g. `"ctrl-x ctrl-s"`.
+/// If any of them can't be parsed, returns None
+pub fn parse_keys(spec: &str) -> Option<Vec<Key>> {
+	let keys = spec.split_whitespace().map(parse_key).collect::<Option<Vec<Key>>>()?;
+	if keys.is_empty() { None } else { Some(keys) }
+}
+
+/// Parses a single key, e.g. `"ctrl-s"`, `"f5"`, or `"x"`.
+/// If it can't be parsed, returns None
+pub fn parse_key(spec: &str) -> Option<Key> {
+	let lower = spec.to_ascii_lowercase();
+	if lower.starts_with("ctrl-") && spec.len() > 5 {
+		return match parse_key(&spec[5..])? {
+			Key::Char(c) => Some(Key::Ctrl(c.to_ascii_lowercase())),
+			_ => None,
+		};
+	}
+	if lower.starts_with("alt-") && spec.len() > 4 {
+		return match parse_key(&spec[4..])? {
+			Key::Char(c) => Some(Key::Alt(c)),
+			_ => None,
+		};
+	}
+	let key = match &lower[..] {
+		"enter" => Key::Enter,
+		"tab" => Key::Tab,
+		"backtab" | "shift-tab" => Key::BackTab,
+		"backspace" => Key::Backspace,
+		"esc" => Key::Esc,
+		"space" => Key::Char(' '),
+		"up" => Key::Up,
+		"down" => Key::Down,
+		"left" => Key::Left,
+		"right" => Key::Right,
+		"home" => Key::Home,
+		"end" => Key::End,
+		"pageup" => Key::PageUp,
+		"pagedown" => Key::PageDown,
+		"insert" => Key::Insert,
+		"delete" => Key::Delete,
+		_ => {
+			let mut chars = spec.chars();
+			match (chars.next(), chars.next()) {
+				(Some(c), None) => Key::Char(c),
+				(Some('f'), Some(_)) | (Some('F'), Some(_)) => match spec[1..].parse() {
+					Ok(n @ 1..=12) => Key::F(n),
+					_ => return None,
+				},
+				_ => return None,
+			}
+		}
+	};
+	Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ::event::Modifiers;
+
+	#[test]
+	fn held_keys() {
+		let mut keymap = Keymap::new();
+		keymap.bind("j", 1).unwrap();
+		keymap.bind("ctrl-x ctrl-s", 2).unwrap();
+		let held = |key, mods, kind| Event::Keyboard(KeyEvent { key, mods, kind });
+		let j = |kind| held(Key::Char('j'), Modifiers::default(), kind);
+		assert_eq!(keymap.process(&j(KeyKind::Press)), Some(1));
+		assert_eq!(keymap.process(&j(KeyKind::Repeat)), Some(1));
+		assert_eq!(keymap.process(&j(KeyKind::Release)), None);
+		keymap.set_repeat(false);
+		assert_eq!(keymap.process(&j(KeyKind::Repeat)), None);
+		// Holding the first key of a chord doesn't break it
+		keymap.set_repeat(true);
+		let x = |kind| held(Key::Char('x'), Modifiers::CTRL, kind);
+		assert_eq!(keymap.process(&x(KeyKind::Press)), None);
+		assert_eq!(keymap.process(&x(KeyKind::Repeat)), None);
+		assert_eq!(keymap.process(&x(KeyKind::Release)), None);
+		assert_eq!(keymap.process(&Event::Key(Key::Ctrl('s'))), Some(2));
+	}
+}
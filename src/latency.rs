@@ -0,0 +1,79 @@
+//! Measuring how long it takes for input to show up on screen, see `Screen::set_latency_tracking`.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// How many of the latest samples are kept, older ones are dropped
+const MAX_SAMPLES: usize = 1024;
+
+/// How long frames took to go out after the input they answered came in, for the latest 1024 frames.
+///
+/// A sample starts when input is read from the terminal and ends when the next frame that changed
+/// anything has been flushed to it. That's as close to the input showing up on screen as can be
+/// measured from here: it leaves out the terminal itself (and the network, over SSH), but takes in
+/// the app handling the events and drawing, the diffing, and writing the frame out.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+	samples: VecDeque<Duration>,
+}
+
+impl LatencyStats {
+	pub fn new() -> LatencyStats {
+		LatencyStats::default()
+	}
+	/// Adds a sample, dropping the oldest one if there are too many.
+	pub fn record(&mut self, latency: Duration) {
+		if self.samples.len() >= MAX_SAMPLES {
+			self.samples.pop_front();
+		}
+		self.samples.push_back(latency);
+	}
+	/// Forgets every sample, e.g. after changing a setting to measure it on its own.
+	pub fn clear(&mut self) {
+		self.samples.clear();
+	}
+	/// How many samples there are.
+	pub fn len(&self) -> usize {
+		self.samples.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.samples.is_empty()
+	}
+	/// Every sample, oldest first.
+	pub fn samples(&self) -> impl Iterator<Item = &Duration> {
+		self.samples.iter()
+	}
+	/// The latency that `p` percent of the samples are at or under, e.g. 50 for the median or 99 for
+	/// the worst but one in a hundred.
+	/// If there aren't any samples, returns None
+	pub fn percentile(&self, p: f64) -> Option<Duration> {
+		if self.samples.is_empty() {
+			return None;
+		}
+		let mut sorted: Vec<Duration> = self.samples.iter().cloned().collect();
+		sorted.sort();
+		// Nearest rank
+		let rank = (p.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+		Some(sorted[rank.saturating_sub(1)])
+	}
+	/// The average of the samples.
+	/// If there aren't any, returns None
+	pub fn mean(&self) -> Option<Duration> {
+		if self.samples.is_empty() {
+			return None;
+		}
+		Some(self.samples.iter().sum::<Duration>() / self.samples.len() as u32)
+	}
+	/// The longest sample.
+	/// If there aren't any, returns None
+	pub fn max(&self) -> Option<Duration> {
+		self.samples.iter().max().cloned()
+	}
+	/// A one line summary, like `p50 1.2ms  p90 3.4ms  p99 8.0ms  max 12.1ms  (340 frames)`. For
+	/// showing in a debug overlay or printing on exit.
+	pub fn summary(&self) -> String {
+		let ms = |d: Option<Duration>| d.map_or(0.0, |d| d.as_secs_f64() * 1000.0);
+		format!("p50 {:.1}ms  p90 {:.1}ms  p99 {:.1}ms  max {:.1}ms  ({} frames)",
+			ms(self.percentile(50.0)), ms(self.percentile(90.0)), ms(self.percentile(99.0)), ms(self.max()), self.len())
+	}
+}
@@ -0,0 +1,258 @@
+//! Splitting the screen into panes, and placing things relative to its edges.
+
+use ::event::Event;
+use ::rect::Rect;
+use ::window::Window;
+
+/// Which way a layout splits its area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+	/// Side by side, left to right
+	Horizontal,
+	/// Stacked, top to bottom
+	Vertical,
+}
+
+/// How much of the split a pane gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Constraint {
+	/// Exactly this many rows/columns, if there's room
+	Fixed(u16),
+	/// A percentage of the whole area
+	Percent(u16),
+	/// A share of whatever the other constraints leave over, weighted by the number
+	Flex(u16),
+}
+
+/// A tree of splits, which works out the `Rect` of every pane in it.
+///
+/// Panes are numbered depth first in the order they were added, so in
+/// `Layout::vertical().pane(Fixed(1)).split(Flex(1), Layout::horizontal().pane(Percent(30)).pane(Flex(1)))`
+/// pane 0 is the top line, and panes 1 and 2 are the left and right of what's under it.
+#[derive(Debug, Clone)]
+pub struct Layout {
+	direction: Direction,
+	children: Vec<(Constraint, Option<Layout>)>,
+	/// The panes' rects from the last `compute`
+	rects: Vec<Rect>,
+}
+
+impl Layout {
+	pub fn new(direction: Direction) -> Layout {
+		Layout {
+			direction,
+			children: Vec::new(),
+			rects: Vec::new(),
+		}
+	}
+	/// Shorthand for a layout that splits side by side.
+	pub fn horizontal() -> Layout {
+		Layout::new(Direction::Horizontal)
+	}
+	/// Shorthand for a layout that splits top to bottom.
+	pub fn vertical() -> Layout {
+		Layout::new(Direction::Vertical)
+	}
+	/// Adds a pane.
+	pub fn pane(mut self, constraint: Constraint) -> Layout {
+		self.children.push((constraint, None));
+		self
+	}
+	/// Adds a part that gets split up further by `inner`.
+	pub fn split(mut self, constraint: Constraint, inner: Layout) -> Layout {
+		self.children.push((constraint, Some(inner)));
+		self
+	}
+	/// Works out where every pane goes inside of `area`, see `rects`.
+	pub fn compute(&mut self, area: Rect) {
+		span!("layout");
+		let mut rects = Vec::new();
+		self.collect(area, &mut rects);
+		self.rects = rects;
+	}
+	/// Recomputes the layout for the new size of the screen on `Event::Resize`.
+	/// Returns whether it did
+	///
+	/// NOTE: This lays out the whole screen, if lines were reserved with `Screen::reserve_top` or
+	/// `Screen::reserve_bottom` call `compute` with `Screen::layout_area` instead.
+	pub fn handle(&mut self, event: &Event) -> bool {
+		match *event {
+			Event::Resize { height, width } => {
+				self.compute(Rect::new(0, 0, height, width));
+				true
+			},
+			_ => false,
+		}
+	}
+	/// The panes' rects from the last `compute`, in pane order.
+	pub fn rects(&self) -> &[Rect] {
+		&self.rects
+	}
+	/// The rect of a single pane.
+	pub fn get(&self, pane: usize) -> Option<Rect> {
+		self.rects.get(pane).cloned()
+	}
+	/// A new, blank Window for every pane.
+	pub fn windows(&self) -> Vec<Window> {
+		self.rects.iter().map(|r| Window::new(r.y, r.x, r.height, r.width)).collect()
+	}
+	/// Splits `area` between the direct children only, without looking at any nested layouts.
+	pub fn split_area(&self, area: Rect) -> Vec<Rect> {
+		let total = match self.direction {
+			Direction::Horizontal => area.width,
+			Direction::Vertical => area.height,
+		};
+		let sizes = self.sizes(total);
+		let mut start = 0;
+		sizes.into_iter().map(|size| {
+			let rect = match self.direction {
+				Direction::Horizontal => Rect::new(area.y, area.x + start, area.height, size),
+				Direction::Vertical => Rect::new(area.y + start, area.x, size, area.width),
+			};
+			start += size;
+			rect
+		}).collect()
+	}
+	/// Internal: Adds the rects of every pane under this layout to `out`
+	fn collect(&mut self, area: Rect, out: &mut Vec<Rect>) {
+		let parts = self.split_area(area);
+		for (&mut (_, ref mut inner), part) in self.children.iter_mut().zip(parts) {
+			match *inner {
+				Some(ref mut inner) => {
+					let start = out.len();
+					inner.collect(part, out);
+					inner.rects = out[start..].to_vec();
+				},
+				None => out.push(part),
+			}
+		}
+	}
+	/// Internal: How much of `total` each child gets
+	fn sizes(&self, total: u16) -> Vec<u16> {
+		let mut left = total;
+		// Fixed and percentage sizes come first, in order, until there's no room left
+		let mut sizes: Vec<u16> = self.children.iter().map(|&(constraint, _)| {
+			let want = match constraint {
+				Constraint::Fixed(n) => n,
+				Constraint::Percent(p) => (total as u32 * p.min(100) as u32 / 100) as u16,
+				Constraint::Flex(_) => 0,
+			};
+			let size = want.min(left);
+			left -= size;
+			size
+		}).collect();
+		// Flex shares whatever is left, with the rounding left over going to the last one
+		let weights: u32 = self.children.iter().map(|&(c, _)| if let Constraint::Flex(w) = c { w as u32 } else { 0 }).sum();
+		if let Some(last) = self.children.iter().rposition(|&(c, _)| matches!(c, Constraint::Flex(w) if w > 0)) {
+			let pool = left as u32;
+			for (i, &(constraint, _)) in self.children.iter().enumerate() {
+				if let Constraint::Flex(w) = constraint {
+					let share = if i == last { left as u32 } else { pool * w as u32 / weights };
+					sizes[i] = share as u16;
+					left -= share as u16;
+				}
+			}
+		}
+		sizes
+	}
+}
+
+/// Which part of an area something sticks to, see `Anchored`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor {
+	TopLeft,
+	Top,
+	TopRight,
+	Left,
+	Center,
+	Right,
+	BottomLeft,
+	Bottom,
+	BottomRight,
+}
+
+/// How big something anchored is, along one side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Extent {
+	/// This many rows/columns, or all of them if there aren't enough
+	Fixed(u16),
+	/// A percentage of the area
+	Percent(u16),
+	/// All of the area, e.g. a status bar that's anchored to the `Bottom` and stretches across
+	Fill,
+}
+
+/// A position given relative to an area (usually the screen) instead of as coordinates, so it can be
+/// worked out again whenever the area changes size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Anchored {
+	pub anchor: Anchor,
+	pub height: Extent,
+	pub width: Extent,
+	/// Moves it down (or up, if negative) from where the anchor puts it
+	pub dy: i16,
+	/// Moves it right (or left, if negative) from where the anchor puts it
+	pub dx: i16,
+}
+
+impl Anchored {
+	pub fn new(anchor: Anchor, height: Extent, width: Extent) -> Anchored {
+		Anchored { anchor, height, width, dy: 0, dx: 0 }
+	}
+	/// Returns it moved by (dy, dx) from where the anchor puts it.
+	pub fn offset(mut self, dy: i16, dx: i16) -> Anchored {
+		self.dy = dy;
+		self.dx = dx;
+		self
+	}
+	/// Works out where it goes inside of `area`. Offsets can't push it out of the area.
+	pub fn resolve(&self, area: Rect) -> Rect {
+		let height = extent(self.height, area.height);
+		let width = extent(self.width, area.width);
+		let (vertical, horizontal) = match self.anchor {
+			Anchor::TopLeft => (0, 0),
+			Anchor::Top => (0, 1),
+			Anchor::TopRight => (0, 2),
+			Anchor::Left => (1, 0),
+			Anchor::Center => (1, 1),
+			Anchor::Right => (1, 2),
+			Anchor::BottomLeft => (2, 0),
+			Anchor::Bottom => (2, 1),
+			Anchor::BottomRight => (2, 2),
+		};
+		let y = place(vertical, height, area.height, self.dy);
+		let x = place(horizontal, width, area.width, self.dx);
+		Rect::new(area.y + y, area.x + x, height, width)
+	}
+	/// Moves and resizes `win` to where it goes inside of `area`, e.g. after an `Event::Resize`.
+	pub fn apply(&self, win: &mut Window, area: Rect) {
+		let rect = self.resolve(area);
+		win.resize(rect.height, rect.width);
+		win.move_to(rect.y, rect.x);
+	}
+	/// A new, blank Window where it goes inside of `area`.
+	pub fn window(&self, area: Rect) -> Window {
+		let rect = self.resolve(area);
+		Window::new(rect.y, rect.x, rect.height, rect.width)
+	}
+}
+
+/// Internal: How many cells an extent works out to, out of `space`
+fn extent(extent: Extent, space: u16) -> u16 {
+	match extent {
+		Extent::Fixed(n) => n.min(space),
+		Extent::Percent(p) => (space as u32 * p.min(100) as u32 / 100) as u16,
+		Extent::Fill => space,
+	}
+}
+
+/// Internal: Where something `size` big starts in `space`, for the start (0), middle (1) or end (2), moved by `offset`
+fn place(side: u8, size: u16, space: u16, offset: i16) -> u16 {
+	let free = space - size;
+	let start = match side {
+		0 => 0,
+		1 => free / 2,
+		_ => free,
+	};
+	(start as i32 + offset as i32).clamp(0, free as i32) as u16
+}
@@ -3,8 +3,67 @@
 #![allow(dead_code, unused_variables)]
 
 extern crate libc;
+#[cfg(unix)]
 extern crate termios;
+extern crate unicode_width;
+#[cfg(feature = "encoding")]
+extern crate encoding_rs;
+#[cfg(feature = "async")]
+extern crate futures_core;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
+// Opens a `tracing` span that lasts until the end of the enclosing block, if the `tracing` feature is on
+#[cfg(feature = "tracing")]
+macro_rules! span {
+	($name:expr) => { let _span = ::tracing::trace_span!($name).entered(); };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+	($name:expr) => {};
+}
+
+pub mod acs;
+pub mod app;
+pub mod backend;
+pub mod buffer;
+pub mod cache;
+pub mod chart;
+pub mod clipboard;
+pub mod diagnostics;
+pub mod event;
+pub mod filter;
+pub mod fuzzy;
+pub mod gesture;
+#[cfg(unix)]
+pub mod guard;
+pub mod input;
+pub mod keymap;
+pub mod latency;
+pub mod layout;
+pub mod quirks;
+pub mod record;
+pub mod rect;
 pub mod screen;
+pub mod selection;
+pub mod shared;
+pub mod shell;
+#[cfg(unix)]
+mod signal;
+#[cfg(all(unix, feature = "async"))]
+pub mod stream;
+pub mod style;
+pub mod testing;
+pub mod text;
+pub mod theme;
+pub mod transcode;
+pub mod vt;
+pub mod widgets;
+pub mod window;
+pub mod wm;
 //mod bindings;
 
+pub use app::run_app;
+
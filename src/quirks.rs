@@ -0,0 +1,150 @@
+//! Working around what terminal multiplexers (tmux and GNU screen) can't pass through.
+//!
+//! A multiplexer sits between the app and the real terminal, so it doesn't matter what the terminal
+//! supports if the multiplexer drops or mangles the escape sequence. The Screen checks `Quirks` before
+//! using those features, and leaves them out (or falls back to something simpler) where they'd break.
+//! Sequences meant for the terminal itself (like its title) can also be wrapped up so the multiplexer
+//! passes them on untouched, see `Multiplexer::passthrough`.
+
+// GNU screen drops passthrough strings longer than this, so longer ones go in pieces
+const SCREEN_CHUNK: usize = 768;
+
+/// The multiplexer the app is running under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+	/// Straight on the terminal
+	None,
+	/// tmux, with its (major, minor) version if it could be told. Only 3.2 and later say which version they are
+	Tmux(Option<(u16, u16)>),
+	/// GNU screen
+	Screen,
+}
+
+impl Multiplexer {
+	/// Works out the multiplexer from the environment.
+	pub fn detect() -> Multiplexer {
+		let var = |name: &str| ::std::env::var(name).unwrap_or_default();
+		if !var("TMUX").is_empty() {
+			let version = if var("TERM_PROGRAM") == "tmux" { parse_version(&var("TERM_PROGRAM_VERSION")) } else { None };
+			Multiplexer::Tmux(version)
+		} else if !var("STY").is_empty() || var("TERM").starts_with("screen") {
+			Multiplexer::Screen
+		} else {
+			Multiplexer::None
+		}
+	}
+	/// Wraps the escape sequence `seq` up so the multiplexer hands it to the terminal outside of it as
+	/// is, instead of acting on it (or dropping it) itself. Without a multiplexer it's left alone.
+	///
+	/// NOTE: tmux only passes these on with its `allow-passthrough` option on, which is off by default
+	/// since 3.3.
+	pub fn passthrough(&self, seq: &str) -> String {
+		match *self {
+			Multiplexer::None => seq.to_string(),
+			// Escapes inside get doubled
+			Multiplexer::Tmux(_) => format!("\x1bPtmux;{}\x1b\\", seq.replace('\x1b', "\x1b\x1b")),
+			Multiplexer::Screen => {
+				let mut out = String::new();
+				let mut rest = seq;
+				while !rest.is_empty() {
+					let mut end = rest.len().min(SCREEN_CHUNK);
+					while !rest.is_char_boundary(end) {
+						end -= 1;
+					}
+					out.push_str("\x1bP");
+					out.push_str(&rest[..end]);
+					out.push_str("\x1b\\");
+					rest = &rest[end..];
+				}
+				out
+			},
+		}
+	}
+}
+
+/// Which features make it through to the terminal, see `Screen::set_quirks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+	/// Changing the cursor's shape (DECSCUSR), otherwise it's left alone
+	pub cursor_style: bool,
+	/// Synchronized output (mode 2026)
+	pub synchronized_output: bool,
+	/// Hyperlinks (OSC 8), otherwise linked text is plain
+	pub links: bool,
+	/// Setting the clipboard (OSC 52)
+	pub clipboard: bool,
+	/// RGB colors, otherwise they're drawn with the 256 color palette
+	pub true_color: bool,
+	/// Curly underlines (SGR 4:3), otherwise they're drawn as plain ones
+	pub curly_underline: bool,
+	/// SGR mouse reports (mode 1006), otherwise the older encoding is used, which can't report past
+	/// column 223
+	pub sgr_mouse: bool,
+	/// The multiplexer itself, for passing sequences through it
+	pub multiplexer: Multiplexer,
+}
+
+impl Quirks {
+	/// Nothing in the way, everything the terminal supports works.
+	pub fn none() -> Quirks {
+		Quirks {
+			cursor_style: true,
+			synchronized_output: true,
+			links: true,
+			clipboard: true,
+			true_color: true,
+			curly_underline: true,
+			sgr_mouse: true,
+			multiplexer: Multiplexer::None,
+		}
+	}
+	/// What makes it through `multiplexer`.
+	pub fn for_multiplexer(multiplexer: Multiplexer) -> Quirks {
+		match multiplexer {
+			Multiplexer::None => Quirks::none(),
+			Multiplexer::Tmux(version) => {
+				// Versions too old to say which they are are older than all of these
+				let since = |major, minor| version.is_some_and(|v| v >= (major, minor));
+				Quirks {
+					cursor_style: since(3, 0),
+					synchronized_output: since(3, 4),
+					links: since(3, 4),
+					clipboard: true,
+					true_color: true,
+					curly_underline: since(3, 0),
+					sgr_mouse: true,
+					multiplexer,
+				}
+			},
+			Multiplexer::Screen => Quirks {
+				cursor_style: false,
+				synchronized_output: false,
+				links: false,
+				// Passed through, see `Multiplexer::passthrough`
+				clipboard: true,
+				true_color: false,
+				curly_underline: false,
+				sgr_mouse: false,
+				multiplexer,
+			},
+		}
+	}
+	/// What makes it through the multiplexer the app is running under, if any.
+	pub fn detect() -> Quirks {
+		Quirks::for_multiplexer(Multiplexer::detect())
+	}
+}
+
+impl Default for Quirks {
+	fn default() -> Quirks {
+		Quirks::none()
+	}
+}
+
+/// Internal: Parses a version like "3.3a" into (3, 3)
+fn parse_version(text: &str) -> Option<(u16, u16)> {
+	let mut parts = text.split('.');
+	let major = parts.next()?.parse().ok()?;
+	let minor: String = parts.next().unwrap_or("0").chars().take_while(char::is_ascii_digit).collect();
+	Some((major, minor.parse().unwrap_or(0)))
+}
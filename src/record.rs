@@ -0,0 +1,309 @@
+//! Recording everything that's written to the terminal, and playing it back.
+//!
+//! Recordings are saved as asciicast (v2) files, which asciinema can play and upload. They also make
+//! good regression tests for drawing, since `Recording::output` is exactly what the terminal got.
+
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use ::screen::Screen;
+
+/// The recording being made, shared between the Screen and its output.
+pub type SharedRecording = Rc<RefCell<Option<Recording>>>;
+
+/// Something that happened during a recording.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+	/// Text (including escape sequences) was written to the terminal
+	Output(String),
+	/// The terminal changed size, to (height, width)
+	Resize(u16, u16),
+}
+
+/// Output written to the terminal, with when it was written, see `Screen::start_recording`.
+#[derive(Debug, Clone)]
+pub struct Recording {
+	height: u16,
+	width: u16,
+	/// When the recording started, None for ones that were loaded
+	started: Option<Instant>,
+	/// Seconds since the start, and what happened then
+	frames: Vec<(f64, Frame)>,
+	/// The start of a character that was split between writes
+	pending: Vec<u8>,
+}
+
+impl Recording {
+	/// Starts a recording on a terminal that's `height` by `width`.
+	pub fn new(height: u16, width: u16) -> Recording {
+		Recording {
+			height,
+			width,
+			started: Some(Instant::now()),
+			frames: Vec::new(),
+			pending: Vec::new(),
+		}
+	}
+	/// The size of the terminal when the recording started, as (height, width).
+	pub fn size(&self) -> (u16, u16) {
+		(self.height, self.width)
+	}
+	/// Everything that happened, with how many seconds into the recording it did.
+	pub fn frames(&self) -> &[(f64, Frame)] {
+		&self.frames
+	}
+	/// Everything that was written, in one string.
+	pub fn output(&self) -> String {
+		self.frames.iter().filter_map(|(_, f)| match *f {
+			Frame::Output(ref text) => Some(text.as_str()),
+			Frame::Resize(..) => None,
+		}).collect()
+	}
+	/// How long the recording is, in seconds.
+	pub fn duration(&self) -> f64 {
+		self.frames.last().map_or(0.0, |&(t, _)| t)
+	}
+	/// Records that `bytes` were written just now.
+	pub fn record_output(&mut self, bytes: &[u8]) {
+		self.pending.extend_from_slice(bytes);
+		// Hang on to a character that's cut off at the end until the rest of it shows up
+		let valid = match ::std::str::from_utf8(&self.pending) {
+			Ok(_) => self.pending.len(),
+			Err(e) if e.error_len().is_none() => e.valid_up_to(),
+			Err(_) => self.pending.len(),
+		};
+		let rest = self.pending.split_off(valid);
+		let text = String::from_utf8_lossy(&self.pending).into_owned();
+		self.pending = rest;
+		if !text.is_empty() {
+			let now = self.now();
+			self.frames.push((now, Frame::Output(text)));
+		}
+	}
+	/// Records that the terminal changed size just now.
+	pub fn record_resize(&mut self, height: u16, width: u16) {
+		let now = self.now();
+		self.frames.push((now, Frame::Resize(height, width)));
+	}
+	/// The recording as an asciicast (v2) file.
+	pub fn to_asciicast(&self) -> String {
+		let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+		let term = ::std::env::var("TERM").unwrap_or_default();
+		let mut out = format!("{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}, \"env\": {{\"TERM\": {}}}}}\n",
+			self.width, self.height, timestamp, quote(&term));
+		for &(time, ref frame) in &self.frames {
+			let (code, data) = match *frame {
+				Frame::Output(ref text) => ("o", text.clone()),
+				Frame::Resize(height, width) => ("r", format!("{}x{}", width, height)),
+			};
+			out.push_str(&format!("[{:.6}, \"{}\", {}]\n", time, code, quote(&data)));
+		}
+		out
+	}
+	/// Parses an asciicast (v2) file. Events other than output and resizes are skipped.
+	/// If it isn't one, returns None
+	pub fn from_asciicast(text: &str) -> Option<Recording> {
+		let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+		let header = lines.next()?;
+		if header_field(header, "version")? != 2 {
+			return None;
+		}
+		let mut recording = Recording {
+			height: header_field(header, "height")? as u16,
+			width: header_field(header, "width")? as u16,
+			started: None,
+			frames: Vec::new(),
+			pending: Vec::new(),
+		};
+		for line in lines {
+			let rest = line.trim().strip_prefix('[')?;
+			let comma = rest.find(',')?;
+			let time: f64 = rest[..comma].trim().parse().ok()?;
+			// Playing it back couldn't wait that long
+			if !time.is_finite() || time < 0.0 {
+				return None;
+			}
+			let (code, rest) = unquote(rest[comma + 1..].trim_start())?;
+			let (data, _) = unquote(rest.trim_start().strip_prefix(',')?.trim_start())?;
+			let frame = match code.as_str() {
+				"o" => Frame::Output(data),
+				"r" => {
+					let (width, height) = data.split_once('x')?;
+					Frame::Resize(height.parse().ok()?, width.parse().ok()?)
+				},
+				_ => continue,
+			};
+			recording.frames.push((time, frame));
+		}
+		Some(recording)
+	}
+	/// Saves the recording as an asciicast (v2) file.
+	/// If it can't be written, returns None
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> Option<()> {
+		fs::write(path, self.to_asciicast()).ok()
+	}
+	/// Loads a recording from an asciicast (v2) file.
+	/// If it can't be read or parsed, returns None
+	pub fn load<P: AsRef<Path>>(path: P) -> Option<Recording> {
+		Recording::from_asciicast(&fs::read_to_string(path).ok()?)
+	}
+	/// Writes the recording to `out` at `speed` times the speed it was recorded at, waiting between
+	/// frames like the original did. Resizes can't be played back, so they're skipped.
+	pub fn play<W: Write>(&self, out: &mut W, speed: f64) -> io::Result<()> {
+		let start = Instant::now();
+		for &(time, ref frame) in &self.frames {
+			if let Frame::Output(ref text) = *frame {
+				// Too far off to wait for is as good as never
+				let due = Duration::try_from_secs_f64((time / speed.max(0.001)).max(0.0)).unwrap_or(Duration::MAX);
+				if let Some(wait) = due.checked_sub(start.elapsed()) {
+					thread::sleep(wait);
+				}
+				out.write_all(text.as_bytes())?;
+				out.flush()?;
+			}
+		}
+		Ok(())
+	}
+	/// Internal: Seconds since the recording started
+	fn now(&self) -> f64 {
+		self.started.map_or(0.0, |s| s.elapsed().as_secs_f64())
+	}
+}
+
+/// Passes everything written through to `inner`, recording it on the way if a recording is running.
+pub struct RecordingWriter<W: Write> {
+	inner: W,
+	recording: SharedRecording,
+}
+
+impl<W: Write> RecordingWriter<W> {
+	pub fn new(inner: W, recording: SharedRecording) -> RecordingWriter<W> {
+		RecordingWriter { inner, recording }
+	}
+}
+
+impl<W: Write> Write for RecordingWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let n = self.inner.write(buf)?;
+		if let Some(ref mut recording) = *self.recording.borrow_mut() {
+			recording.record_output(&buf[..n]);
+		}
+		Ok(n)
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
+
+impl Screen {
+	/// Starts recording everything written to the terminal, replacing any recording that was running.
+	/// Everything gets drawn again on the next refresh, so the recording starts with the whole screen.
+	pub fn start_recording(&mut self) {
+		let (height, width) = self.terminal_size();
+		*self.recording().borrow_mut() = Some(Recording::new(height, width));
+		self.redraw();
+	}
+	/// Stops recording.
+	/// Returns the recording, or None if there wasn't one running
+	pub fn stop_recording(&mut self) -> Option<Recording> {
+		self.flush();
+		self.recording().borrow_mut().take()
+	}
+	/// Whether a recording is running.
+	pub fn is_recording(&self) -> bool {
+		self.recording().borrow().is_some()
+	}
+	/// Plays a recording back on the terminal at `speed` times the speed it was recorded at, blocking
+	/// until it's done. What was on screen gets drawn again on the next refresh.
+	///
+	/// NOTE: The recording is played as it is, so it should be from a terminal of the same size.
+	pub fn play_recording(&mut self, recording: &Recording, speed: f64) {
+		self.flush();
+		let mut out = PlaybackWriter(self);
+		recording.play(&mut out, speed).unwrap_or(());
+		self.redraw();
+	}
+}
+
+/// Internal: Writes straight through a Screen's output, for `play_recording`
+struct PlaybackWriter<'a>(&'a mut Screen);
+
+impl<'a> Write for PlaybackWriter<'a> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.0.write_raw(&String::from_utf8_lossy(buf));
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		self.0.flush();
+		Ok(())
+	}
+}
+
+/// Internal: `text` as a JSON string
+fn quote(text: &str) -> String {
+	let mut out = String::with_capacity(text.len() + 2);
+	out.push('"');
+	for ch in text.chars() {
+		match ch {
+			'"' => out.push_str("\\\""),
+			'\\' => out.push_str("\\\\"),
+			'\n' => out.push_str("\\n"),
+			'\r' => out.push_str("\\r"),
+			'\t' => out.push_str("\\t"),
+			c if (c as u32) < 0x20 || c == '\u{7f}' => out.push_str(&format!("\\u{:04x}", c as u32)),
+			c => out.push(c),
+		}
+	}
+	out.push('"');
+	out
+}
+
+/// Internal: Parses the JSON string at the start of `text`.
+/// Returns it and whatever comes after it, or None if there isn't one
+fn unquote(text: &str) -> Option<(String, &str)> {
+	let mut chars = text.strip_prefix('"')?.char_indices();
+	let mut out = String::new();
+	while let Some((i, ch)) = chars.next() {
+		match ch {
+			'"' => return Some((out, &text[i + 2..])),
+			'\\' => {
+				let escaped = match chars.next()?.1 {
+					'n' => '\n',
+					'r' => '\r',
+					't' => '\t',
+					'b' => '\u{8}',
+					'f' => '\u{c}',
+					'u' => {
+						let mut code = 0;
+						for _ in 0..4 {
+							code = code * 16 + chars.next()?.1.to_digit(16)?;
+						}
+						// Pairs of surrogates (for characters past U+FFFF) need putting back together
+						if (0xd800..0xdc00).contains(&code) {
+							let rest: String = chars.by_ref().take(6).map(|(_, c)| c).collect();
+							let low = u32::from_str_radix(rest.strip_prefix("\\u")?, 16).ok()?;
+							code = 0x10000 + ((code - 0xd800) << 10) + (low.checked_sub(0xdc00)?);
+						}
+						::std::char::from_u32(code)?
+					},
+					other => other,
+				};
+				out.push(escaped);
+			},
+			ch => out.push(ch),
+		}
+	}
+	None
+}
+
+/// Internal: A number from the header of an asciicast
+fn header_field(header: &str, name: &str) -> Option<u64> {
+	let key = format!("\"{}\"", name);
+	let rest = header[header.find(&key)? + key.len()..].trim_start().strip_prefix(':')?.trim_start();
+	let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+	digits.parse().ok()
+}
@@ -0,0 +1,68 @@
+/// A rectangular area, in cells.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+	/// The top line
+	pub y: u16,
+	/// The left-most column
+	pub x: u16,
+	/// The amount of lines
+	pub height: u16,
+	/// The amount of columns
+	pub width: u16,
+}
+
+impl Rect {
+	pub fn new(y: u16, x: u16, height: u16, width: u16) -> Rect {
+		Rect { y, x, height, width }
+	}
+	/// The line just below the rect.
+	pub fn bottom(&self) -> u16 {
+		self.y.saturating_add(self.height)
+	}
+	/// The column just right of the rect.
+	pub fn right(&self) -> u16 {
+		self.x.saturating_add(self.width)
+	}
+	/// Whether the rect has no cells at all.
+	pub fn is_empty(&self) -> bool {
+		self.height == 0 || self.width == 0
+	}
+	/// Whether (y, x) is inside of the rect.
+	pub fn contains(&self, y: u16, x: u16) -> bool {
+		y >= self.y && y < self.bottom() && x >= self.x && x < self.right()
+	}
+	/// Whether all of `other` is inside of the rect.
+	pub fn contains_rect(&self, other: &Rect) -> bool {
+		other.y >= self.y && other.bottom() <= self.bottom() && other.x >= self.x && other.right() <= self.right()
+	}
+	/// The area covered by both rects, or None if they don't overlap.
+	pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+		let y = self.y.max(other.y);
+		let x = self.x.max(other.x);
+		let bottom = self.bottom().min(other.bottom());
+		let right = self.right().min(other.right());
+		if bottom <= y || right <= x {
+			return None;
+		}
+		Some(Rect::new(y, x, bottom - y, right - x))
+	}
+	/// What's left of the rect once `other` is taken out of it, as up to four rects that don't overlap:
+	/// the full-width parts above and below `other`, then the parts beside it.
+	pub fn subtract(&self, other: &Rect) -> Vec<Rect> {
+		let cut = match self.intersect(other) {
+			Some(cut) => cut,
+			None => return if self.is_empty() { Vec::new() } else { vec![*self] },
+		};
+		let pieces = [
+			Rect::new(self.y, self.x, cut.y - self.y, self.width),
+			Rect::new(cut.bottom(), self.x, self.bottom() - cut.bottom(), self.width),
+			Rect::new(cut.y, self.x, cut.height, cut.x - self.x),
+			Rect::new(cut.y, cut.right(), cut.height, self.right() - cut.right()),
+		];
+		pieces.iter().filter(|r| !r.is_empty()).cloned().collect()
+	}
+	/// Moves the rect by (dy, dx).
+	pub fn offset(&self, dy: u16, dx: u16) -> Rect {
+		Rect::new(self.y.saturating_add(dy), self.x.saturating_add(dx), self.height, self.width)
+	}
+}
@@ -1,102 +1,907 @@
-use std::io::{stdout, Write};
-use std::os::unix::io::AsRawFd;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::mem;
+use std::io::{stdout, BufWriter, Write};
+use std::ops::BitOr;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicUsize};
+use std::time::{Duration, Instant};
 //use std::default::Default;
-use ::libc::{STDOUT_FILENO};
-use ::termios::{Termios, tcgetattr, tcsetattr, cfmakeraw};
+use ::acs::{self, LineDrawing};
+use ::backend::Backend;
+#[cfg(unix)]
+use ::backend::UnixBackend;
+#[cfg(windows)]
+use ::backend::WindowsBackend;
+use ::buffer::{Buffer, Cell, CONTINUATION};
+use ::cache::CapCache;
+use ::event::{Event, Key, MouseKind, Regions};
+use ::filter::Filters;
+#[cfg(unix)]
+use ::guard::CrashGuard;
+use ::rect::Rect;
+use ::shared::{DrawHandle, Shared};
+#[cfg(unix)]
+use ::signal;
+use ::style::{Attr, Color, ColorDepth, Link, Luminance, Style};
+use ::theme::Theme;
+use ::input::{Decoder, EscMode};
+use ::gesture::{GestureConfig, Gestures};
+use ::latency::LatencyStats;
+use ::quirks::{Multiplexer, Quirks};
+use ::record::{RecordingWriter, SharedRecording};
+use ::text::{char_width, str_width};
+use ::transcode::{SharedTranscoder, Transcoder, TranscodingWriter};
+use ::window::Window;
 
 // Because Rust won't escape "\033" in a string to 27
 const ESCAPE: char = 27 as char;
 const BEL: char = 7 as char;
-const IEXTEN: u32 = 0100000;
-const TCSANOW: i32 = 0;
+const IEXTEN: u32 = 0o100000;
 
-// This doesn't seem to be found in the `libc` crate, so just re-define it here anyway.
-const TIOCGWINSZ: ::libc::c_ulong = 0x00005413;
+// How long to wait for the terminal to answer a query before giving up on it
+const QUERY_TIMEOUT_MS: i32 = 500;
 
+// How many events can be waiting to be read, unless changed with `set_event_queue`
+const DEFAULT_QUEUE_LIMIT: usize = 1024;
+
+// How many of the events read last are kept, for `diagnostics::bundle`
+const RECENT_EVENTS: usize = 64;
+
+// Clearing the end of a line (or screen) with one sequence beats writing blanks over at least this many cells
+const CLEAR_MIN_CELLS: usize = 8;
+
+// Put in `front` for cells whose contents on the terminal aren't known, it never gets drawn so they never match
+const STALE: char = '\u{10ffff}';
+
+// There's no SIGWINCH on Windows, so while waiting for input the console's size gets checked this often (ms)
+#[cfg(windows)]
+const RESIZE_CHECK_MS: i32 = 100;
+
+// The id of the Screen that has the terminal, or 0 if none does. Two at once would each think they
+// had it to themselves, and the second would take the first one's settings as the ones to put back
+static OWNER: AtomicUsize = AtomicUsize::new(0);
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Something to run when the screen shuts down, see `Screen::on_shutdown`.
+pub type ShutdownHook = Box<dyn FnOnce(&mut Screen)>;
+
+/// The terminal, and everything that's drawn on it.
+///
+/// A Screen belongs to the thread that made it, other threads can draw through a `DrawHandle` (see `draw_handle`).
+/// Only one can be alive at a time, since there's only one terminal to set up and put back (Screens with
+/// no terminal behind them, like `TestScreen`'s, don't count).
 pub struct Screen {
+	/// Tells this Screen apart from others that have had the terminal, see `try_reacquire`
+	id: usize,
 	turn_on: String,
 	turn_off: String,
-	dims: TermDim,
+	/// The size of the terminal, see `size`
+	dims: Size,
+	/// Where the viewport is, while only the bottom of the terminal is used (see `Viewport::Inline`)
+	inline: Option<Inline>,
 	cur_pos: TermDim,
-	term_original: Termios,
-	term_settings: Termios,
-	term_descript: i32,
+	/// Positions saved with `save_cursor`
+	cursor_stack: Vec<TermDim>,
+	/// The terminal's settings, size and input
+	backend: Box<dyn Backend>,
 	cursor_state: CursorState,
+	/// The shape set with `set_cursor_style`, None if it's still the terminal's default
+	cursor_style: Option<CursorStyle>,
 	state_mode: ModeState,
+	/// What the terminal should look like after the next refresh
+	back: Buffer,
+	/// What the terminal currently looks like
+	front: Buffer,
+	watchdog: Option<Watchdog>,
+	last_check: Instant,
+	/// Where the cursor should be, while the watchdog waits for the terminal to say where it is
+	cursor_check: Option<(u16, u16)>,
+	/// Whether the terminal said the cursor was somewhere else, so the watchdog repaints
+	cursor_moved: bool,
+	last_repaint: Instant,
+	/// Whether `back` is a fixed size grid that gets scaled up, see `set_logical_size`
+	logical: bool,
+	/// The logical grid scaled up, kept so that it isn't allocated again on every refresh
+	scaled: Option<Buffer>,
+	/// Everything gets written here, and only reaches the terminal on `refresh` or `flush`
+	out: BufWriter<RecordingWriter<TranscodingWriter<Box<dyn Write>>>>,
+	/// Shared with `out`, see `start_recording`
+	recording: SharedRecording,
+	/// Shared with `out`, see `set_transcoder`
+	transcoder: SharedTranscoder,
+	caps: CapCache,
+	decoder: Decoder,
+	/// Events waiting to be read, see `set_event_queue`
+	queued: VecDeque<Event>,
+	/// Put back with `unget_event`, handed out before anything else (last one first)
+	ungot: Vec<Event>,
+	/// How many events `queued` can hold
+	queue_limit: usize,
+	overflow: OverflowPolicy,
+	/// How many events were dropped since the last `Event::Overflow`
+	dropped: usize,
+	/// The last few events handed out, oldest first, see `recent_events`
+	recent: VecDeque<Event>,
+	mouse_mode: MouseMode,
+	/// What was asked for with `set_keyboard_protocol`, if it's on
+	keyboard: Option<KeyboardFlags>,
+	/// Whether focus changes get reported, see `set_focus_reporting`
+	focus_reporting: bool,
+	/// What Ctrl-C does, None for whatever the mode does
+	signal_policy: Option<SignalPolicy>,
+	/// How SIGWINCH was handled before it was caught, to put back when done
+	#[cfg(unix)]
+	old_sigwinch: Option<::libc::sigaction>,
+	/// How SIGINT was handled before `SignalPolicy::Interrupt` caught it
+	#[cfg(unix)]
+	old_sigint: Option<::libc::sigaction>,
+	/// Whether stopping puts the terminal back first, see `set_job_control`
+	job_control: bool,
+	/// How SIGTSTP was handled before `set_job_control` caught it
+	#[cfg(unix)]
+	old_sigtstp: Option<::libc::sigaction>,
+	/// Clickable regions that are on screen
+	regions: Regions,
+	#[cfg(unix)]
+	crash_guard: Option<CrashGuard>,
+	/// Windows drawn from other threads, made the first time `draw_handle` is called
+	shared: Option<Arc<Shared>>,
+	/// Lines taken off the top (true) or bottom (false) with `reserve_top`/`reserve_bottom`
+	reserved: Vec<(bool, Window)>,
+	/// What each reserved window was when it was last copied into `back`, so unchanged ones can be
+	/// skipped. Only good while `back` is still what it was right after, see `reserved_back`
+	reserved_drawn: Vec<Option<((u64, u64), Rect)>>,
+	/// The generation of `back` right after the reserved windows were last copied into it
+	reserved_back: Option<(u64, u64)>,
+	/// What `write_str` writes into, made the first time it's needed
+	console: Option<Window>,
+	/// Areas that `draw` can't draw over, see `protect`. Ones that were unprotected are None
+	protected: Vec<Option<Rect>>,
+	/// Every page made with `new_page`, the one being shown is None since it lives in `back` and friends
+	pages: Vec<Option<Page>>,
+	page: usize,
+	filters: Filters,
+	/// How many colors the terminal can show, see `set_color_depth`
+	color_depth: ColorDepth,
+	/// Whether the terminal supports hyperlinks, see `set_link_support`
+	links: bool,
+	/// Whether the terminal draws curly underlines, see `set_curly_underline_support`
+	curly: bool,
+	/// Whether frames get wrapped in synchronized updates (mode 2026), None until it's been found out,
+	/// see `supports_synchronized_output`
+	sync: Option<bool>,
+	/// Whether the terminal was asked about synchronized output, so one that never answers isn't asked
+	/// again on every frame
+	sync_asked: bool,
+	/// The styles the app draws with, see `set_theme`
+	theme: Theme,
+	/// How line drawing characters get drawn, see `set_line_drawing`
+	line_drawing: LineDrawing,
+	/// What the multiplexer in the way (if any) lets through, see `set_quirks`
+	quirks: Quirks,
+	motion: MotionPolicy,
+	/// Recognizes mouse gestures, while they're on, see `set_gestures`
+	gestures: Option<Gestures>,
+	/// Run in order by `shutdown`, see `on_shutdown`
+	shutdown_hooks: Vec<ShutdownHook>,
+	/// Whether the terminal has already been put back
+	finished: bool,
+	/// Every timer from `set_timer`, as (id, how often, next tick)
+	timers: Vec<(usize, Duration, Instant)>,
+	/// How long `read_key` waits, None for forever
+	input_timeout: Option<Duration>,
+	/// Shown on top of `back`, bottom one first, see `show_popup`
+	popups: Vec<Popup>,
+	next_popup: usize,
+	/// What went into the frame that was last written, to skip ones that are the same
+	shown: Option<FrameKey>,
+	/// A hash of each line in `front`, as of when it was last written, see `write_changes`
+	line_hashes: Vec<Option<u64>>,
+	/// The title last set with `set_title`, if it hasn't been popped since
+	title: Option<String>,
+	/// How many titles have been pushed with `push_title` and not popped yet
+	title_pushes: usize,
+	/// How long frames took to answer input, while it's being measured, see `set_latency_tracking`
+	latency: Option<LatencyStats>,
+	/// When the input that no frame has answered yet came in
+	input_at: Option<Instant>,
+	/// The id of the buffer `front` was last written straight from, while nothing was on top of it.
+	/// Its clean lines don't need to be looked at
+	synced: Option<u64>,
+}
+
+/// What a page that isn't being shown looks like, see `Screen::new_page`.
+struct Page {
+	back: Buffer,
+	cur_pos: TermDim,
+	regions: Regions,
 }
 
 impl Screen {
+	/// Builds a Screen that draws to stdout.
+	/// If another Screen is alive, returns None (see `try_reacquire`), like the other constructors. That
+	/// can be told apart from stdout not being a terminal with `in_use`
 	pub fn new() -> Option<Screen> {
-		// TODO: Hard-code as little stuff as possible, return None if unable to get something that we require
-		
+		Screen::with_backend(stdout(), std_backend()?)
+	}
+	/// Builds a Screen that draws to `file`, e.g. `/dev/tty` opened explicitly while stdout is redirected.
+	#[cfg(unix)]
+	pub fn from_fd<T: Write + AsRawFd + 'static>(file: T) -> Option<Screen> {
+		let descriptor = file.as_raw_fd();
+		Screen::with_output(file, descriptor)
+	}
+	/// Builds a Screen that writes to `writer`, using `descriptor` to query and set up the terminal.
+	/// `descriptor` has to stay open for as long as the Screen is alive.
+	#[cfg(unix)]
+	pub fn with_output<W: Write + 'static>(writer: W, descriptor: RawFd) -> Option<Screen> {
 		// Check if the output is a terminal, if not then it's impossible to build Screen
-		if unsafe { ::libc::isatty(::libc::STDOUT_FILENO as i32) } == 0 {
+		let backend = UnixBackend::new(descriptor, descriptor)?;
+		Screen::with_backend(writer, Box::new(backend))
+	}
+	/// Builds a Screen that draws to stdout, in just the bottom `rows` rows of the terminal, see `Viewport::Inline`.
+	pub fn new_inline(rows: u16) -> Option<Screen> {
+		Screen::with_viewport(stdout(), std_backend()?, Viewport::Inline(rows))
+	}
+	/// Builds a Screen that draws to stdout, like `new`, with a helper process that puts the terminal back
+	/// if this one dies without cleaning up (see `spawn_crash_guard`).
+	/// If the helper can't be started, returns None too
+	#[cfg(unix)]
+	pub fn new_guarded() -> Option<Screen> {
+		let mut screen = Screen::new()?;
+		screen.spawn_crash_guard()?;
+		Some(screen)
+	}
+	/// Builds a Screen that writes to `writer`, and goes through `backend` for everything else about the
+	/// terminal (see `Backend`).
+	/// If the terminal's size can't be found out, returns None
+	pub fn with_backend<W: Write + 'static>(writer: W, backend: Box<dyn Backend>) -> Option<Screen> {
+		Screen::with_viewport(writer, backend, Viewport::Fullscreen)
+	}
+	/// Like `with_backend`, but only takes over the part of the terminal that `viewport` says.
+	pub fn with_viewport<W: Write + 'static>(writer: W, backend: Box<dyn Backend>, viewport: Viewport) -> Option<Screen> {
+		Screen::build(writer, backend, viewport, false)
+	}
+	/// Like `new`, but takes the terminal even if another Screen has it, for when that one is never going
+	/// to be dropped (e.g. it was leaked with `mem::forget`, or is stuck in a reference cycle). The other
+	/// Screen leaves the terminal alone from then on, even when it's dropped, and mustn't be drawn with.
+	///
+	/// Either way the terminal gets put back the way it was before the first Screen, not how the other
+	/// Screen left it.
+	/// If the terminal can't be set up, returns None
+	pub fn try_reacquire() -> Option<Screen> {
+		Screen::build(stdout(), std_backend()?, Viewport::Fullscreen, true)
+	}
+	/// Whether a Screen has the terminal, in which case no other can be built until it's dropped.
+	pub fn in_use() -> bool {
+		OWNER.load(atomic::Ordering::SeqCst) != 0
+	}
+	/// Internal: Builds a Screen, see `with_viewport`. `take_over` takes the terminal from any other Screen
+	/// that has it, instead of giving up
+	fn build<W: Write + 'static>(writer: W, backend: Box<dyn Backend>, viewport: Viewport, take_over: bool) -> Option<Screen> {
+		// TODO: Hard-code as little stuff as possible, return None if unable to get something that we require
+
+		// Get as much info as possible and then build Screen
+		let (rows, cols) = backend.size()?;
+		let dims = Size::new(rows, cols);
+
+		let id = NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed);
+		// Screens with no terminal behind them can't get in each other's way
+		let terminal = backend.is_terminal();
+		if terminal && take_over {
+			OWNER.store(id, atomic::Ordering::SeqCst);
+		} else if terminal && OWNER.compare_exchange(0, id, atomic::Ordering::SeqCst, atomic::Ordering::SeqCst).is_err() {
 			return None;
 		}
-		
-		// Get as much info as possible and then build Screen
-		let dims = TermDim::query().unwrap();
-		
-		let fd = match ::std::fs::File::open("/dev/tty") {
-			Ok(e) => e,
-			_ => return None
-		};
-		let term_state = match Termios::from_fd(fd.as_raw_fd()) { Ok(e) => e, _ => return None };
+
+		let transcoder = SharedTranscoder::default();
+		let recording: SharedRecording = Rc::new(RefCell::new(None));
 
 		let mut out = Screen {
+			id,
 			turn_on: format!("{0}7{0}[?1049h", ESCAPE),
 			turn_off: format!("{0}[2J{0}[?1049l{0}8", ESCAPE),
-			dims: dims,
+			back: Buffer::new(dims.rows, dims.cols),
+			front: Buffer::new(dims.rows, dims.cols),
+			logical: false,
+			scaled: None,
+			watchdog: None,
+			last_check: Instant::now(),
+			cursor_check: None,
+			cursor_moved: false,
+			last_repaint: Instant::now(),
+			out: BufWriter::new(RecordingWriter::new(TranscodingWriter::new(Box::new(writer), transcoder.clone()), recording.clone())),
+			recording,
+			transcoder,
+			caps: CapCache::load(),
+			decoder: Decoder::new(),
+			queued: VecDeque::new(),
+			ungot: Vec::new(),
+			queue_limit: DEFAULT_QUEUE_LIMIT,
+			overflow: OverflowPolicy::DropOldest,
+			dropped: 0,
+			recent: VecDeque::new(),
+			mouse_mode: MouseMode::Off,
+			keyboard: None,
+			focus_reporting: false,
+			signal_policy: None,
+			#[cfg(unix)]
+			old_sigwinch: None,
+			#[cfg(unix)]
+			old_sigint: None,
+			job_control: false,
+			#[cfg(unix)]
+			old_sigtstp: None,
+			regions: Regions::new(),
+			#[cfg(unix)]
+			crash_guard: None,
+			shared: None,
+			reserved: Vec::new(),
+			reserved_drawn: Vec::new(),
+			reserved_back: None,
+			console: None,
+			protected: Vec::new(),
+			pages: vec![None],
+			page: 0,
+			filters: Filters::new(),
+			color_depth: ColorDepth::detect(),
+			links: false,
+			curly: false,
+			sync: None,
+			sync_asked: false,
+			theme: Theme::builtin("dark").unwrap_or_default(),
+			line_drawing: LineDrawing::detect(),
+			quirks: Quirks::detect(),
+			motion: MotionPolicy::Coalesce,
+			gestures: None,
+			shutdown_hooks: Vec::new(),
+			finished: false,
+			timers: Vec::new(),
+			input_timeout: None,
+			popups: Vec::new(),
+			next_popup: 0,
+			shown: None,
+			latency: None,
+			input_at: None,
+			line_hashes: Vec::new(),
+			title: None,
+			title_pushes: 0,
+			synced: None,
+			dims,
+			inline: None,
 			cur_pos: TermDim { height: 0, width: 0 },
-			term_original: term_state,
-			term_settings: term_state,
-			term_descript: ::libc::STDOUT_FILENO,
+			cursor_stack: Vec::new(),
+			backend,
 			cursor_state: CursorState::Blinking, // Should always be defaulted to "Blinking"
+			cursor_style: None,
 			state_mode: ModeState::Default,
 		};
 		
-		// For current reference http://cboard.cprogramming.com/linux-programming/158476-termios-examples.html
-		
-		// Grab a copy of the current Struct_termios
-		if tcgetattr(out.term_descript, &mut out.term_original).is_err() || tcgetattr(out.term_descript, &mut out.term_settings).is_err() {
-			println!("FAILURE!");
-			return None;
+		out.links = match out.caps.get("osc8") {
+			Some(value) => value == "yes",
+			None => guess_link_support() && out.quirks.links,
+		};
+		out.curly = match out.caps.get("curly") {
+			Some(value) => value == "yes",
+			None => guess_curly_support() && out.quirks.curly_underline,
+		};
+		if !out.quirks.true_color {
+			out.color_depth = out.color_depth.min(ColorDepth::Indexed);
 		}
-		
-		// Turn the alt screen on
-		print!("{}", out.turn_on);
+
+		// Find out about resizes
+		#[cfg(unix)]
+		if terminal {
+			out.old_sigwinch = signal::catch(::libc::SIGWINCH);
+		}
+
+		match viewport {
+			// Turn the alt screen on
+			Viewport::Fullscreen => write!(out.out, "{}", out.turn_on).unwrap_or(()),
+			Viewport::Inline(rows) => {
+				out.turn_on = String::new();
+				out.inline = Some(Inline { rows, origin: 0 });
+				// Finding out where the cursor is needs raw mode, or the answer would wait for enter
+				out.backend.set_raw().unwrap_or(());
+				out.place_inline(terminal);
+				out.backend.reset().unwrap_or(());
+			},
+		}
+		out.flush();
 		
 		Some(out)
 	}
+	/// Moves the cursor to (y, x), counting from 1 the way the terminal does (0 counts as 1 too).
+	/// NOTE: Everything else counts from 0, see `move_to`.
 	pub fn move_cursor(&mut self, y: u16, x: u16) {
+		self.move_to(y.saturating_sub(1), x.saturating_sub(1));
+	}
+	/// Moves the cursor to (y, x), counting from 0 like everything else.
+	pub fn move_to(&mut self, y: u16, x: u16) {
 		self.cur_pos.height = y;
 		self.cur_pos.width = x;
-		print!("{}[{};{}H", ESCAPE, y, x);
+		write!(self.out, "{}[{};{}H", ESCAPE, self.top() as u32 + y as u32 + 1, x as u32 + 1).unwrap_or(());
+	}
+	/// Moves the cursor `dy` rows down and `dx` columns right (up and left for negative ones), stopping
+	/// at the edges of the terminal. Like the rest of the moves below, this writes whichever escape
+	/// sequence is shortest, so it's cheaper than `move_to` for small steps.
+	pub fn move_rel(&mut self, dy: i32, dx: i32) {
+		let (y, x) = self.cursor_pos();
+		let max = (self.front.get_height().saturating_sub(1) as i32, self.front.get_width().saturating_sub(1) as i32);
+		let to = ((y as i32 + dy).clamp(0, max.0) as u16, (x as i32 + dx).clamp(0, max.1) as u16);
+		self.move_cursor_rel(to);
+	}
+	/// Moves the cursor to column `x` of the line it's on.
+	pub fn move_to_col(&mut self, x: u16) {
+		let y = self.cur_pos.height;
+		self.move_cursor_rel((y, x.min(self.front.get_width().saturating_sub(1))));
+	}
+	/// Moves the cursor `n` rows up, see `move_rel`.
+	pub fn cursor_up(&mut self, n: u16) {
+		self.move_rel(-(n as i32), 0);
+	}
+	pub fn cursor_down(&mut self, n: u16) {
+		self.move_rel(n as i32, 0);
+	}
+	pub fn cursor_left(&mut self, n: u16) {
+		self.move_rel(0, -(n as i32));
+	}
+	pub fn cursor_right(&mut self, n: u16) {
+		self.move_rel(0, n as i32);
+	}
+	/// Internal: Moves the cursor to `to` (y, x) from where it's tracked to be, the shortest way
+	fn move_cursor_rel(&mut self, to: (u16, u16)) {
+		let seq = cursor_motion(Some(self.cursor_pos()), to, self.front.get_width(), self.top());
+		write!(self.out, "{}", seq).unwrap_or(());
+		self.cur_pos.height = to.0;
+		self.cur_pos.width = to.1;
 	}
-	/// Sets the title of the terminal window.
-	pub fn set_title(&self, title: &str) {
-		print!("{}]2;{}{}", ESCAPE, title, BEL);
+	/// Where the cursor is, as (y, x).
+	///
+	/// This is where rcurses last put it, see `query_cursor_pos` for asking the terminal instead.
+	pub fn cursor_pos(&self) -> (u16, u16) {
+		(self.cur_pos.height, self.cur_pos.width)
+	}
+	/// Asks the terminal where the cursor really is, as (y, x), and starts tracking that position.
+	/// If the terminal doesn't answer in time, returns None
+	///
+	/// Any input that arrives while waiting is kept for `read_event`.
+	/// NOTE: Unless the terminal is in raw mode, the answer only shows up after enter is pressed.
+	pub fn query_cursor_pos(&mut self) -> Option<(u16, u16)> {
+		let (y, x) = self.query(&format!("{}[6n", ESCAPE), find_cursor_report)?;
+		// The terminal counts from 1
+		self.cur_pos.height = y.saturating_sub(1).saturating_sub(self.top());
+		self.cur_pos.width = x.saturating_sub(1);
+		Some(self.cursor_pos())
+	}
+	/// Remembers where the cursor is, so it can be put back with `restore_cursor`.
+	/// Saved positions stack up, each `restore_cursor` goes back one.
+	pub fn save_cursor(&mut self) {
+		let pos = self.cur_pos.clone();
+		self.cursor_stack.push(pos);
+	}
+	/// Moves the cursor back to the last position saved with `save_cursor`.
+	/// If nothing is saved, returns None
+	pub fn restore_cursor(&mut self) -> Option<()> {
+		let pos = self.cursor_stack.pop()?;
+		self.move_to(pos.height, pos.width);
+		Some(())
+	}
+	/// Clears the whole screen.
+	pub fn clear(&mut self) {
+		self.write_clear_all();
+		self.back.clear();
+		self.front.clear();
+		self.regions.clear();
+	}
+	/// Clears the line the cursor is on.
+	pub fn clear_line(&mut self) {
+		write!(self.out, "{}[2K", ESCAPE).unwrap_or(());
+		let (y, _) = self.cursor_pos();
+		self.back.clear_line(y);
+		self.front.clear_line(y);
+	}
+	/// Clears from the cursor to the end of its line.
+	pub fn clear_to_eol(&mut self) {
+		write!(self.out, "{}[K", ESCAPE).unwrap_or(());
+		let (y, x) = self.cursor_pos();
+		self.back.clear_to_eol(y, x);
+		self.front.clear_to_eol(y, x);
+	}
+	/// Clears from the cursor to the bottom of the screen.
+	pub fn clear_to_eos(&mut self) {
+		write!(self.out, "{}[J", ESCAPE).unwrap_or(());
+		let (y, x) = self.cursor_pos();
+		self.back.clear_to_eos(y, x);
+		self.front.clear_to_eos(y, x);
+	}
+	/// Sets the title of the terminal window. Under a multiplexer this sets the title of its pane, and is
+	/// passed through to the terminal outside of it too (see `Multiplexer::passthrough`).
+	///
+	/// The title from before is saved first (see `push_title`), and put back when the screen shuts down.
+	/// Control characters are left out of it, since they could end the escape sequence it's sent in.
+	pub fn set_title(&mut self, title: &str) {
+		if self.title_pushes == 0 {
+			self.push_title();
+		}
+		let title: String = title.chars().filter(|c| !c.is_control()).collect();
+		self.write_title_seq(&format!("{}]2;{}{}", ESCAPE, title, BEL));
+		self.title = Some(title);
+	}
+	/// Saves the title of the terminal window on the terminal's stack of titles (XTWINOPS 22), to be put
+	/// back with `pop_title`. Every title pushed is popped when the screen shuts down.
+	///
+	/// NOTE: Terminals that don't keep a stack of titles ignore this, so the title stays whatever was set last.
+	pub fn push_title(&mut self) {
+		self.write_title_seq(&format!("{}[22;0t", ESCAPE));
+		self.title_pushes += 1;
+	}
+	/// Puts back the title saved by the last `push_title` (XTWINOPS 23).
+	/// If there aren't any left, returns None
+	pub fn pop_title(&mut self) -> Option<()> {
+		self.title_pushes = self.title_pushes.checked_sub(1)?;
+		self.write_title_seq(&format!("{}[23;0t", ESCAPE));
+		self.title = None;
+		Some(())
+	}
+	/// Internal: Writes a sequence that changes the title, passing it through the multiplexer (if any) too
+	fn write_title_seq(&mut self, seq: &str) {
+		write!(self.out, "{}", seq).unwrap_or(());
+		if self.quirks.multiplexer != Multiplexer::None {
+			self.write_passthrough(seq);
+		}
 	}
 	/// Sets the cursor's state.
 	pub fn set_cursor(&mut self, flag: CursorState) {
 		match flag {
 			CursorState::Solid => {
-				// TODO: Currently unimplemented
-				return;
+				if self.cursor_state.is_off() {
+					write!(self.out, "{}[?25h", ESCAPE).unwrap_or(());
+				}
+				self.set_cursor_style(CursorStyle::SteadyBlock);
 			},
 			CursorState::Blinking => {
-				if !self.cursor_state.is_blinking() {
-					print!("{}[?25h", ESCAPE);
+				if self.cursor_state.is_off() {
+					write!(self.out, "{}[?25h", ESCAPE).unwrap_or(());
+				}
+				if self.cursor_state.is_solid() {
+					// Back to whatever blinking shape the user had
+					self.reset_cursor_style();
 				}
 			},
 			CursorState::Off => {
 				if !self.cursor_state.is_off() {
-					print!("{}[?25l", ESCAPE);
+					write!(self.out, "{}[?25l", ESCAPE).unwrap_or(());
 				}
 			}
 		}
 		self.cursor_state = flag;
 	}
+	/// Sets the shape of the cursor, e.g. a bar for insert mode and a block for normal mode.
+	/// Doesn't change whether the cursor is shown.
+	///
+	/// NOTE: Terminals that don't support DECSCUSR ignore this, and it's left out entirely where a
+	/// multiplexer is known not to pass it on (see `set_quirks`).
+	pub fn set_cursor_style(&mut self, style: CursorStyle) {
+		if !self.quirks.cursor_style {
+			return;
+		}
+		write!(self.out, "{}[{} q", ESCAPE, style.code()).unwrap_or(());
+		if !self.cursor_state.is_off() {
+			self.cursor_state = if style.is_blinking() { CursorState::Blinking } else { CursorState::Solid };
+		}
+		self.cursor_style = Some(style);
+	}
+	/// Puts the cursor back to the terminal's default shape.
+	pub fn reset_cursor_style(&mut self) {
+		if !self.quirks.cursor_style {
+			return;
+		}
+		write!(self.out, "{}[0 q", ESCAPE).unwrap_or(());
+		if self.cursor_state.is_solid() {
+			self.cursor_state = CursorState::Blinking;
+		}
+		self.cursor_style = None;
+	}
+	/// Sets how text gets converted for terminals that don't use UTF-8, both for drawing and for input.
+	/// `None` means the terminal is UTF-8 (the default).
+	///
+	/// `transcode::from_locale` picks one based on the environment.
+	pub fn set_transcoder(&mut self, transcoder: Option<Box<dyn Transcoder>>) {
+		// Anything already written has to go out in the old encoding
+		self.flush();
+		*self.transcoder.borrow_mut() = transcoder;
+	}
+	/// Forks a tiny helper process that restores the terminal if this process dies without dropping the
+	/// Screen (SIGKILL, the OOM killer, `process::exit`, ...), so the user's shell is never left broken.
+	/// If the helper can't be started, returns None
+	#[cfg(unix)]
+	pub fn spawn_crash_guard(&mut self) -> Option<()> {
+		if self.crash_guard.is_some() {
+			return Some(());
+		}
+		// Undo everything we might turn on, since the helper can't know what's on by the time we die
+		let restore = format!("{0}[0m{0}[0 q{0}[?25h{0}[?1003l{0}[?1002l{0}[?1000l{0}[?1006l{0}[?1004l{1}", ESCAPE, self.turn_off);
+		let (fd, original) = self.backend.original()?;
+		self.crash_guard = Some(CrashGuard::spawn(fd, original, restore.into_bytes())?);
+		Some(())
+	}
+	/// Sets which mouse actions get reported as events.
+	pub fn set_mouse(&mut self, flag: MouseMode) {
+		// Turn everything off first, the modes don't replace each other
+		write!(self.out, "{0}[?1003l{0}[?1002l{0}[?1000l{0}[?1006l", ESCAPE).unwrap_or(());
+		let mode = match flag {
+			MouseMode::Off => None,
+			MouseMode::Clicks => Some(1000),
+			MouseMode::Drag => Some(1002),
+			MouseMode::Motion => Some(1003),
+		};
+		if let Some(mode) = mode {
+			write!(self.out, "{}[?{}h", ESCAPE, mode).unwrap_or(());
+			// 1006 makes the terminal use the SGR encoding, which isn't limited to 223 columns
+			if self.quirks.sgr_mouse {
+				write!(self.out, "{}[?1006h", ESCAPE).unwrap_or(());
+			}
+		}
+		self.mouse_mode = flag;
+	}
+	/// Sets whether the terminal reports gaining and losing focus, as `Event::FocusGained` and
+	/// `Event::FocusLost`. E.g. for pausing animations while nobody's looking.
+	///
+	/// NOTE: Terminals that don't support it just never send them. Under tmux they only come with its
+	/// `focus-events` option on.
+	pub fn set_focus_reporting(&mut self, on: bool) {
+		write!(self.out, "{}[?1004{}", ESCAPE, if on { 'h' } else { 'l' }).unwrap_or(());
+		self.flush();
+		self.focus_reporting = on;
+	}
+	/// Whether focus changes get reported, see `set_focus_reporting`.
+	pub fn focus_reporting(&self) -> bool {
+		self.focus_reporting
+	}
+	/// Turns on the kitty keyboard protocol with `flags`, so that keys come in without any ambiguity: Esc
+	/// doesn't have to be told apart from the start of an escape sequence by waiting, and every modifier
+	/// (and key releases, if asked for) gets reported, see `Event::Keyboard`. If it's already on, the
+	/// flags are changed.
+	/// If the terminal doesn't support it (it gets asked, unless the answer is in the capability cache),
+	/// returns None and keys keep coming in the usual way
+	pub fn set_keyboard_protocol(&mut self, flags: KeyboardFlags) -> Option<()> {
+		if self.keyboard.is_some() {
+			write!(self.out, "{}[={}u", ESCAPE, flags.0).unwrap_or(());
+		} else {
+			// Keys read along with the answer get decoded right away, so they have to be decoded the new way
+			self.decoder.set_kitty_keyboard(true);
+			let supported = match self.caps.get("kitty-keyboard") {
+				Some(value) => Some(value == "yes"),
+				None => self.query(&format!("{0}[?u{0}[c", ESCAPE), find_keyboard_reply),
+			};
+			if let Some(supported) = supported {
+				self.caps.set("kitty-keyboard", if supported { "yes" } else { "no" });
+			}
+			if supported != Some(true) {
+				self.decoder.set_kitty_keyboard(false);
+				return None;
+			}
+			write!(self.out, "{}[>{}u", ESCAPE, flags.0).unwrap_or(());
+		}
+		self.flush();
+		self.keyboard = Some(flags);
+		Some(())
+	}
+	/// Turns the kitty keyboard protocol back off.
+	pub fn reset_keyboard_protocol(&mut self) {
+		if self.keyboard.take().is_some() {
+			write!(self.out, "{}[<u", ESCAPE).unwrap_or(());
+			self.flush();
+		}
+		self.decoder.set_kitty_keyboard(false);
+	}
+	/// What was asked for with `set_keyboard_protocol`, or None if it's off.
+	pub fn keyboard_protocol(&self) -> Option<KeyboardFlags> {
+		self.keyboard
+	}
+	/// Sets how long a lone ESC waits for the rest of an escape sequence before it's read as the ESC key,
+	/// see `Decoder::set_escape_timeout`.
+	pub fn set_escape_timeout(&mut self, timeout: Duration) {
+		self.decoder.set_escape_timeout(timeout);
+	}
+	pub fn escape_timeout(&self) -> Duration {
+		self.decoder.escape_timeout()
+	}
+	/// Sets what ESC followed by another key means, see `EscMode`.
+	pub fn set_esc_mode(&mut self, mode: EscMode) {
+		self.decoder.set_esc_mode(mode);
+	}
+	pub fn esc_mode(&self) -> EscMode {
+		self.decoder.esc_mode()
+	}
+	/// Makes the terminal sending `seq` read as `event`, see `Decoder::register`.
+	pub fn register_sequence<E: Into<Event>>(&mut self, seq: &str, event: E) {
+		self.decoder.register(seq, event);
+	}
+	/// Forgets a sequence added with `register_sequence`.
+	pub fn unregister_sequence(&mut self, seq: &str) {
+		self.decoder.unregister(seq);
+	}
+	/// Sets whether input that isn't understood is read as `Event::Unknown`, instead of dropped (the default).
+	pub fn set_strict_input(&mut self, strict: bool) {
+		self.decoder.set_strict(strict);
+	}
+	/// Sets whether input that isn't understood gets kept for `diagnostics::bundle`, see `Decoder::set_log_unknown`.
+	pub fn set_log_unknown_input(&mut self, on: bool) {
+		self.decoder.set_log_unknown(on);
+	}
+	/// The latest input that wasn't understood, while it's being logged (see `set_log_unknown_input`).
+	pub fn unknown_input(&self) -> impl Iterator<Item = &[u8]> {
+		self.decoder.unknown_input()
+	}
+	/// Waits for the next event.
+	/// If reading from the terminal fails, returns None
+	///
+	/// NOTE: Unless the terminal is in raw mode, nothing shows up until enter is pressed.
+	pub fn read_event(&mut self) -> Option<Event> {
+		self.wait_event(None)
+	}
+	/// Waits up to `timeout` for the next event.
+	/// If nothing happens in time or reading from the terminal fails, returns None
+	pub fn poll_event(&mut self, timeout: Duration) -> Option<Event> {
+		self.wait_event(Some(Instant::now() + timeout))
+	}
+	/// Puts `event` back, so that it's the next one read, like ncurses' `ungetch`. Putting back several
+	/// means they get read in the opposite order, last one first.
+	///
+	/// NOTE: It's handed back as is, without going through the filters or hit regions again.
+	pub fn unget_event(&mut self, event: Event) {
+		self.ungot.push(event);
+	}
+	/// Reads `text` as if it was typed, after whatever else is waiting to be read. Escape sequences in it
+	/// work too, so it can replay keys (for macros, or repeating the last command), or drive an app in a
+	/// test through the real terminal. The events go through the usual filters and hit regions.
+	pub fn feed_input(&mut self, text: &str) {
+		let mut decoder = self.decoder.blank();
+		decoder.feed(text.as_bytes());
+		// There's nothing more coming, so a lone ESC at the end is the key
+		while let Some(event) = decoder.next_event().or_else(|| decoder.flush()) {
+			self.push_event(event);
+		}
+	}
+	/// Sets how long `read_key` waits for a key, like ncurses' `timeout`. None waits forever (the default),
+	/// and zero doesn't wait at all, only returning keys that were already pressed.
+	pub fn set_input_timeout(&mut self, timeout: Option<Duration>) {
+		self.input_timeout = timeout;
+	}
+	/// How long `read_key` waits for a key, see `set_input_timeout`.
+	pub fn input_timeout(&self) -> Option<Duration> {
+		self.input_timeout
+	}
+	/// Waits for the next key press, up to the input timeout (see `set_input_timeout`). For game loops
+	/// and the like, that need to get on with drawing whether or not a key was pressed.
+	/// If no key is pressed in time or reading from the terminal fails, returns None
+	///
+	/// NOTE: Other events that come in while waiting are dropped.
+	pub fn read_key(&mut self) -> Option<Key> {
+		let deadline = self.input_timeout.map(|timeout| Instant::now() + timeout);
+		loop {
+			if let Event::Key(key) = self.wait_event(deadline)? {
+				return Some(key);
+			}
+		}
+	}
+	/// Starts a timer that sends `Event::Tick(id)` every `every`, replacing any timer with the same id.
+	/// Ticks come out of `read_event` and `poll_event`, no other thread needed.
+	///
+	/// NOTE: If the app falls behind, missed ticks aren't made up for, there's only ever one waiting.
+	pub fn set_timer(&mut self, every: Duration, id: usize) {
+		self.cancel_timer(id);
+		self.timers.push((id, every, Instant::now() + every));
+	}
+	/// Stops a timer started with `set_timer`.
+	pub fn cancel_timer(&mut self, id: usize) {
+		self.timers.retain(|&(i, _, _)| i != id);
+	}
+	/// Internal: Waits for the next event, until `deadline` if there is one
+	fn wait_event(&mut self, deadline: Option<Instant>) -> Option<Event> {
+		loop {
+			if let Some(event) = self.next_decoded(false) {
+				return Some(event);
+			}
+			let now = Instant::now();
+			if let Some(id) = self.due_timer(now) {
+				// Through the queue so the filters see it too
+				self.push_event(Event::Tick(id));
+				continue;
+			}
+			if deadline.is_some_and(|deadline| now >= deadline) {
+				// Still take in what was already typed, so a timeout of zero doesn't miss it
+				if self.wait_input(0, true)? && self.read_input()? > 0 {
+					continue;
+				}
+				return None;
+			}
+			// Half of an escape sequence is waiting, only wait a bit for the rest of it
+			let escape = if self.has_partial_input() { self.escape_timeout().as_millis().min(i32::MAX as u128) as i32 } else { -1 };
+			let until = self.timers.iter().map(|&(_, _, next)| next).chain(deadline).min();
+			let other = until.map_or(-1, |until| until.saturating_duration_since(now).as_millis().min(i32::MAX as u128) as i32 + 1);
+			let timeout = if escape < 0 || (other >= 0 && other < escape) { other } else { escape };
+			if !self.wait_input(timeout, true)? {
+				// Only give up on the rest of the escape sequence if that's what we were waiting on
+				if !self.queued.is_empty() || timeout != escape {
+					continue;
+				}
+				if let Some(event) = self.next_decoded(true) {
+					return Some(event);
+				}
+				continue;
+			}
+			if self.read_input()? == 0 {
+				// The end of input, so whatever is left of it is complete
+				return self.next_decoded(true);
+			}
+		}
+	}
+	/// Internal: The id of a timer that's due, which then gets set for its next tick
+	fn due_timer(&mut self, now: Instant) -> Option<usize> {
+		let timer = self.timers.iter_mut().filter(|t| t.2 <= now).min_by_key(|t| t.2)?;
+		timer.2 += timer.1;
+		if timer.2 <= now {
+			timer.2 = now + timer.1;
+		}
+		Some(timer.0)
+	}
+	/// Reads a line of text at the cursor, after showing `prompt`, with the usual editing keys: backspace,
+	/// Ctrl-W to delete a word, Ctrl-U to delete everything, and Enter to finish. Works in raw mode.
+	/// If it's cancelled (Esc, Ctrl-C, or Ctrl-D on an empty line) or reading fails, returns None
+	///
+	/// NOTE: Other events that come in while reading are dropped.
+	pub fn read_line(&mut self, prompt: &str) -> Option<String> {
+		let (y, x) = self.cursor_pos();
+		let width = self.back.get_width().saturating_sub(x);
+		let mut line = String::new();
+		loop {
+			// Keep the end of a long line in view, with room for the cursor after it
+			let mut skip = 0;
+			while skip < line.len() && str_width(prompt) + str_width(&line[skip..]) >= width as usize {
+				skip += line[skip..].chars().next().unwrap().len_utf8();
+			}
+			let mut win = Window::new(y, x, 1, width);
+			win.print(0, 0, prompt);
+			win.print(0, str_width(prompt).min(width as usize) as u16, &line[skip..]);
+			self.draw(&win);
+			let end = x + (str_width(prompt) + str_width(&line[skip..])).min(width.saturating_sub(1) as usize) as u16;
+			self.move_to(y, end);
+			self.refresh();
+
+			let key = match self.read_event()? {
+				Event::Key(key) => key,
+				_ => continue,
+			};
+			match key {
+				Key::Enter | Key::Ctrl('j') | Key::Ctrl('m') => {
+					if y + 1 < self.back.get_height() {
+						self.move_to(y + 1, 0);
+					}
+					return Some(line);
+				},
+				Key::Backspace | Key::Ctrl('h') => {
+					line.pop();
+				},
+				Key::Ctrl('w') => {
+					let trimmed = line.trim_end().len();
+					let start = line[..trimmed].rfind(' ').map_or(0, |i| i + 1);
+					line.truncate(start);
+				},
+				Key::Ctrl('u') => line.clear(),
+				Key::Esc | Key::Ctrl('c') => return None,
+				Key::Ctrl('d') if line.is_empty() => return None,
+				Key::Char(c) => line.push(c),
+				_ => {},
+			}
+		}
+	}
 	/// Attempts to set the terminal's mode.
 	/// If it fails, returns None
 	/// 
@@ -111,8 +916,7 @@ impl Screen {
 				None
 			},
 			ModeState::Raw => {
-				cfmakeraw(&mut self.term_settings);
-				self.update_term()
+				self.backend.set_raw().and_then(|_| self.apply_signal_policy())
 			}
 		};
 		if out.is_some() {
@@ -120,109 +924,2069 @@ impl Screen {
 		}
 		out
 	}
+	/// Sets what Ctrl-C does, whatever mode the terminal is in (see `SignalPolicy`). Until this is called
+	/// it's up to the mode: raw mode makes it a key, other modes leave it sending SIGINT.
+	/// If the terminal's settings can't be changed, returns None
+	pub fn set_signal_policy(&mut self, policy: SignalPolicy) -> Option<()> {
+		self.signal_policy = Some(policy);
+		self.apply_signal_policy()
+	}
+	/// What Ctrl-C does, if `set_signal_policy` was called.
+	pub fn signal_policy(&self) -> Option<SignalPolicy> {
+		self.signal_policy
+	}
+	/// Internal: Makes the terminal's settings and SIGINT's handler match the signal policy.
+	/// If the terminal's settings can't be changed, returns None
+	fn apply_signal_policy(&mut self) -> Option<()> {
+		let policy = match self.signal_policy {
+			Some(policy) => policy,
+			None => return Some(()),
+		};
+		#[cfg(unix)]
+		match (policy, self.old_sigint.is_some()) {
+			(SignalPolicy::Interrupt, false) => self.old_sigint = signal::catch(::libc::SIGINT),
+			(SignalPolicy::Interrupt, true) => {},
+			(_, _) => self.release_sigint(),
+		}
+		// Ctrl-C ends the process without the Screen getting dropped, so something else has to put the
+		// terminal back. If the helper can't be started, it's still better to let it through
+		#[cfg(unix)]
+		if policy == SignalPolicy::Raise && self.backend.is_terminal() {
+			self.spawn_crash_guard().unwrap_or(());
+		}
+		self.backend.set_signals(policy != SignalPolicy::Key)
+	}
+	/// Turns on putting the terminal back the way it was when the process gets stopped (by Ctrl-Z, when
+	/// the mode or `SignalPolicy` leaves it sending SIGTSTP), and setting it up again once it's continued,
+	/// see `suspend`. Until this is called, the process stops right away and leaves the terminal as it is.
+	///
+	/// NOTE: The stop waits for the next `read_event` (which handles it right away while waiting) or
+	/// `refresh`, so an app that's busy for a while without either stops late. Windows has no SIGTSTP,
+	/// so there this does nothing.
+	pub fn set_job_control(&mut self, on: bool) {
+		self.job_control = on;
+		#[cfg(unix)]
+		match (on, self.old_sigtstp.is_some()) {
+			(true, false) if self.backend.is_terminal() => self.old_sigtstp = signal::catch(::libc::SIGTSTP),
+			(false, true) => self.release_sigtstp(),
+			_ => {},
+		}
+	}
+	/// Whether stopping puts the terminal back first, see `set_job_control`.
+	pub fn job_control(&self) -> bool {
+		self.job_control
+	}
+	/// Internal: Puts back SIGTSTP's handler from before `set_job_control`, if it was caught
+	#[cfg(unix)]
+	fn release_sigtstp(&mut self) {
+		if let Some(old) = self.old_sigtstp.take() {
+			signal::restore(::libc::SIGTSTP, &old);
+		}
+	}
+	/// Internal: Puts back SIGINT's handler from before `SignalPolicy::Interrupt`, if it was caught
+	#[cfg(unix)]
+	fn release_sigint(&mut self) {
+		if let Some(old) = self.old_sigint.take() {
+			signal::restore(::libc::SIGINT, &old);
+		}
+	}
 	/// Sets the terminal to how it was when creating this
 	pub fn set_screen_default(&mut self) -> Option<()> {
-		self.term_settings = self.term_original;
-		self.update_term()
+		self.backend.reset()
+	}
+	/// Puts the terminal back the way it was and stops the process, like Ctrl-Z does in the shell.
+	/// Once the process is continued (e.g. with `fg`), the terminal is set up again and everything gets
+	/// repainted on the next refresh.
+	///
+	/// NOTE: In raw mode Ctrl-Z is just a key (`Key::Ctrl('z')`), so call this for it. Otherwise the
+	/// terminal sends SIGTSTP, which only comes here with `set_job_control`.
+	#[cfg(unix)]
+	pub fn suspend(&mut self) {
+		// Leave the terminal how we found it, without forgetting our own settings
+		write!(self.out, "{0}[0m{0}[?25h", ESCAPE).unwrap_or(());
+		if self.cursor_style.is_some() {
+			write!(self.out, "{}[0 q", ESCAPE).unwrap_or(());
+		}
+		write!(self.out, "{0}[?1003l{0}[?1002l{0}[?1000l{0}[?1006l", ESCAPE).unwrap_or(());
+		if self.keyboard.is_some() {
+			write!(self.out, "{}[<u", ESCAPE).unwrap_or(());
+		}
+		if self.focus_reporting {
+			write!(self.out, "{}[?1004l", ESCAPE).unwrap_or(());
+		}
+		for _ in 0..self.title_pushes {
+			self.write_title_seq(&format!("{}[23;0t", ESCAPE));
+		}
+		write!(self.out, "{}", self.turn_off).unwrap_or(());
+		self.flush();
+		self.backend.suspend().unwrap_or(());
+
+		// Stop for real, execution carries on from here after SIGCONT
+		signal::stop();
+
+		self.backend.resume().unwrap_or(());
+		write!(self.out, "{}", self.turn_on).unwrap_or(());
+		if self.cursor_state.is_off() {
+			write!(self.out, "{}[?25l", ESCAPE).unwrap_or(());
+		}
+		if let Some(style) = self.cursor_style {
+			write!(self.out, "{}[{} q", ESCAPE, style.code()).unwrap_or(());
+		}
+		let mouse = self.mouse_mode;
+		self.set_mouse(mouse);
+		if let Some(flags) = self.keyboard {
+			write!(self.out, "{}[>{}u", ESCAPE, flags.0).unwrap_or(());
+		}
+		if self.focus_reporting {
+			write!(self.out, "{}[?1004h", ESCAPE).unwrap_or(());
+		}
+		for _ in 0..self.title_pushes {
+			self.write_title_seq(&format!("{}[22;0t", ESCAPE));
+		}
+		if let Some(title) = self.title.clone() {
+			self.write_title_seq(&format!("{}]2;{}{}", ESCAPE, title, BEL));
+		}
+		// The terminal might have been resized while we were stopped
+		self.resize();
+		// Outside of raw mode the answer would be echoed, and wait for enter
+		let ask = self.can_query();
+		self.place_inline(ask);
+		self.repaint();
+		self.flush();
 	}
-	/// Temp: Just here in-case I need it.
-	pub fn flush(&self) {
-		stdout().flush().unwrap();
+	/// Sends everything written so far to the terminal, without drawing any changed cells.
+	pub fn flush(&mut self) {
+		span!("flush");
+		self.out.flush().unwrap_or(());
 	}
-	/// Internal: Attempts to set the termios struct
-	fn update_term(&mut self) -> Option<()> {
-		if tcsetattr(self.term_descript, TCSANOW, &self.term_settings).is_err() {
-			None
+	/// Copies the window's cells onto the screen. They won't show up until the next `refresh`.
+	/// Anything hanging off of the edge of the screen is cut off.
+	pub fn draw(&mut self, win: &Window) {
+		span!("draw");
+		if win.rect().intersect(&self.area()).is_none() {
+			// Off of the screen, e.g. after the terminal shrank
+			return;
+		}
+		let masked = self.masked();
+		if masked.is_empty() {
+			self.back.copy_from(win.buffer(), win.get_y(), win.get_x());
 		} else {
-			Some(())
+			self.back.copy_from_except(win.buffer(), win.get_y(), win.get_x(), &masked);
 		}
+		self.regions.add_window(win);
 	}
-}
-
-impl Drop for Screen {
-	fn drop(&mut self) {
-		self.set_cursor(CursorState::Blinking);
-		self.set_screen_default().unwrap_or(());
-		print!("{}", self.turn_off);
-		self.flush();
+	/// Like `draw`, but draws over protected areas too. For whatever owns them to update them with.
+	pub fn draw_protected(&mut self, win: &Window) {
+		self.back.copy_from(win.buffer(), win.get_y(), win.get_x());
+		self.regions.add_window(win);
 	}
-}
-
-#[derive(Debug, Default, Clone)]
-struct TermDim {
-	height: u16,
-	width: u16
-}
-impl TermDim {
-	#[inline]
-	/// Get the height (amount of lines) of the terminal
-	pub fn get_height(&self) -> u16 {
-		self.height
+	/// Writes text at the console's cursor the way it would go on a plain terminal, for porting tools
+	/// that print line by line: it wraps at the right edge, scrolls once it gets to the bottom, and
+	/// understands `\n`, `\r` and `\t` (see `Window::put_str`). The console is drawn straight away, and
+	/// the screen's cursor follows it.
+	/// If the text runs out of room, which only happens once scrolling is turned off, returns None
+	pub fn write_str(&mut self, text: &str) -> Option<()> {
+		self.console();
+		let mut console = self.console.take()?;
+		let out = console.put_str(text);
+		self.draw(&console);
+		let (y, x) = console.cursor();
+		self.move_to(console.get_y() + y, console.get_x() + x.min(console.get_width().saturating_sub(1)));
+		self.console = Some(console);
+		out
 	}
-	#[inline]
-	/// Get the width (amount of columns) of the terminal
-	pub fn get_width(&self) -> u16 {
-		self.width
+	/// Writes text and then a newline, see `write_str`.
+	pub fn writeln(&mut self, text: &str) -> Option<()> {
+		self.write_str(text)?;
+		self.write_str("\n")
 	}
-
-	/// Queries the size of the terminal
-	pub fn query() -> Option<TermDim> {
-		let fd = match ::std::fs::File::open("/dev/tty") {
-			Ok(e) => e,
-			_ => return None
-		};
-		
-		let ws = (0, 0);
-
-		if unsafe { ::libc::ioctl(fd.as_raw_fd(), TIOCGWINSZ, &ws) } < 0 {
-			// The query failed, return None
+	/// The window `write_str` writes into, which covers the layout area (see `layout_area`). It scrolls
+	/// to start with, which can be turned off with `set_scrollok`, and its style and tab stops can be
+	/// changed too.
+	pub fn console(&mut self) -> &mut Window {
+		let area = self.layout_area();
+		let console = self.console.get_or_insert_with(|| {
+			let mut win = Window::new(area.y, area.x, area.height, area.width);
+			win.set_scrollok(true);
+			win
+		});
+		// Keep up with resizes and reserved lines
+		if console.rect() != area {
+			console.move_to(area.y, area.x);
+			console.resize(area.height, area.width);
+		}
+		console
+	}
+	/// Protects an area of the screen from being drawn over (by `draw`, and so also shared windows),
+	/// e.g. for a status line that widgets shouldn't trample. It can still be drawn over with
+	/// `draw_protected`. Lines taken with `reserve_top` and `reserve_bottom` are always protected.
+	/// Returns an id for `unprotect`
+	///
+	/// NOTE: Modals and popups still go on top of it, since they're meant to be above everything.
+	pub fn protect(&mut self, rect: Rect) -> usize {
+		self.protected.push(Some(rect));
+		self.protected.len() - 1
+	}
+	/// Lets an area protected with `protect` be drawn over again.
+	/// If it isn't protected, returns None
+	pub fn unprotect(&mut self, id: usize) -> Option<()> {
+		self.protected.get_mut(id)?.take().map(|_| ())
+	}
+	/// Whether (y, x) is protected from being drawn over, see `protect`.
+	pub fn is_protected(&self, y: u16, x: u16) -> bool {
+		self.masked().iter().any(|r| r.contains(y, x))
+	}
+	/// Internal: Every protected area, including the reserved lines
+	fn masked(&self) -> Vec<Rect> {
+		self.protected.iter().flatten().cloned().chain(self.reserved.iter().map(|r| r.1.rect())).collect()
+	}
+	/// Draws a stack of windows, bottom one first, skipping the ones that would be completely covered
+	/// by the ones above them or are off of the screen.
+	/// Returns how many were drawn
+	pub fn draw_stack(&mut self, wins: &[&Window]) -> usize {
+		let visible = visible_windows(self.area(), wins);
+		for (win, _) in wins.iter().zip(&visible).filter(|&(_, &visible)| visible) {
+			self.draw(win);
+		}
+		visible.iter().filter(|&&v| v).count()
+	}
+	/// Internal: The whole drawing area
+	fn area(&self) -> Rect {
+		Rect::new(0, 0, self.back.get_height(), self.back.get_width())
+	}
+	/// Draws a window as a modal on top of everything drawn so far, with the effects in `effects`.
+	///
+	/// NOTE: This draws over what's under it for good, see `show_popup` for one that can be taken away again.
+	pub fn draw_modal(&mut self, win: &Window, effects: ModalEffects) {
+		layer(&mut self.back, win, effects);
+		self.regions.add_window(win);
+	}
+	/// Shows a window on top of everything else (including earlier popups) until it's dismissed, with the
+	/// effects in `effects`. Whatever the app draws keeps going underneath it, so dismissing it brings
+	/// that back without anything having to be drawn again. Shows on the next refresh.
+	/// Returns the id of the popup, see `popup` for changing what's in it
+	///
+	/// NOTE: While a dimming popup is up, clickable regions under it don't get mouse events.
+	pub fn show_popup(&mut self, win: Window, effects: ModalEffects) -> PopupId {
+		let id = PopupId(self.next_popup);
+		self.next_popup += 1;
+		let mut regions = Regions::new();
+		regions.add_window(&win);
+		self.popups.push(Popup { id, win, effects, regions });
+		id
+	}
+	/// The window of a popup, for drawing in it or moving it. Changes show on the next refresh.
+	/// If there's no popup with that id, returns None
+	pub fn popup(&mut self, id: PopupId) -> Option<&mut Window> {
+		self.popups.iter_mut().find(|p| p.id == id).map(|p| &mut p.win)
+	}
+	/// Takes a popup away, uncovering what was under it on the next refresh.
+	/// Returns its window, or None if there's no popup with that id
+	pub fn dismiss_popup(&mut self, id: PopupId) -> Option<Window> {
+		let i = self.popups.iter().position(|p| p.id == id)?;
+		Some(self.popups.remove(i).win)
+	}
+	/// Moves a popup on top of the others.
+	/// If there's no popup with that id, returns None
+	pub fn raise_popup(&mut self, id: PopupId) -> Option<()> {
+		let i = self.popups.iter().position(|p| p.id == id)?;
+		let popup = self.popups.remove(i);
+		self.popups.push(popup);
+		Some(())
+	}
+	/// Moves a popup under the others.
+	/// If there's no popup with that id, returns None
+	pub fn lower_popup(&mut self, id: PopupId) -> Option<()> {
+		let i = self.popups.iter().position(|p| p.id == id)?;
+		let popup = self.popups.remove(i);
+		self.popups.insert(0, popup);
+		Some(())
+	}
+	/// Every popup that's up, from the bottom one to the top one.
+	pub fn popups(&self) -> Vec<PopupId> {
+		self.popups.iter().map(|p| p.id).collect()
+	}
+	/// Internal: What's drawn with the popups layered on top, None if there aren't any
+	fn layered(&self) -> Option<Buffer> {
+		if self.popups.is_empty() {
 			return None;
 		}
-
-		Some(TermDim {
-			height: ws.0,
-			width: ws.1
-		})
+		let mut out = self.back.clone();
+		for popup in &self.popups {
+			layer(&mut out, &popup.win, popup.effects);
+		}
+		Some(out)
 	}
-}
-
-/// The possible states for the Cursor
-pub enum CursorState {
-	/// Cursor is Solid
-	Solid,
-	/// Cursor is Blinking (default)
-	Blinking,
-	/// Cursor is disabled
-	Off
-}
-impl CursorState {
-	pub fn is_solid(&self) -> bool {
-		match *self {
-			CursorState::Solid => true,
-			_ => false
+	/// Internal: What the next frame is made from
+	fn frame_key(&self) -> FrameKey {
+		FrameKey {
+			back: self.back.generation(),
+			front: self.front.generation(),
+			popups: self.popups.iter().map(|p| (p.win.buffer().generation(), p.win.rect(), p.effects)).collect(),
 		}
 	}
-	pub fn is_blinking(&self) -> bool {
-		match *self {
-			CursorState::Blinking => true,
-			_ => false
+	/// Internal: Turns mouse events inside of clickable regions into `Event::Region`s, popups first
+	fn check_regions(&mut self, event: Event) -> Event {
+		let mouse = match event {
+			Event::Mouse(mouse) => mouse,
+			_ => return event,
+		};
+		for popup in self.popups.iter_mut().rev() {
+			if popup.win.rect().contains(mouse.y, mouse.x) {
+				return popup.regions.check(event);
+			}
+			if popup.effects.dim {
+				// Everything under a modal is out of reach
+				return event;
+			}
 		}
+		self.regions.check(event)
 	}
-	pub fn is_off(&self) -> bool {
-		match *self {
-			CursorState::Off => true,
-			_ => false
+	/// Makes everything get drawn onto a fixed grid of `(height, width)` cells, which gets scaled up by a
+	/// whole number to fill as much of the terminal as it can, and centered. `None` goes back to drawing
+	/// straight onto the terminal.
+	///
+	/// Full blocks ('█') fill all of a scaled up cell, anything else is only put in its top-left corner.
+	/// Mouse events get reported in grid coordinates.
+	pub fn set_logical_size(&mut self, size: Option<(u16, u16)>) {
+		let (height, width) = size.unwrap_or((self.front.get_height(), self.front.get_width()));
+		self.logical = size.is_some();
+		self.scaled = None;
+		self.back = Buffer::new(height, width);
+		// Start over from a blank terminal, so nothing drawn at the old scale is left behind
+		self.clear();
+	}
+	/// Turns on periodic checks (done during `refresh`) that the terminal still shows what rcurses thinks it does,
+	/// repainting everything if something else wrote to it. `None` turns them off.
+	pub fn set_watchdog(&mut self, watchdog: Option<Watchdog>) {
+		self.last_check = Instant::now();
+		self.last_repaint = Instant::now();
+		self.watchdog = watchdog;
+	}
+	/// Gets a handle that other threads can draw windows through.
+	/// If it can't be set up, returns None
+	pub fn draw_handle(&mut self) -> Option<DrawHandle> {
+		if self.shared.is_none() {
+			self.shared = Some(Shared::new()?);
 		}
+		self.shared.as_ref().map(Shared::handle)
 	}
-}
-
-/// Possible modes for the terminal to be in.
-pub enum ModeState {
-	/// The default mode for the terminal, typed text will go to the screen.
-	Default,
-	/// Typed text will get sent *only* to the screen, not to the screen.  Expected keystrokes to send signals (e.g., ctrl+c for SIGINT) will still work.
-	Cbreak,
-	/// Like cbreak, but even the signal keystrokes will get sent to the program w/o sending the signal.
-	Raw,
-}
-
+	/// Internal: Draws the windows that came in through `DrawHandle`s, for the next refresh to show
+	fn draw_pending(&mut self) {
+		if let Some(shared) = self.shared.clone() {
+			for win in shared.take_pending() {
+				self.draw(&win);
+			}
+		}
+	}
+	/// Writes everything that changed since the last refresh to the terminal, then flushes the output.
+	/// Windows drawn through `DrawHandle`s since the last refresh are drawn first.
+	pub fn refresh(&mut self) {
+		span!("refresh");
+		self.draw_pending();
+		// Anything else that changed `back` might have been under them
+		let all = self.reserved_back != Some(self.back.generation());
+		self.reserved_drawn.resize(self.reserved.len(), None);
+		for ((_, win), drawn) in self.reserved.iter().zip(&mut self.reserved_drawn) {
+			let key = Some((win.buffer().generation(), win.rect()));
+			if all || *drawn != key {
+				self.back.copy_from(win.buffer(), win.get_y(), win.get_x());
+				*drawn = key;
+			}
+			self.regions.add_window(win);
+		}
+		self.reserved_back = Some(self.back.generation());
+		for popup in &mut self.popups {
+			// Picks up regions registered since it was shown, keeping track of which one is hovered
+			popup.regions.add_window(&popup.win);
+		}
+		#[cfg(unix)]
+		self.handle_signals();
+		// There's no SIGWINCH to say so, the console's size has to be checked
+		#[cfg(windows)]
+		self.resize();
+		self.run_watchdog();
+		// Apps that draw on every tick mostly draw the same thing, which doesn't need diffing again
+		let changed = self.shown.as_ref() != Some(&self.frame_key());
+		if changed {
+			// Have the terminal hold off on showing the frame until it's all there, so it never tears
+			let sync = self.supports_synchronized_output();
+			if sync {
+				write!(self.out, "{}[?2026h", ESCAPE).unwrap_or(());
+			}
+			self.write_changes();
+			if sync {
+				write!(self.out, "{}[?2026l", ESCAPE).unwrap_or(());
+			}
+			self.shown = Some(self.frame_key());
+		}
+		self.flush();
+		// Input that didn't change anything doesn't get answered by a later frame either
+		if let (Some(start), true) = (self.input_at.take(), changed) {
+			if let Some(ref mut stats) = self.latency {
+				stats.record(start.elapsed());
+			}
+		}
+	}
+	/// Turns measuring how long frames take to answer input on or off, see `LatencyStats`. Turning it
+	/// off throws the samples away.
+	pub fn set_latency_tracking(&mut self, on: bool) {
+		if on != self.latency.is_some() {
+			self.latency = if on { Some(LatencyStats::new()) } else { None };
+		}
+		self.input_at = None;
+	}
+	/// What's been measured, if latency tracking is on.
+	pub fn latency(&self) -> Option<&LatencyStats> {
+		self.latency.as_ref()
+	}
+	/// What's been measured, for clearing it. If latency tracking is off, returns None
+	pub fn latency_mut(&mut self) -> Option<&mut LatencyStats> {
+		self.latency.as_mut()
+	}
+	/// Takes `rows` lines off the top of the screen for a header, below any taken before.
+	/// Returns the id of its window (see `reserved`), or None if there aren't enough lines left
+	///
+	/// The window is drawn on every refresh, over anything else drawn there, and gets resized along
+	/// with the terminal. Use `layout_area` for the part of the screen that's left.
+	pub fn reserve_top(&mut self, rows: u16) -> Option<usize> {
+		self.reserve(true, rows)
+	}
+	/// Takes `rows` lines off the bottom of the screen for a footer, above any taken before.
+	/// Returns the id of its window, or None if there aren't enough lines left
+	pub fn reserve_bottom(&mut self, rows: u16) -> Option<usize> {
+		self.reserve(false, rows)
+	}
+	/// The window for lines taken with `reserve_top` or `reserve_bottom`.
+	pub fn reserved(&mut self, id: usize) -> Option<&mut Window> {
+		self.reserved.get_mut(id).map(|(_, win)| win)
+	}
+	/// How big the terminal is. It's kept up to date as the terminal gets resized (see `Event::Resize`).
+	///
+	/// NOTE: With `set_logical_size`, everything gets drawn onto a grid of a different size, see `layout_area`.
+	pub fn size(&self) -> Size {
+		self.dims
+	}
+	/// How many rows the terminal has, see `size`.
+	pub fn rows(&self) -> u16 {
+		self.dims.rows
+	}
+	/// How many columns the terminal has, see `size`.
+	pub fn cols(&self) -> u16 {
+		self.dims.cols
+	}
+	/// The part of the screen that isn't reserved, for laying out everything else in.
+	pub fn layout_area(&self) -> Rect {
+		let top: u16 = self.reserved.iter().filter(|r| r.0).map(|r| r.1.get_height()).sum();
+		let bottom: u16 = self.reserved.iter().filter(|r| !r.0).map(|r| r.1.get_height()).sum();
+		let height = self.back.get_height().saturating_sub(top + bottom);
+		Rect::new(top.min(self.back.get_height()), 0, height, self.back.get_width())
+	}
+	/// Internal: Reserves lines on the top or bottom
+	fn reserve(&mut self, top: bool, rows: u16) -> Option<usize> {
+		if rows == 0 || rows > self.layout_area().height {
+			return None;
+		}
+		self.reserved.push((top, Window::new(0, 0, rows, self.back.get_width())));
+		self.place_reserved();
+		Some(self.reserved.len() - 1)
+	}
+	/// Internal: Works out where the reserved lines go, and makes them as wide as the screen
+	fn place_reserved(&mut self) {
+		let (height, width) = (self.back.get_height(), self.back.get_width());
+		let (mut top, mut bottom) = (0, height);
+		for &mut (is_top, ref mut win) in &mut self.reserved {
+			let rows = win.get_height();
+			win.resize(rows, width);
+			if is_top {
+				win.move_to(top, 0);
+				top += rows;
+			} else {
+				bottom = bottom.saturating_sub(rows);
+				win.move_to(bottom, 0);
+			}
+		}
+	}
+	/// Makes a new, blank page to draw on, and returns its id. The screen starts out on page 0.
+	///
+	/// Every page keeps its own cells, cursor position and clickable regions, so switching between
+	/// them (see `switch_to`) doesn't need anything to be drawn again.
+	pub fn new_page(&mut self) -> usize {
+		self.pages.push(Some(Page {
+			back: Buffer::new(self.back.get_height(), self.back.get_width()),
+			cur_pos: TermDim::default(),
+			regions: Regions::new(),
+		}));
+		self.pages.len() - 1
+	}
+	/// Shows another page. Drawing goes to that page from now on, and the next refresh only writes the
+	/// cells that are different between the two.
+	/// If there's no page with that id, returns None
+	pub fn switch_to(&mut self, page: usize) -> Option<()> {
+		if page == self.page {
+			return Some(());
+		}
+		let mut next = self.pages.get_mut(page)?.take()?;
+		if (next.back.get_height(), next.back.get_width()) != (self.back.get_height(), self.back.get_width()) {
+			// The terminal was resized while it wasn't shown
+			let mut back = Buffer::new(self.back.get_height(), self.back.get_width());
+			back.copy_from(&next.back, 0, 0);
+			next.back = back;
+		}
+		let prev = Page {
+			back: ::std::mem::replace(&mut self.back, next.back),
+			cur_pos: self.cur_pos.clone(),
+			regions: ::std::mem::replace(&mut self.regions, next.regions),
+		};
+		self.pages[self.page] = Some(prev);
+		self.page = page;
+		self.move_to(next.cur_pos.height, next.cur_pos.width);
+		Some(())
+	}
+	/// The id of the page being shown.
+	pub fn current_page(&self) -> usize {
+		self.page
+	}
+	/// Overrides how many colors the terminal is thought to be able to show. Colors it can't show get
+	/// drawn as the nearest one it can.
+	///
+	/// NOTE: This is guessed from `COLORTERM` and `TERM` when the screen is made, see `ColorDepth::detect`.
+	pub fn set_color_depth(&mut self, depth: ColorDepth) {
+		self.color_depth = depth;
+		// Everything drawn with the old colors has to go
+		self.repaint();
+	}
+	/// Overrides how line drawing characters (borders, tables, see `acs`) get drawn, for terminals or
+	/// fonts that can't show Unicode's box drawing characters.
+	///
+	/// NOTE: This is guessed from the locale when the screen is made, see `LineDrawing::detect`.
+	pub fn set_line_drawing(&mut self, line_drawing: LineDrawing) {
+		self.line_drawing = line_drawing;
+		self.repaint();
+	}
+	/// How line drawing characters get drawn.
+	pub fn line_drawing(&self) -> LineDrawing {
+		self.line_drawing
+	}
+	/// Sets what happens to mouse motion that comes in faster than it's read, see `MotionPolicy`.
+	pub fn set_motion_policy(&mut self, policy: MotionPolicy) {
+		self.motion = policy;
+	}
+	/// Turns on recognizing double clicks, drags and wheel acceleration with the given thresholds, or
+	/// turns it off with None (the default). While it's on, mouse events come as gestures, see `Gestures`
+	/// for how they replace the raw ones. Drags need `MouseMode::Drag` or `MouseMode::Motion`.
+	pub fn set_gestures(&mut self, config: Option<GestureConfig>) {
+		match (config, self.gestures.as_mut()) {
+			(Some(config), Some(gestures)) => gestures.set_config(config),
+			(config, _) => self.gestures = config.map(Gestures::new),
+		}
+	}
+	/// The thresholds gestures are recognized by, if they're on.
+	pub fn gestures(&self) -> Option<GestureConfig> {
+		self.gestures.as_ref().map(|g| g.config())
+	}
+	/// Overrides which features the multiplexer the app runs under lets through, see `Quirks`. Features
+	/// it doesn't are left out: hyperlinks are turned off, RGB colors use the 256 color palette and curly
+	/// underlines are plain, until turned back on with `set_link_support`, `set_color_depth` or
+	/// `set_curly_underline_support`.
+	///
+	/// NOTE: This is worked out from the environment when the screen is made, see `Quirks::detect`.
+	pub fn set_quirks(&mut self, quirks: Quirks) {
+		self.quirks = quirks;
+		if !quirks.links {
+			self.links = false;
+		}
+		if !quirks.curly_underline {
+			self.curly = false;
+		}
+		if !quirks.true_color {
+			self.color_depth = self.color_depth.min(ColorDepth::Indexed);
+		}
+		// The mouse encoding might have to change
+		if self.mouse_mode != MouseMode::Off {
+			let mode = self.mouse_mode;
+			self.set_mouse(mode);
+		}
+		self.repaint();
+	}
+	/// What the multiplexer the app runs under lets through.
+	pub fn quirks(&self) -> Quirks {
+		self.quirks
+	}
+	/// Limits how many events can be waiting to be read (at least 1), and sets what happens to events that
+	/// come in once that many are waiting, see `OverflowPolicy`. Whenever events get dropped the app
+	/// gets an `Event::Overflow` saying how many, before any of the events that are left.
+	///
+	/// NOTE: The default is 1024 events, dropping the oldest.
+	pub fn set_event_queue(&mut self, limit: usize, policy: OverflowPolicy) {
+		self.queue_limit = limit.max(1);
+		self.overflow = policy;
+		while self.queued.len() > self.queue_limit {
+			self.queued.pop_front();
+			self.dropped += 1;
+		}
+	}
+	/// Switches the theme the app draws with (the built in dark one to begin with, see `Theme::builtin`).
+	/// The whole terminal gets drawn again on the next refresh, so the app should draw everything with
+	/// the new styles before then.
+	pub fn set_theme(&mut self, theme: Theme) {
+		self.theme = theme;
+		self.repaint();
+	}
+	/// The theme the app draws with, see `set_theme`.
+	pub fn theme(&self) -> &Theme {
+		&self.theme
+	}
+	/// Overrides whether the terminal supports hyperlinks (OSC 8). Where it doesn't, linked text is drawn
+	/// as plain text. This is remembered for the terminal across runs, in the capability cache.
+	///
+	/// NOTE: Terminals can't be asked, so unless this is called it's a guess from the environment.
+	pub fn set_link_support(&mut self, supported: bool) {
+		self.links = supported;
+		self.caps.set("osc8", if supported { "yes" } else { "no" });
+	}
+	/// Whether linked text is drawn as a hyperlink.
+	pub fn supports_links(&self) -> bool {
+		self.links
+	}
+	/// Overrides whether the terminal supports curly underlines (`Attr::CURLY`). Where it doesn't, they're
+	/// drawn as plain underlines, since `4:3` would be taken for underline and italic. This is remembered
+	/// for the terminal across runs, in the capability cache.
+	///
+	/// NOTE: Terminals can't be asked, so unless this is called it's a guess from the environment.
+	pub fn set_curly_underline_support(&mut self, supported: bool) {
+		self.curly = supported;
+		self.caps.set("curly", if supported { "yes" } else { "no" });
+	}
+	/// Whether curly underlines are drawn as curly.
+	pub fn supports_curly_underline(&self) -> bool {
+		self.curly
+	}
+	/// Overrides whether the terminal supports synchronized output (mode 2026), which `refresh` uses so
+	/// half drawn frames never show. This is remembered for the terminal across runs, in the capability
+	/// cache.
+	pub fn set_synchronized_output(&mut self, supported: bool) {
+		self.sync = Some(supported);
+		self.caps.set("sync", if supported { "yes" } else { "no" });
+	}
+	/// Whether `refresh` wraps frames in synchronized updates. Unless `set_synchronized_output` was
+	/// called or it's in the capability cache, the terminal gets asked (with DECRQM) the first time it's
+	/// in raw mode. Until it answers, frames aren't wrapped.
+	///
+	/// NOTE: It's always off when the multiplexer in the way can't pass it through (see `set_quirks`).
+	pub fn supports_synchronized_output(&mut self) -> bool {
+		if !self.quirks.synchronized_output {
+			return false;
+		}
+		if let Some(sync) = self.sync {
+			return sync;
+		}
+		let sync = match self.caps.get("sync") {
+			Some(value) => value == "yes",
+			// Outside of raw mode the answer would be echoed, and wait for enter
+			None if self.sync_asked || !self.can_query() => return false,
+			// DA1 comes after, which every terminal answers, so ones that don't know DECRQM don't hold things up
+			None => {
+				self.sync_asked = true;
+				match self.query(&format!("{0}[?2026$p{0}[c", ESCAPE), find_mode_reply) {
+					Some(supported) => {
+						self.caps.set("sync", if supported { "yes" } else { "no" });
+						supported
+					},
+					// No answer at all, so it's left unknown (and not saved in the cache)
+					None => return false,
+				}
+			},
+		};
+		self.sync = Some(sync);
+		sync
+	}
+	/// Internal: Whether the terminal can be asked things without the answer showing up on screen, which
+	/// takes raw mode (and a terminal)
+	fn can_query(&self) -> bool {
+		matches!(self.state_mode, ModeState::Raw) && self.backend.is_terminal()
+	}
+	/// Asks the terminal what color its background is (with OSC 11), e.g. to pick colors that go with it.
+	/// Under tmux, which only answers this itself from 3.3 on, the question is passed through to the
+	/// terminal outside if tmux doesn't answer.
+	/// If the terminal doesn't say, returns None
+	///
+	/// NOTE: GNU screen can't pass the answer back, so there it's always None. Terminals that are known
+	/// (from the capability cache) not to answer aren't asked.
+	pub fn query_background(&mut self) -> Option<Color> {
+		if self.caps.get("osc11") == Some("no") {
+			return None;
+		}
+		// DA1 comes after, which every terminal answers, so ones that don't know OSC 11 don't hold things up
+		let mut found = self.query(&format!("{0}]11;?\x07{0}[c", ESCAPE), find_background_reply);
+		if let (Multiplexer::Tmux(_), None | Some(None)) = (self.quirks.multiplexer, found) {
+			let request = self.quirks.multiplexer.passthrough(&format!("{}]11;?\x07", ESCAPE));
+			found = self.query(&format!("{}{}[c", request, ESCAPE), find_background_reply);
+		}
+		// Only a DA1 without a color says it doesn't know OSC 11, nothing at all could be a slow terminal.
+		// The color itself isn't kept, since the theme can change between runs
+		match found {
+			Some(Some(color)) => {
+				self.caps.set("osc11", "yes");
+				Some(color)
+			},
+			Some(None) => {
+				self.caps.set("osc11", "no");
+				None
+			},
+			None => None,
+		}
+	}
+	/// Whether the terminal's background is dark or light, see `query_background`. Apps can use this to
+	/// pick a theme to start with, see `Theme::for_background`.
+	/// If the terminal doesn't say, returns None
+	pub fn detect_background(&mut self) -> Option<Luminance> {
+		Luminance::of(self.query_background()?)
+	}
+	/// How many colors the terminal is thought to be able to show.
+	pub fn color_depth(&self) -> ColorDepth {
+		self.color_depth
+	}
+	/// The filters every event goes through before `read_event` (or `event_stream`) hands it out,
+	/// e.g. for global shortcuts.
+	pub fn filters(&mut self) -> &mut Filters {
+		&mut self.filters
+	}
+	/// The capabilities cached from earlier runs in this terminal.
+	/// Anything stored here gets saved when the screen is dropped.
+	pub fn caps(&mut self) -> &mut CapCache {
+		&mut self.caps
+	}
+	/// The last few events that were read (64 at most), oldest first. For bug reports, see
+	/// `diagnostics::bundle`.
+	pub fn recent_events(&self) -> &VecDeque<Event> {
+		&self.recent
+	}
+	/// Internal: The capability cache, for looking at without changing it
+	pub(crate) fn cap_cache(&self) -> &CapCache {
+		&self.caps
+	}
+	/// Internal: Writes the cells that differ from what's on the terminal
+	fn write_changes(&mut self) {
+		span!("diff");
+		let layered = self.layered();
+		let source = layered.as_ref().unwrap_or(&self.back);
+		let scaled = if self.logical {
+			let mut buf = self.scaled.take().unwrap_or_else(|| Buffer::new(0, 0));
+			self.scale_into(source, &mut buf);
+			Some(buf)
+		} else {
+			None
+		};
+		let frame = scaled.as_ref().unwrap_or(source);
+		let direct = layered.is_none() && scaled.is_none();
+		let synced = direct && self.synced == Some(self.back.generation().0);
+		self.line_hashes.resize(frame.get_height() as usize, None);
+
+		// Where the terminal's cursor is after the last cell we wrote, so that we can skip moving it for neighbours
+		let mut next: Option<(u16, u16)> = None;
+		let top = self.top();
+		// The style the terminal is using, if we've set one yet
+		let mut style: Option<Style> = None;
+		let mut link: Option<Link> = None;
+		// Whether the terminal is switched to the alternate character set
+		let mut alternate = false;
+		// Text waiting to go out in one write, until the next escape sequence
+		let mut run = String::new();
+
+		// Lines that are blank from here on down can be cleared all at once, if enough is on them now
+		let blank = |row: &[Cell]| row.iter().all(|c| *c == Cell::default());
+		let bottom = (0..frame.get_height()).rev().take_while(|&y| blank(frame.row(y))).last();
+		if let Some(bottom) = bottom {
+			let stale = (bottom..self.front.get_height()).flat_map(|y| self.front.row(y)).filter(|c| **c != Cell::default()).count();
+			if stale >= CLEAR_MIN_CELLS {
+				write_clear(&mut self.out, &mut style, top + bottom, 0, 'J');
+				self.front.clear_to_eos(bottom, 0);
+				next = Some((bottom, 0));
+			}
+		}
+		for y in 0..frame.get_height() {
+			// Lines that are the same as on the terminal are skipped without going through their cells
+			if synced && !frame.is_dirty(y) && !self.front.is_dirty(y) {
+				continue;
+			}
+			let hash = frame.line_hash(y);
+			if !self.front.is_dirty(y) && self.line_hashes[y as usize] == hash {
+				continue;
+			}
+			self.line_hashes[y as usize] = hash;
+
+			// The same goes for the end of the line
+			let row = frame.row(y);
+			let end = row.iter().rposition(|c| *c != Cell::default()).map_or(0, |x| x + 1);
+			let stale = self.front.row(y)[end..].iter().filter(|c| **c != Cell::default()).count();
+			if stale >= CLEAR_MIN_CELLS {
+				let end = end as u16;
+				write_run(&mut self.out, &mut run);
+				write_clear(&mut self.out, &mut style, top + y, end, 'K');
+				self.front.clear_to_eol(y, end);
+				next = Some((y, end));
+			}
+			for x in 0..frame.get_width() {
+				let cell = *frame.get(y, x).unwrap();
+				if self.front.get(y, x) == Some(&cell) {
+					continue;
+				}
+				if cell.ch == CONTINUATION {
+					// Covered by the wide character before it
+					self.front.set(y, x, cell);
+					continue;
+				}
+				if next != Some((y, x)) {
+					let motion = cursor_motion(next, (y, x), frame.get_width(), top);
+					// Writing out what's already there can be shorter than moving over it
+					let link = if self.links { link } else { None };
+					match next.and_then(|from| gap_text(frame, from, (y, x), style, link, alternate, self.color_depth)) {
+						Some(gap) if gap.len() < motion.len() => run.push_str(&gap),
+						_ => {
+							write_run(&mut self.out, &mut run);
+							write!(self.out, "{}", motion).unwrap_or(());
+						},
+					}
+				}
+				let depth = self.color_depth;
+				let unseen = cell.ch == ' ' && style.is_some_and(|s| blank_looks_same(&s, &cell.style, depth));
+				if style != Some(cell.style) && !unseen {
+					let seq = match style {
+						Some(ref from) => cell.style.sgr_change_with(from, depth, self.curly),
+						None => cell.style.sgr_with(depth, self.curly),
+					};
+					if !seq.is_empty() {
+						write_run(&mut self.out, &mut run);
+						write!(self.out, "{}", seq).unwrap_or(());
+					}
+					style = Some(cell.style);
+				}
+				if self.links && cell.style.link != link {
+					let seq = cell.style.link.map_or_else(|| Link::end().to_string(), |l| l.start());
+					write_run(&mut self.out, &mut run);
+					write!(self.out, "{}", seq).unwrap_or(());
+					link = cell.style.link;
+				}
+				let ch = match self.line_drawing {
+					LineDrawing::Unicode => cell.ch,
+					LineDrawing::Acs => match acs::to_acs(cell.ch) {
+						Some(ch) => {
+							if !alternate {
+								write_run(&mut self.out, &mut run);
+								write!(self.out, "{}(0", ESCAPE).unwrap_or(());
+								alternate = true;
+							}
+							ch
+						},
+						None => {
+							if alternate {
+								write_run(&mut self.out, &mut run);
+								write!(self.out, "{}(B", ESCAPE).unwrap_or(());
+								alternate = false;
+							}
+							cell.ch
+						},
+					},
+					LineDrawing::Ascii => acs::to_ascii(cell.ch).unwrap_or(cell.ch),
+				};
+				run.push(ch);
+				self.front.set(y, x, cell);
+				next = Some((y, x + char_width(cell.ch).max(1)));
+			}
+		}
+		write_run(&mut self.out, &mut run);
+		if alternate {
+			write!(self.out, "{}(B", ESCAPE).unwrap_or(());
+		}
+		if link.is_some() {
+			write!(self.out, "{}", Link::end()).unwrap_or(());
+		}
+		if style.is_some_and(|s| s != Style::default()) {
+			// Anything else written to the terminal shouldn't pick up the last cell's style
+			write!(self.out, "{}[0m", ESCAPE).unwrap_or(());
+		}
+		if next.is_some() {
+			// Put the cursor back where the user left it
+			let seq = cursor_motion(next, self.cursor_pos(), self.front.get_width(), top);
+			write!(self.out, "{}", seq).unwrap_or(());
+		}
+		// Anything that changes `front` from here on (like invalidating it) shows up as dirty
+		self.front.mark_clean();
+		if direct {
+			self.back.mark_clean();
+			self.synced = Some(self.back.generation().0);
+		} else {
+			self.synced = None;
+		}
+		if scaled.is_some() {
+			self.scaled = scaled;
+		}
+	}
+	/// Internal: Does the checks asked for with `set_watchdog`, if they're due
+	fn run_watchdog(&mut self) {
+		let (check_every, repaint_every) = match self.watchdog {
+			Some(ref w) => (w.check_every, w.repaint_every),
+			None => return,
+		};
+		let now = Instant::now();
+		let moved = mem::take(&mut self.cursor_moved);
+		let stale = moved || repaint_every.is_some_and(|every| now.duration_since(self.last_repaint) >= every);
+
+		if now.duration_since(self.last_check) >= check_every {
+			self.last_check = now;
+
+			// Put back the modes we set, in case something reset them
+			write!(self.out, "{}[0m", ESCAPE).unwrap_or(());
+			if self.cursor_state.is_off() {
+				write!(self.out, "{}[?25l", ESCAPE).unwrap_or(());
+			} else {
+				write!(self.out, "{}[?25h", ESCAPE).unwrap_or(());
+			}
+			let mouse = self.mouse_mode;
+			self.set_mouse(mouse);
+
+			// Anything else writing to the terminal is almost sure to have moved the cursor. Waiting for the
+			// answer would hold up the frame, so it's checked when it comes in with the rest of the input
+			// (see `receive_input`). Outside of raw mode it would be echoed, so it isn't asked for at all
+			if self.can_query() {
+				let expected = self.cursor_pos();
+				let max = (self.front.get_height().saturating_sub(1), self.front.get_width().saturating_sub(1));
+				self.cursor_check = Some((expected.0.min(max.0), expected.1.min(max.1)));
+				write!(self.out, "{}[6n", ESCAPE).unwrap_or(());
+			}
+		}
+
+		if stale {
+			self.last_repaint = now;
+			self.repaint();
+		}
+	}
+	/// What's been drawn in a cell of the screen: its character and style, like ncurses' `inch`. This
+	/// is what the next refresh puts there, which can be ahead of the terminal.
+	/// If the position is off the screen, returns None
+	///
+	/// NOTE: Popups aren't included, since they're only layered on top while refreshing. `snapshot`
+	/// has them.
+	pub fn cell_at(&self, y: u16, x: u16) -> Option<&Cell> {
+		self.back.get(y, x)
+	}
+	/// Everything that's been drawn, with popups on top, as plain text (see `Buffer::to_text`). For
+	/// snapshot tests of an app's screens.
+	///
+	/// NOTE: Reserved and shared windows are only copied in on refresh, so they show up after one.
+	pub fn snapshot(&self) -> String {
+		self.layered().unwrap_or_else(|| self.back.clone()).to_text()
+	}
+	/// Like `snapshot`, but with the styles as escape sequences (see `Buffer::to_ansi`).
+	pub fn snapshot_ansi(&self) -> String {
+		self.layered().unwrap_or_else(|| self.back.clone()).to_ansi()
+	}
+	/// Blanks the terminal and draws everything again, for when something else wrote to it.
+	pub fn redraw(&mut self) {
+		self.repaint();
+		self.refresh();
+	}
+	/// Makes the next refresh draw every cell inside of `rect` again, even ones that look unchanged.
+	pub fn invalidate_rect(&mut self, rect: Rect) {
+		let (factor, off_y, off_x) = self.scaling();
+		let scale = |n: u16, off: u16| n.saturating_mul(factor).saturating_add(off);
+		let scaled = Rect::new(scale(rect.y, off_y), scale(rect.x, off_x), scale(rect.height, 0), scale(rect.width, 0));
+		let area = Rect::new(0, 0, self.front.get_height(), self.front.get_width());
+		if let Some(rect) = scaled.intersect(&area) {
+			for y in rect.y..rect.bottom() {
+				for x in rect.x..rect.right() {
+					self.front.get_mut(y, x).unwrap().ch = STALE;
+				}
+			}
+		}
+	}
+	/// Internal: Blanks the terminal and forgets what's on it, so the next refresh draws every cell
+	fn repaint(&mut self) {
+		write!(self.out, "{}[0m", ESCAPE).unwrap_or(());
+		self.write_clear_all();
+		self.front.clear();
+	}
+	/// Internal: Blanks the whole terminal, or just the viewport if it's inline
+	fn write_clear_all(&mut self) {
+		match self.inline {
+			Some(ref inline) => write!(self.out, "{0}[{1};1H{0}[J", ESCAPE, inline.origin + 1).unwrap_or(()),
+			None => write!(self.out, "{}[2J", ESCAPE).unwrap_or(()),
+		}
+	}
+	/// Internal: The row of the terminal the top of the screen is on, which is only ever not 0 for an inline viewport
+	fn top(&self) -> u16 {
+		self.inline.as_ref().map_or(0, |inline| inline.origin)
+	}
+	/// Internal: Makes room for the inline viewport from the cursor down, scrolling what's on the terminal
+	/// up if there isn't enough, and sizes everything to it. Unless `ask`, the cursor is taken to be at the
+	/// bottom, instead of asking the terminal where it is
+	fn place_inline(&mut self, ask: bool) {
+		let rows = match self.inline {
+			Some(ref mut inline) => {
+				inline.origin = 0;
+				inline.rows
+			},
+			None => return,
+		};
+		let (height, width) = (self.dims.rows, self.dims.cols);
+		let rows = rows.clamp(1, height.max(1));
+		// If it can't be told, go below everything
+		let pos = if ask { self.query_cursor_pos() } else { None };
+		let (y, x) = pos.unwrap_or((height.saturating_sub(1), 1));
+		// Start on a line of our own rather than after the prompt, and scroll with newlines if that's too low
+		let feeds = rows - 1 + if x > 0 { 1 } else { 0 };
+		write!(self.out, "\r{}", "\n".repeat(feeds as usize)).unwrap_or(());
+		let origin = (y + feeds).min(height.saturating_sub(1)) + 1 - rows;
+		self.set_inline_origin(origin, rows, width);
+		write!(self.out, "{0}[{1};1H{0}[J", ESCAPE, origin + 1).unwrap_or(());
+		self.cur_pos = TermDim::default();
+	}
+	/// Internal: Puts the inline viewport at `origin`, `rows` by `width`, with nothing drawn on it yet
+	fn set_inline_origin(&mut self, origin: u16, rows: u16, width: u16) {
+		if let Some(ref mut inline) = self.inline {
+			inline.origin = origin;
+		}
+		// Leave the cursor where the viewport was, for the shell to carry on from
+		self.turn_off = format!("{0}[{1};1H{0}[0m{0}[J", ESCAPE, origin + 1);
+		let mut back = Buffer::new(rows, width);
+		back.copy_from(&self.back, 0, 0);
+		self.back = back;
+		self.front = Buffer::new(rows, width);
+		self.place_reserved();
+	}
+	/// Internal: How much the logical grid gets scaled up by, and where its top-left corner ends up
+	fn scaling(&self) -> (u16, u16, u16) {
+		if !self.logical {
+			return (1, 0, 0);
+		}
+		let (height, width) = (self.back.get_height(), self.back.get_width());
+		let factor = (self.front.get_height() / height.max(1)).min(self.front.get_width() / width.max(1)).max(1);
+		let off_y = self.front.get_height().saturating_sub(height * factor) / 2;
+		let off_x = self.front.get_width().saturating_sub(width * factor) / 2;
+		(factor, off_y, off_x)
+	}
+	/// Internal: Scales the logical grid up to the size of the terminal, into `out`
+	fn scale_into(&self, source: &Buffer, out: &mut Buffer) {
+		let (factor, off_y, off_x) = self.scaling();
+		if (out.get_height(), out.get_width()) == (self.front.get_height(), self.front.get_width()) {
+			// The margins around the grid have to be blank
+			out.clear();
+		} else {
+			*out = Buffer::new(self.front.get_height(), self.front.get_width());
+		}
+		for y in 0..source.get_height() {
+			for x in 0..source.get_width() {
+				let cell = *source.get(y, x).unwrap();
+				let fill = is_block(cell.ch);
+				for dy in 0..factor {
+					for dx in 0..factor {
+						let mut scaled = cell;
+						if !fill && (dy, dx) != (0, 0) {
+							scaled.ch = ' ';
+						}
+						out.set(off_y + y * factor + dy, off_x + x * factor + dx, scaled);
+					}
+				}
+			}
+		}
+	}
+	/// Internal: Turns mouse positions on the terminal into positions on the logical grid
+	fn to_logical(&self, event: Event) -> Event {
+		let event = match event {
+			Event::Mouse(mut mouse) if self.inline.is_some() => {
+				mouse.y = mouse.y.saturating_sub(self.top());
+				Event::Mouse(mouse)
+			},
+			_ => event,
+		};
+		match event {
+			Event::Mouse(mut mouse) if self.logical => {
+				let (factor, off_y, off_x) = self.scaling();
+				mouse.y = mouse.y.saturating_sub(off_y) / factor;
+				mouse.x = mouse.x.saturating_sub(off_x) / factor;
+				Event::Mouse(mouse)
+			},
+			_ => event,
+		}
+	}
+	/// Internal: The next event that has been fully read, ready to hand to the user.
+	/// `force` decodes incomplete input too, for when no more of it is coming
+	pub(crate) fn next_decoded(&mut self, force: bool) -> Option<Event> {
+		span!("decode");
+		let event = match self.ungot.pop() {
+			Some(event) => event,
+			None => self.next_filtered(force)?,
+		};
+		if self.recent.len() >= RECENT_EVENTS {
+			self.recent.pop_front();
+		}
+		self.recent.push_back(event.clone());
+		Some(event)
+	}
+	/// Internal: The next queued event that makes it through the filters, see `next_decoded`
+	fn next_filtered(&mut self, mut force: bool) -> Option<Event> {
+		loop {
+			let event = if self.dropped > 0 {
+				Event::Overflow { dropped: mem::replace(&mut self.dropped, 0) }
+			} else {
+				match self.queued.pop_front() {
+					Some(event) => {
+						let event = self.to_logical(event);
+						self.check_regions(event)
+					},
+					None if force => {
+						// Only once, whatever is read after this gets its own chance to be completed
+						force = false;
+						let event = self.decoder.flush()?;
+						self.push_event(event);
+						self.decode_input();
+						continue;
+					},
+					None => return None,
+				}
+			};
+			if let Some(event) = self.filters.run(event) {
+				return Some(event);
+			}
+		}
+	}
+	/// Internal: Queues every complete event the decoder has
+	fn decode_input(&mut self) {
+		while let Some(event) = self.decoder.next_event() {
+			self.push_event(event);
+		}
+	}
+	/// Internal: Queues an event, or what it turns into if gestures are on
+	pub(crate) fn push_event(&mut self, event: Event) {
+		match (self.gestures.as_mut(), event) {
+			(Some(gestures), Event::Mouse(mouse)) => {
+				for mouse in gestures.feed(mouse, Instant::now()) {
+					self.queue_event(Event::Mouse(mouse));
+				}
+			},
+			(_, event) => self.queue_event(event),
+		}
+	}
+	/// Internal: Queues an event, making room for it the way `set_event_queue` says if the queue is full
+	fn queue_event(&mut self, event: Event) {
+		if self.motion == MotionPolicy::Coalesce && self.queued.back().is_some_and(|last| is_same_motion(last, &event)) {
+			// Only the latest of a run of motion matters
+			self.queued.pop_back();
+		} else if self.queued.len() >= self.queue_limit {
+			self.dropped += 1;
+			match self.overflow {
+				OverflowPolicy::DropOldest => {
+					self.queued.pop_front();
+				},
+				OverflowPolicy::DropNewest => return,
+				OverflowPolicy::Coalesce => {
+					let kind = mem::discriminant(&event);
+					match self.queued.iter().rposition(|e| mem::discriminant(e) == kind) {
+						Some(i) => self.queued.remove(i),
+						None => self.queued.pop_front(),
+					};
+				},
+			}
+		}
+		self.queued.push_back(event);
+	}
+	/// Internal: Whether part of an event (e.g. the start of an escape sequence) has been read
+	pub(crate) fn has_partial_input(&self) -> bool {
+		self.decoder.has_pending()
+	}
+	/// Internal: Reads once from the terminal, blocking if nothing is there.
+	/// Returns how many bytes were read (0 at the end of input), or None if reading failed
+	pub(crate) fn read_input(&mut self) -> Option<usize> {
+		let mut bytes = [0u8; 256];
+		let n = self.backend.read(&mut bytes)?;
+		self.receive_input(&bytes[..n]);
+		Some(n)
+	}
+	/// Internal: The descriptor input is read from
+	#[cfg(unix)]
+	pub(crate) fn input_fd(&self) -> RawFd {
+		self.backend.input_fd()
+	}
+	/// Internal: Sends `request` to the terminal and waits for its answer, which `find` picks out of the input
+	/// as (start, end, answer). Anything else that was read gets kept for `read_event`.
+	/// If no answer comes in time, returns None
+	pub(crate) fn query<T, F: Fn(&[u8]) -> Option<(usize, usize, T)>>(&mut self, request: &str, find: F) -> Option<T> {
+		write!(self.out, "{}", request).unwrap_or(());
+		self.flush();
+
+		let mut got = Vec::new();
+		while self.wait_input(QUERY_TIMEOUT_MS, false)? {
+			let mut bytes = [0u8; 256];
+			let n = match self.backend.read(&mut bytes) {
+				Some(n) if n > 0 => n,
+				_ => break,
+			};
+			got.extend_from_slice(&bytes[..n]);
+			if let Some((start, end, answer)) = find(&got) {
+				self.receive_input(&got[..start]);
+				self.receive_input(&got[end..]);
+				return Some(answer);
+			}
+		}
+		self.receive_input(&got);
+		None
+	}
+	/// Internal: The recording shared with the output, see `start_recording`
+	pub(crate) fn recording(&self) -> &SharedRecording {
+		&self.recording
+	}
+	/// Internal: The size of the terminal, as (height, width)
+	pub(crate) fn terminal_size(&self) -> (u16, u16) {
+		(self.front.get_height(), self.front.get_width())
+	}
+	/// Internal: Writes `seq` so that it gets through the multiplexer (if any) to the terminal itself
+	pub(crate) fn write_passthrough(&mut self, seq: &str) {
+		let wrapped = self.quirks.multiplexer.passthrough(seq);
+		self.write_raw(&wrapped);
+	}
+	/// Internal: Writes straight to the terminal's output, it gets there on the next flush
+	pub(crate) fn write_raw(&mut self, text: &str) {
+		write!(self.out, "{}", text).unwrap_or(());
+	}
+	/// Internal: Hands bytes read from the terminal to the decoder, converting them to UTF-8 first if needed,
+	/// and queues the events that makes
+	fn receive_input(&mut self, bytes: &[u8]) {
+		// The answer to the watchdog's question isn't for the app
+		if let (Some(expected), Some((start, end, (y, x)))) = (self.cursor_check, find_cursor_report(bytes)) {
+			self.cursor_check = None;
+			// The terminal counts from 1
+			let pos = (y.saturating_sub(1).saturating_sub(self.top()), x.saturating_sub(1));
+			self.cursor_moved = pos != expected;
+			self.receive_input(&bytes[..start]);
+			self.receive_input(&bytes[end..]);
+			return;
+		}
+		if self.latency.is_some() && !bytes.is_empty() && self.input_at.is_none() {
+			self.input_at = Some(Instant::now());
+		}
+		match *self.transcoder.borrow_mut() {
+			Some(ref mut t) => self.decoder.feed(t.decode(bytes).as_bytes()),
+			None => self.decoder.feed(bytes),
+		}
+		self.decode_input();
+	}
+	/// Internal: Waits up to `timeout` milliseconds (forever if negative) for input.
+	/// If `draws` is set, windows drawn through a `DrawHandle` in the meantime get drawn (but not shown
+	/// until the next refresh).
+	/// Returns whether there's input, or None if polling failed. Stops waiting early if a signal queues an event.
+	#[cfg(unix)]
+	fn wait_input(&mut self, timeout: i32, draws: bool) -> Option<bool> {
+		if self.backend.input_fd() < 0 {
+			// Nothing to poll, e.g. no terminal behind the backend, so it does the waiting itself
+			return self.backend.wait(timeout);
+		}
+		let start = Instant::now();
+		loop {
+			let wake = match self.shared {
+				Some(ref shared) if draws => shared.wake_fd(),
+				// poll skips negative descriptors
+				_ => -1,
+			};
+			let mut fds = [
+				::libc::pollfd { fd: self.backend.input_fd(), events: ::libc::POLLIN, revents: 0 },
+				::libc::pollfd { fd: wake, events: ::libc::POLLIN, revents: 0 },
+				::libc::pollfd { fd: signal::pipe_fd(), events: ::libc::POLLIN, revents: 0 },
+			];
+			let left = if timeout < 0 { -1 } else { (timeout - start.elapsed().as_millis() as i32).max(0) };
+			let n = unsafe { ::libc::poll(fds.as_mut_ptr(), fds.len() as ::libc::nfds_t, left) };
+			if n < 0 {
+				if ::std::io::Error::last_os_error().kind() != ::std::io::ErrorKind::Interrupted {
+					return None;
+				}
+				continue;
+			}
+			if fds[1].revents != 0 {
+				self.draw_pending();
+			}
+			if fds[0].revents != 0 {
+				return Some(true);
+			}
+			if fds[2].revents != 0 {
+				self.handle_signals();
+				if !self.queued.is_empty() {
+					return Some(false);
+				}
+			}
+			if n == 0 {
+				return Some(false);
+			}
+		}
+	}
+	/// Internal: Waits up to `timeout` milliseconds (forever if negative) for input, like the Unix one, but
+	/// through the backend. Windows drawn through a `DrawHandle` and resizes are checked for every so often.
+	#[cfg(windows)]
+	fn wait_input(&mut self, timeout: i32, draws: bool) -> Option<bool> {
+		let start = Instant::now();
+		loop {
+			if draws && self.shared.as_ref().is_some_and(|shared| shared.has_pending()) {
+				self.draw_pending();
+			}
+			if self.resize() {
+				return Some(false);
+			}
+			let left = if timeout < 0 { RESIZE_CHECK_MS } else { (timeout - start.elapsed().as_millis() as i32).clamp(0, RESIZE_CHECK_MS) };
+			if self.backend.wait(left)? {
+				return Some(true);
+			}
+			if timeout >= 0 && start.elapsed().as_millis() as i32 >= timeout {
+				return Some(false);
+			}
+		}
+	}
+	/// Internal: Deals with the signals that came in since last time
+	#[cfg(unix)]
+	pub(crate) fn handle_signals(&mut self) {
+		for sig in signal::take() {
+			match sig {
+				::libc::SIGWINCH => {
+					self.resize();
+				},
+				::libc::SIGTSTP => self.suspend(),
+				::libc::SIGINT => self.push_event(Event::Interrupt),
+				_ => {},
+			}
+		}
+	}
+	/// Internal: Catches up with the terminal's size, queueing an `Event::Resize` if it changed.
+	/// Returns whether it changed
+	pub(crate) fn resize(&mut self) -> bool {
+		if !self.fit_terminal() {
+			return false;
+		}
+		self.push_event(Event::Resize {
+			height: self.back.get_height(),
+			width: self.back.get_width(),
+		});
+		true
+	}
+	/// Internal: Catches up with the terminal's size, without telling the app.
+	/// Returns whether it changed
+	pub(crate) fn fit_terminal(&mut self) -> bool {
+		let dims = match self.backend.size() {
+			Some((rows, cols)) => Size::new(rows, cols),
+			None => return false,
+		};
+		if dims == self.dims {
+			return false;
+		}
+		self.dims = dims;
+		match self.inline.clone() {
+			// It keeps its rows as far as they fit, moving up if the terminal got shorter
+			Some(inline) => {
+				let rows = inline.rows.clamp(1, dims.rows.max(1));
+				self.set_inline_origin(inline.origin.min(dims.rows.saturating_sub(rows)), rows, dims.cols);
+			},
+			None => {
+				if !self.logical {
+					// Keep what was drawn, so there's something on screen until the app draws again
+					let mut back = Buffer::new(dims.rows, dims.cols);
+					back.copy_from(&self.back, 0, 0);
+					self.back = back;
+				}
+				self.front = Buffer::new(dims.rows, dims.cols);
+				self.place_reserved();
+			},
+		}
+		self.repaint();
+		if let Some(ref mut recording) = *self.recording.borrow_mut() {
+			recording.record_resize(dims.rows, dims.cols);
+		}
+		true
+	}
+}
+
+impl Drop for Screen {
+	fn drop(&mut self) {
+		self.finish();
+	}
+}
+
+impl Screen {
+	/// Registers something to run when the screen shuts down (see `shutdown`), while the terminal is still
+	/// set up, e.g. to save state or draw a last frame. Hooks run in the order they were added.
+	pub fn on_shutdown<F: FnOnce(&mut Screen) + 'static>(&mut self, hook: F) {
+		self.shutdown_hooks.push(Box::new(hook));
+	}
+	/// Shuts the screen down: runs the hooks from `on_shutdown`, draws anything still waiting to be drawn,
+	/// turns off every mode that was turned on, and then puts the terminal back the way it was.
+	///
+	/// NOTE: Dropping the screen does the same, this just makes it explicit.
+	pub fn shutdown(mut self) {
+		self.finish();
+	}
+	/// Internal: The whole shutdown sequence, only done once
+	fn finish(&mut self) {
+		if self.finished {
+			return;
+		}
+		// Hooks can add more hooks
+		while !self.shutdown_hooks.is_empty() {
+			for hook in ::std::mem::take(&mut self.shutdown_hooks) {
+				hook(self);
+			}
+		}
+		// Since `try_reacquire` the terminal is another Screen's to put back
+		let owner = OWNER.load(atomic::Ordering::SeqCst) == self.id;
+		if owner && self.shared.as_ref().is_some_and(|shared| shared.has_pending()) {
+			self.refresh();
+		}
+		self.finished = true;
+
+		if owner {
+			self.set_cursor(CursorState::Blinking);
+			if self.cursor_style.is_some() {
+				self.reset_cursor_style();
+			}
+			self.set_mouse(MouseMode::Off);
+			if self.focus_reporting {
+				self.set_focus_reporting(false);
+			}
+			self.reset_keyboard_protocol();
+			while self.pop_title().is_some() {}
+			if self.set_screen_default().is_some() {
+				// Cleaned up, so the next Screen can take the settings as it finds them
+				self.backend.release();
+			}
+			write!(self.out, "{}", self.turn_off).unwrap_or(());
+			self.flush();
+			self.caps.save().unwrap_or(());
+			// The handlers aren't counted, so releasing them here would take them from the new owner too
+			#[cfg(unix)]
+			{
+				if let Some(old) = self.old_sigwinch.take() {
+					signal::restore(::libc::SIGWINCH, &old);
+				}
+				self.release_sigtstp();
+				self.release_sigint();
+			}
+		}
+		#[cfg(unix)]
+		if let Some(guard) = self.crash_guard.take() {
+			guard.release();
+		}
+		OWNER.compare_exchange(self.id, 0, atomic::Ordering::SeqCst, atomic::Ordering::SeqCst).unwrap_or(0);
+	}
+}
+
+/// Internal: The backend for the terminal that stdout and stdin are on
+#[cfg(unix)]
+fn std_backend() -> Option<Box<dyn Backend>> {
+	Some(Box::new(UnixBackend::new(::libc::STDOUT_FILENO, ::libc::STDIN_FILENO)?))
+}
+/// Internal: The backend for the console that stdout and stdin are on
+#[cfg(windows)]
+fn std_backend() -> Option<Box<dyn Backend>> {
+	Some(Box::new(WindowsBackend::new()?))
+}
+
+/// Internal: Finds a cursor position report (`ESC [ y ; x R`) in `buf`.
+/// Returns where it starts and ends, and the position in it
+fn find_cursor_report(buf: &[u8]) -> Option<(usize, usize, (u16, u16))> {
+	for start in 0..buf.len() {
+		if !buf[start..].starts_with(b"\x1b[") {
+			continue;
+		}
+		let rest = &buf[start + 2..];
+		let end = match rest.iter().position(|&b| !(b.is_ascii_digit() || b == b';')) {
+			Some(end) if rest[end] == b'R' => end,
+			_ => continue,
+		};
+		let params = ::std::str::from_utf8(&rest[..end]).ok()?;
+		let mut nums = params.split(';');
+		let (y, x) = match (nums.next(), nums.next(), nums.next()) {
+			(Some(y), Some(x), None) => (y.parse().ok()?, x.parse().ok()?),
+			_ => continue,
+		};
+		return Some((start, start + 2 + end + 1, (y, x)));
+	}
+	None
+}
+
+/// Internal: Finds the answers to `ESC [ ? u` (the kitty keyboard protocol's flags) and DA1 in `buf`.
+/// Every terminal answers DA1, and only ones with the protocol answer the first, before it.
+/// Returns where they start and end, and whether the protocol is supported
+fn find_keyboard_reply(buf: &[u8]) -> Option<(usize, usize, bool)> {
+	let find = |buf: &[u8], last: u8| (0..buf.len()).find_map(|start| {
+		let rest = buf[start..].strip_prefix(b"\x1b[?")?;
+		let end = rest.iter().position(|&b| !(b.is_ascii_digit() || b == b';'))?;
+		if rest[end] == last { Some((start, start + 3 + end + 1)) } else { None }
+	});
+	let (da_start, da_end) = find(buf, b'c')?;
+	let flags = find(&buf[..da_start], b'u');
+	Some((flags.map_or(da_start, |f| f.0), da_end, flags.is_some()))
+}
+
+/// Internal: Finds the answers to a DECRQM query for mode 2026 (`ESC [ ? 2026 ; state $ y`) and DA1 in
+/// `buf`, with whether the mode is supported. States 1 and 2 are set and reset, the rest mean it isn't
+/// (0), or it can't be changed (3 and 4)
+fn find_mode_reply(buf: &[u8]) -> Option<(usize, usize, bool)> {
+	let (da_start, da_end) = find_da_reply(buf)?;
+	let mode = (0..da_start).find_map(|start| {
+		let rest = buf[start..da_start].strip_prefix(b"\x1b[?2026;")?;
+		let end = rest.iter().position(|&b| !b.is_ascii_digit())?;
+		if rest[end..].starts_with(b"$y") { Some((start, &rest[..end])) } else { None }
+	});
+	let supported = mode.is_some_and(|(_, state)| state == b"1" || state == b"2");
+	Some((mode.map_or(da_start, |m| m.0), da_end, supported))
+}
+
+/// Internal: Finds the answers to an OSC 11 query (`ESC ] 11 ; rgb:RRRR/GGGG/BBBB`, ended by BEL or ST)
+/// and DA1 in `buf`, with the background color if the terminal gave one
+fn find_background_reply(buf: &[u8]) -> Option<(usize, usize, Option<Color>)> {
+	let (da_start, da_end) = find_da_reply(buf)?;
+	let reply = (0..da_start).find_map(|start| {
+		let rest = buf[start..da_start].strip_prefix(b"\x1b]11;")?;
+		let end = rest.iter().position(|&b| b == 0x07 || b == 0x1b)?;
+		Some((start, parse_rgb_spec(&rest[..end])))
+	});
+	Some((reply.map_or(da_start, |r| r.0), da_end, reply.and_then(|r| r.1)))
+}
+
+/// Internal: Finds the answer to DA1 (`ESC [ ? ... c`) in `buf`, as (start, end)
+fn find_da_reply(buf: &[u8]) -> Option<(usize, usize)> {
+	(0..buf.len()).find_map(|start| {
+		let rest = buf[start..].strip_prefix(b"\x1b[?")?;
+		let end = rest.iter().position(|&b| !(b.is_ascii_digit() || b == b';'))?;
+		if rest[end] == b'c' { Some((start, start + 3 + end + 1)) } else { None }
+	})
+}
+
+/// Internal: An X11 color spec like `rgb:RRRR/GGGG/BBBB`, where each part has 1 to 4 hex digits
+fn parse_rgb_spec(spec: &[u8]) -> Option<Color> {
+	let spec = ::std::str::from_utf8(spec).ok()?.strip_prefix("rgb:")?;
+	let mut parts = spec.split('/').map(|part| {
+		if part.is_empty() || part.len() > 4 {
+			return None;
+		}
+		let max = (1u32 << (4 * part.len())) - 1;
+		Some((u32::from_str_radix(part, 16).ok()? * 255 / max) as u8)
+	});
+	let (r, g, b) = (parts.next()??, parts.next()??, parts.next()??);
+	if parts.next().is_some() {
+		return None;
+	}
+	Some(Color::Rgb(r, g, b))
+}
+
+/// Internal: The shortest sequence that moves the cursor from `from` to `to`, both (y, x) counting from
+/// row `top` of the terminal, out of CUP and the relative moves (CUU/CUD/CUF/CUB, CHA and carriage
+/// return). If it's not known where the cursor is, or it's past the end of a `width` wide line (where
+/// relative moves come out one off), that's CUP
+fn cursor_motion(from: Option<(u16, u16)>, to: (u16, u16), width: u16, top: u16) -> String {
+	let absolute = format!("{}[{};{}H", ESCAPE, top as u32 + to.0 as u32 + 1, to.1 as u32 + 1);
+	let from = match from {
+		Some(from) if from.1 < width => from,
+		_ => return absolute,
+	};
+	// The count can be left out when it's 1
+	let step = |n: u16, op: char| if n == 1 { format!("{}[{}", ESCAPE, op) } else { format!("{}[{}{}", ESCAPE, n, op) };
+	let vertical = match to.0.cmp(&from.0) {
+		Ordering::Less => step(from.0 - to.0, 'A'),
+		Ordering::Greater => step(to.0 - from.0, 'B'),
+		Ordering::Equal => String::new(),
+	};
+	let horizontal = match to.1.cmp(&from.1) {
+		Ordering::Equal => String::new(),
+		Ordering::Greater => step(to.1 - from.1, 'C'),
+		Ordering::Less if to.1 == 0 => "\r".to_string(),
+		Ordering::Less => step(from.1 - to.1, 'D'),
+	};
+	let column = format!("{}[{}G", ESCAPE, to.1 as u32 + 1);
+	let horizontal = if !horizontal.is_empty() && column.len() < horizontal.len() { column } else { horizontal };
+	let relative = vertical + &horizontal;
+	if relative.len() < absolute.len() { relative } else { absolute }
+}
+
+/// Internal: Writes the erase sequence ending in `op` (EL or ED) with the cursor at (y, x), after
+/// switching to the default style, since the cleared cells take on the current background
+fn write_clear<W: Write>(out: &mut W, style: &mut Option<Style>, y: u16, x: u16, op: char) {
+	write!(out, "{}[{};{}H", ESCAPE, y + 1, x + 1).unwrap_or(());
+	if *style != Some(Style::default()) {
+		write!(out, "{}[0m", ESCAPE).unwrap_or(());
+		*style = Some(Style::default());
+	}
+	write!(out, "{}[{}", ESCAPE, op).unwrap_or(());
+}
+
+/// Internal: Writes out the text that's been saved up, see `write_changes`
+fn write_run<W: Write>(out: &mut W, run: &mut String) {
+	if !run.is_empty() {
+		out.write_all(run.as_bytes()).unwrap_or(());
+		run.clear();
+	}
+}
+
+/// Internal: Whether a blank in style `b` looks just like one in style `a`, which it does unless the
+/// background or something drawn across the cell is different
+fn blank_looks_same(a: &Style, b: &Style, depth: ColorDepth) -> bool {
+	let plain = |s: &Style| [Attr::UNDERLINE, Attr::CURLY, Attr::STRIKE, Attr::REVERSE].iter().all(|&a| !s.attrs.contains(a));
+	plain(a) && plain(b) && a.bg.downgrade(depth) == b.bg.downgrade(depth) && a.link == b.link
+}
+
+/// Internal: The text of the cells on the terminal from `from` up to `to` on the same line, for writing
+/// over them instead of moving past them. Only plain text that's already there in the terminal's current
+/// style (`style` and `link`) will do, otherwise returns None
+fn gap_text(frame: &Buffer, from: (u16, u16), to: (u16, u16), style: Option<Style>, link: Option<Link>, alternate: bool, depth: ColorDepth) -> Option<String> {
+	let style = style?;
+	if from.0 != to.0 || from.1 >= to.1 || alternate {
+		return None;
+	}
+	let mut out = String::new();
+	for &cell in &frame.row(from.0)[from.1 as usize..to.1 as usize] {
+		let same = cell.style == style || (cell.ch == ' ' && blank_looks_same(&style, &cell.style, depth));
+		if !(cell.ch == ' ' || cell.ch.is_ascii_graphic()) || !same || cell.style.link != link {
+			return None;
+		}
+		out.push(cell.ch);
+	}
+	Some(out)
+}
+
+/// Internal: Which of a stack of windows (bottom one first) have any cells showing inside of `area`
+fn visible_windows(area: Rect, wins: &[&Window]) -> Vec<bool> {
+	let mut covered = vec![false; area.height as usize * area.width as usize];
+	let mut visible = vec![false; wins.len()];
+	for (i, win) in wins.iter().enumerate().rev() {
+		let rect = match win.rect().intersect(&area) {
+			Some(rect) => rect,
+			None => continue,
+		};
+		for y in rect.y..rect.bottom() {
+			let row = y as usize * area.width as usize;
+			for cell in &mut covered[row + rect.x as usize..row + rect.right() as usize] {
+				visible[i] |= !*cell;
+				*cell = true;
+			}
+		}
+	}
+	visible
+}
+
+/// Internal: Draws `win` onto `buf` with the effects of a modal
+fn layer(buf: &mut Buffer, win: &Window, effects: ModalEffects) {
+	if effects.dim {
+		// Dim everything, the window covers its own area right after anyway
+		let all = Rect::new(0, 0, buf.get_height(), buf.get_width());
+		buf.restyle(all, |s| s.attrs |= Attr::DIM);
+	}
+	if effects.shadow {
+		let shadow = win.rect().offset(1, 2);
+		buf.restyle(shadow, |s| {
+			s.attrs |= Attr::DIM;
+			s.bg = Color::Black;
+		});
+	}
+	buf.copy_from(win.buffer(), win.get_y(), win.get_x());
+}
+
+/// Internal: Whether both events are the mouse moving in the same way, so only the second one matters
+fn is_same_motion(a: &Event, b: &Event) -> bool {
+	match (a, b) {
+		(&Event::Mouse(a), &Event::Mouse(b)) => match (a.kind, b.kind) {
+			(MouseKind::Move, MouseKind::Move) => true,
+			(MouseKind::Drag(a), MouseKind::Drag(b)) => a == b,
+			(MouseKind::DragMove(a), MouseKind::DragMove(b)) => a == b,
+			_ => false,
+		},
+		_ => false,
+	}
+}
+
+/// Internal: Whether the environment looks like a terminal that's known to understand `4:3` for curly underlines
+fn guess_curly_support() -> bool {
+	let var = |name: &str| ::std::env::var(name).unwrap_or_default();
+	// VTE 0.51.2
+	if var("VTE_VERSION").parse::<u32>().is_ok_and(|v| v >= 5102) {
+		return true;
+	}
+	let env = format!("{} {}", var("TERM"), var("TERM_PROGRAM")).to_lowercase();
+	["kitty", "wezterm", "foot", "alacritty", "ghostty", "contour", "mintty"].iter().any(|t| env.contains(t))
+}
+
+/// Internal: Whether the environment looks like a terminal that's known to support hyperlinks
+fn guess_link_support() -> bool {
+	let var = |name: &str| ::std::env::var(name).unwrap_or_default();
+	if var("VTE_VERSION").parse::<u32>().is_ok_and(|v| v >= 5000) || !var("WT_SESSION").is_empty() || !var("KONSOLE_VERSION").is_empty() {
+		return true;
+	}
+	let env = format!("{} {}", var("TERM"), var("TERM_PROGRAM")).to_lowercase();
+	["kitty", "wezterm", "iterm", "foot", "alacritty", "ghostty", "contour"].iter().any(|t| env.contains(t))
+}
+
+/// Internal: Whether a character looks right when repeated to fill a scaled up cell. Only ones that
+/// fill all of it do, half blocks and the like would come out striped
+fn is_block(ch: char) -> bool {
+	ch == ' ' || ch == '█'
+}
+
+/// How big the terminal is, in rows and columns of cells, see `Screen::size`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Size {
+	pub rows: u16,
+	pub cols: u16,
+}
+impl Size {
+	pub fn new(rows: u16, cols: u16) -> Size {
+		Size { rows, cols }
+	}
+}
+
+/// Internal: A position on the terminal, `height` being the row and `width` the column
+#[derive(Debug, Default, Clone)]
+struct TermDim {
+	height: u16,
+	width: u16
+}
+impl TermDim {
+	#[inline]
+	/// Get the row
+	pub fn get_height(&self) -> u16 {
+		self.height
+	}
+	#[inline]
+	/// Get the column
+	pub fn get_width(&self) -> u16 {
+		self.width
+	}
+}
+
+/// The possible states for the Cursor
+pub enum CursorState {
+	/// Cursor is Solid (a steady block, see `Screen::set_cursor_style` for other shapes)
+	Solid,
+	/// Cursor is Blinking (default)
+	Blinking,
+	/// Cursor is disabled
+	Off
+}
+impl CursorState {
+	pub fn is_solid(&self) -> bool {
+		matches!(*self, CursorState::Solid)
+	}
+	pub fn is_blinking(&self) -> bool {
+		matches!(*self, CursorState::Blinking)
+	}
+	pub fn is_off(&self) -> bool {
+		matches!(*self, CursorState::Off)
+	}
+}
+
+/// The shapes the cursor can have, see `Screen::set_cursor_style`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CursorStyle {
+	BlinkingBlock,
+	SteadyBlock,
+	BlinkingUnderline,
+	SteadyUnderline,
+	BlinkingBar,
+	SteadyBar,
+}
+impl CursorStyle {
+	pub fn is_blinking(&self) -> bool {
+		matches!(*self, CursorStyle::BlinkingBlock | CursorStyle::BlinkingUnderline | CursorStyle::BlinkingBar)
+	}
+	/// Internal: The DECSCUSR parameter for the style
+	fn code(&self) -> u8 {
+		match *self {
+			CursorStyle::BlinkingBlock => 1,
+			CursorStyle::SteadyBlock => 2,
+			CursorStyle::BlinkingUnderline => 3,
+			CursorStyle::SteadyUnderline => 4,
+			CursorStyle::BlinkingBar => 5,
+			CursorStyle::SteadyBar => 6,
+		}
+	}
+}
+
+/// Internal: A window shown on top of everything, see `Screen::show_popup`
+struct Popup {
+	id: PopupId,
+	win: Window,
+	effects: ModalEffects,
+	/// The popup's own clickable regions, in screen coordinates
+	regions: Regions,
+}
+
+/// Internal: What goes into a frame, see `Screen::frame_key`. If it's the same as last time, so is the frame.
+#[derive(PartialEq)]
+struct FrameKey {
+	back: (u64, u64),
+	/// What the terminal shows, which changes when it's repainted or resized
+	front: (u64, u64),
+	popups: Vec<((u64, u64), Rect, ModalEffects)>,
+}
+
+/// Identifies a popup, see `Screen::show_popup`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PopupId(usize);
+
+/// Effects for `Screen::draw_modal` and `Screen::show_popup`, to make it obvious that the modal has focus.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ModalEffects {
+	/// Dim everything behind the modal
+	pub dim: bool,
+	/// Draw a drop shadow below and to the right of the modal
+	pub shadow: bool,
+}
+
+/// Settings for `Screen::set_watchdog`.
+#[derive(Debug, Clone, Copy)]
+pub struct Watchdog {
+	/// How often to check that the cursor is where rcurses left it (needs raw mode), repainting if it isn't.
+	/// The terminal's answer is read along with input, and the repaint happens on the refresh after that
+	pub check_every: Duration,
+	/// How often to repaint everything, whether or not anything looks wrong
+	pub repaint_every: Option<Duration>,
+}
+
+/// What happens to mouse motion (moves and drags) that comes in faster than the app reads events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionPolicy {
+	/// Runs of motion that are waiting to be read get collapsed into the last one, so the app doesn't
+	/// fall behind the mouse (default)
+	Coalesce,
+	/// Every bit of motion gets its own event, e.g. for drawing apps that need the whole path
+	KeepAll,
+}
+
+/// What happens to an event that comes in while the event queue is full, see `Screen::set_event_queue`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+	/// The oldest waiting event is dropped to make room (default)
+	DropOldest,
+	/// The new event is dropped
+	DropNewest,
+	/// The newest waiting event of the same kind (e.g. an older resize, or the last key) is dropped to make
+	/// room, so the latest of each kind always gets through. If there isn't one, the oldest is dropped
+	Coalesce,
+}
+
+/// Which mouse actions get reported.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseMode {
+	/// No mouse reporting (default)
+	Off,
+	/// Button presses, releases, and the scroll wheel
+	Clicks,
+	/// Like clicks, but also moving the mouse while a button is held down
+	Drag,
+	/// Every mouse movement, needed for hovering
+	Motion,
+}
+
+/// How much of the terminal a Screen takes over, see `Screen::with_viewport`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Viewport {
+	/// All of it, on the alternate screen, so whatever was there before comes back afterwards
+	Fullscreen,
+	/// Just this many rows at the bottom of the normal screen, under what the shell printed (like fzf).
+	/// What's above scrolls up to make room if it has to, and the rows are cleared again on exit, with
+	/// the cursor left at the top of them.
+	///
+	/// NOTE: Everything counts from the top of the viewport, and the size in `Event::Resize` is the
+	/// viewport's, while `Screen::size` is still the whole terminal.
+	Inline(u16),
+}
+
+/// Internal: Where an inline viewport is on the terminal
+#[derive(Debug, Clone)]
+struct Inline {
+	/// How many rows it wants
+	rows: u16,
+	/// The row of the terminal its top is on
+	origin: u16,
+}
+
+/// What Ctrl-C does, see `Screen::set_signal_policy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalPolicy {
+	/// It sends SIGINT like usual, which ends the program unless something else handles it. Ctrl-Z and
+	/// Ctrl-\ send their signals too
+	///
+	/// NOTE: So that the terminal isn't left in raw mode when that happens, this starts the crash guard
+	/// (see `Screen::spawn_crash_guard`) on Unix.
+	Raise,
+	/// It's a key like any other, `Key::Ctrl('c')`
+	Key,
+	/// It comes in as `Event::Interrupt`, as does SIGINT from anywhere else. Ctrl-Z and Ctrl-\ send
+	/// their signals
+	///
+	/// NOTE: Windows has no SIGINT to catch, so there it's the same as `Raise`.
+	Interrupt,
+}
+
+/// What to ask of the kitty keyboard protocol, combined with `|`, see `Screen::set_keyboard_protocol`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyboardFlags(u8);
+impl KeyboardFlags {
+	/// Send keys that would be ambiguous (Esc, Alt and Ctrl combinations) as escape sequences
+	pub const DISAMBIGUATE: KeyboardFlags = KeyboardFlags(1);
+	/// Report repeats and releases too
+	pub const EVENT_TYPES: KeyboardFlags = KeyboardFlags(1 << 1);
+	/// Say what shift makes of a key, along with the key itself
+	pub const ALTERNATE_KEYS: KeyboardFlags = KeyboardFlags(1 << 2);
+	/// Send every key as an escape sequence, even ones that type text, so shift gets reported on them
+	pub const ALL_KEYS: KeyboardFlags = KeyboardFlags(1 << 3);
+
+	/// Whether every flag in `other` is set.
+	pub fn contains(&self, other: KeyboardFlags) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+impl BitOr for KeyboardFlags {
+	type Output = KeyboardFlags;
+	fn bitor(self, other: KeyboardFlags) -> KeyboardFlags {
+		KeyboardFlags(self.0 | other.0)
+	}
+}
+
+/// Possible modes for the terminal to be in.
+pub enum ModeState {
+	/// The default mode for the terminal, typed text will go to the screen.
+	Default,
+	/// Typed text will get sent *only* to the screen, not to the screen.  Expected keystrokes to send signals (e.g., ctrl+c for SIGINT) will still work.
+	Cbreak,
+	/// Like cbreak, but even the signal keystrokes will get sent to the program w/o sending the signal.
+	Raw,
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ::testing::TestScreen;
+
+	/// Internal: Whether the terminal's cell looks like the one that was drawn, which for blanks only
+	/// takes what shows (see `blank_looks_same`)
+	fn looks_same(shown: Cell, drawn: Cell) -> bool {
+		shown.ch == drawn.ch && (shown.style == drawn.style || (drawn.ch == ' ' && blank_looks_same(&shown.style, &drawn.style, ColorDepth::TrueColor)))
+	}
+	fn assert_shown(ts: &TestScreen, win: &Window) {
+		for y in 0..win.get_height() {
+			for x in 0..win.get_width() {
+				let (shown, drawn) = (ts.cell(y, x), *win.cell_at(y, x).unwrap());
+				assert!(looks_same(shown, drawn), "({}, {}) shows {:?} instead of {:?}", y, x, shown, drawn);
+			}
+		}
+	}
+
+	#[test]
+	fn changes_round_trip() {
+		let mut ts = TestScreen::new(5, 20);
+		let mut win = Window::new(0, 0, 5, 20);
+		win.print_styled(0, 0, "hello", Style::new().fg(Color::Red).attrs(Attr::BOLD));
+		win.print_styled(1, 3, "world", Style::new().bg(Color::Rgb(1, 2, 3)));
+		win.print(4, 18, "界");
+		ts.draw(&win);
+		ts.refresh();
+		assert_shown(&ts, &win);
+		assert_eq!(ts.line(0), "hello               ");
+
+		// A single cell, then a line that's mostly blanked out
+		win.put_char(0, 1, 'a');
+		win.print(1, 0, "        ");
+		ts.draw(&win);
+		ts.refresh();
+		assert_shown(&ts, &win);
+		assert_eq!(ts.snapshot(), "hallo\n\n\n\n                  界");
+	}
+
+	#[test]
+	fn random_frames_round_trip() {
+		const CHARS: [char; 6] = ['a', 'b', 'Z', ' ', '─', '#'];
+		const COLORS: [Color; 5] = [Color::Default, Color::Red, Color::Indexed(100), Color::Rgb(10, 200, 30), Color::Blue];
+		const ATTRS: [Attr; 5] = [Attr::NONE, Attr::BOLD, Attr::UNDERLINE, Attr::REVERSE, Attr::ITALIC];
+		// xorshift, so every run draws the same frames
+		let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+		let mut next = |n: usize| {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			(state % n as u64) as usize
+		};
+		let (height, width) = (12, 30);
+		let mut ts = TestScreen::new(height, width);
+		let mut win = Window::new(0, 0, height, width);
+		for frame in 0..60 {
+			// Some frames change a few cells, some blank out most of the screen
+			let rects = if frame % 10 == 9 { 1 } else { 1 + next(4) };
+			for _ in 0..rects {
+				let (y, x) = (next(height as usize) as u16, next(width as usize) as u16);
+				let rect = Rect::new(y, x, 1 + next((height - y) as usize) as u16, 1 + next((width - x) as usize) as u16);
+				if frame % 10 == 9 {
+					win.fill(rect, ' ', Style::default());
+					continue;
+				}
+				for cy in rect.y..rect.bottom() {
+					for cx in rect.x..rect.right() {
+						let style = Style::new().fg(COLORS[next(5)]).bg(COLORS[next(5)]).attrs(ATTRS[next(5)]);
+						win.print_styled(cy, cx, &CHARS[next(6)].to_string(), style);
+					}
+				}
+			}
+			ts.draw(&win);
+			ts.refresh();
+			assert_shown(&ts, &win);
+		}
+	}
+
+	#[test]
+	fn cursor_motion_cheapest() {
+		let esc = |s: &str| s.replace('^', "\x1b");
+		// Nowhere to move from, or from past the end of the line
+		assert_eq!(cursor_motion(None, (2, 3), 80, 0), esc("^[3;4H"));
+		assert_eq!(cursor_motion(Some((0, 80)), (0, 3), 80, 0), esc("^[1;4H"));
+		assert_eq!(cursor_motion(None, (0, 0), 80, 5), esc("^[6;1H"));
+		// Relative moves, leaving out counts of 1
+		assert_eq!(cursor_motion(Some((5, 5)), (4, 5), 80, 0), esc("^[A"));
+		assert_eq!(cursor_motion(Some((5, 5)), (8, 5), 80, 0), esc("^[3B"));
+		assert_eq!(cursor_motion(Some((5, 5)), (5, 7), 80, 0), esc("^[2C"));
+		assert_eq!(cursor_motion(Some((5, 5)), (5, 4), 80, 0), esc("^[D"));
+		assert_eq!(cursor_motion(Some((5, 5)), (6, 0), 80, 0), esc("^[B\r"));
+		// CHA when it's shorter than the relative move, and CUP when that's shorter than both
+		assert_eq!(cursor_motion(Some((5, 50)), (5, 5), 80, 0), esc("^[6G"));
+		assert_eq!(cursor_motion(Some((50, 50)), (1, 1), 80, 0), esc("^[2;2H"));
+		assert_eq!(cursor_motion(Some((5, 5)), (5, 5), 80, 0), "");
+	}
+
+	#[test]
+	fn mode_reply() {
+		assert_eq!(find_mode_reply(b"\x1b[?2026;2$y\x1b[?62;22c"), Some((0, 20, true)));
+		assert_eq!(find_mode_reply(b"\x1b[?2026;1$y\x1b[?1c"), Some((0, 16, true)));
+		// Not known, or can't be changed
+		assert_eq!(find_mode_reply(b"\x1b[?2026;0$y\x1b[?1c"), Some((0, 16, false)));
+		assert_eq!(find_mode_reply(b"\x1b[?2026;4$y\x1b[?1c"), Some((0, 16, false)));
+		// Only DA1, with a key typed before it
+		assert_eq!(find_mode_reply(b"q\x1b[?62c"), Some((1, 7, false)));
+		// Still waiting for DA1
+		assert_eq!(find_mode_reply(b"\x1b[?2026;2$y"), None);
+	}
+
+	#[test]
+	fn background_reply() {
+		let reply = b"\x1b]11;rgb:ffff/8080/0000\x07\x1b[?62c";
+		assert_eq!(find_background_reply(reply), Some((0, reply.len(), Some(Color::Rgb(255, 128, 0)))));
+		let reply = b"x\x1b]11;rgb:00/11/22\x1b\\\x1b[?62c";
+		assert_eq!(find_background_reply(reply), Some((1, reply.len(), Some(Color::Rgb(0, 0x11, 0x22)))));
+		assert_eq!(find_background_reply(b"\x1b[?62c"), Some((0, 6, None)));
+		assert_eq!(find_background_reply(b"\x1b]11;rgb:ffff/8080/0000\x07"), None);
+	}
+
+	#[test]
+	fn rgb_spec() {
+		assert_eq!(parse_rgb_spec(b"rgb:ffff/0000/8080"), Some(Color::Rgb(255, 0, 128)));
+		assert_eq!(parse_rgb_spec(b"rgb:f/0/8"), Some(Color::Rgb(255, 0, 136)));
+		assert_eq!(parse_rgb_spec(b"rgb:fff/000/800"), Some(Color::Rgb(255, 0, 127)));
+		assert_eq!(parse_rgb_spec(b"rgb:ff/00"), None);
+		assert_eq!(parse_rgb_spec(b"rgb:ff/00/00/00"), None);
+		assert_eq!(parse_rgb_spec(b"rgb:fffff/0/0"), None);
+		assert_eq!(parse_rgb_spec(b"rgb://"), None);
+		assert_eq!(parse_rgb_spec(b"#ff0000"), None);
+	}
+}
@@ -0,0 +1,191 @@
+//! Selecting text in a window with the mouse, the way terminals do it.
+//!
+//! Dragging selects cell by cell, double clicking selects a word at a time, and triple clicking a line
+//! at a time. Mouse reporting has to include drags for this to work, see `Screen::set_mouse`.
+
+use std::time::{Duration, Instant};
+use ::buffer::CONTINUATION;
+use ::event::{Event, MouseButton, MouseKind};
+use ::rect::Rect;
+use ::style::Attr;
+use ::window::Window;
+
+/// How long apart clicks can be and still count as a double or triple click
+const MULTI_CLICK_MS: u64 = 400;
+
+/// What a selection grows by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Snap {
+	Char,
+	Word,
+	Line,
+}
+
+/// A stream of selected cells (like a terminal's selection, not a rectangle) inside of a window.
+#[derive(Debug, Clone)]
+pub struct Selection {
+	/// Where the selection started and where it's been dragged to, relative to the window
+	anchor: (u16, u16),
+	head: (u16, u16),
+	snap: Snap,
+	dragging: bool,
+	/// Whether there's anything selected at all
+	active: bool,
+	/// The last click, and how many clicks in a row it was
+	last_click: Option<(Instant, (u16, u16), u8)>,
+}
+
+impl Selection {
+	pub fn new() -> Selection {
+		Selection {
+			anchor: (0, 0),
+			head: (0, 0),
+			snap: Snap::Char,
+			dragging: false,
+			active: false,
+			last_click: None,
+		}
+	}
+	/// Updates the selection from a mouse event (in screen coordinates) on `win`.
+	/// Returns whether the selection changed
+	pub fn handle(&mut self, event: &Event, win: &Window) -> bool {
+		let mouse = match *event {
+			Event::Mouse(mouse) => mouse,
+			_ => return false,
+		};
+		let area = win.rect();
+		match mouse.kind {
+			MouseKind::Press(MouseButton::Left) if area.contains(mouse.y, mouse.x) => {
+				let pos = (mouse.y - area.y, mouse.x - area.x);
+				let now = Instant::now();
+				let clicks = match self.last_click {
+					Some((at, at_pos, n)) if at_pos == pos && now.duration_since(at) < Duration::from_millis(MULTI_CLICK_MS) => n % 3 + 1,
+					_ => 1,
+				};
+				self.last_click = Some((now, pos, clicks));
+				self.snap = match clicks {
+					1 => Snap::Char,
+					2 => Snap::Word,
+					_ => Snap::Line,
+				};
+				self.anchor = pos;
+				self.head = pos;
+				self.dragging = true;
+				// A single click on its own selects nothing
+				self.active = clicks > 1;
+				true
+			},
+			MouseKind::Drag(MouseButton::Left) | MouseKind::DragMove(MouseButton::Left) if self.dragging => {
+				let pos = clamp(area, mouse.y, mouse.x);
+				let changed = pos != self.head || !self.active;
+				self.head = pos;
+				self.active = true;
+				changed
+			},
+			MouseKind::Release(MouseButton::Left) | MouseKind::DragEnd(MouseButton::Left) if self.dragging => {
+				self.dragging = false;
+				false
+			},
+			_ => false,
+		}
+	}
+	/// Whether anything is selected.
+	pub fn is_active(&self) -> bool {
+		self.active
+	}
+	/// Whether the mouse button is still held down.
+	pub fn is_dragging(&self) -> bool {
+		self.dragging
+	}
+	/// Selects nothing.
+	pub fn clear(&mut self) {
+		self.active = false;
+		self.dragging = false;
+	}
+	/// The first and last selected cells of `win` (inclusive), after snapping to words or lines.
+	/// If nothing is selected, returns None
+	pub fn range(&self, win: &Window) -> Option<((u16, u16), (u16, u16))> {
+		if !self.active || win.get_width() == 0 {
+			return None;
+		}
+		let (mut start, mut end) = if self.anchor <= self.head { (self.anchor, self.head) } else { (self.head, self.anchor) };
+		let last = win.get_width() - 1;
+		match self.snap {
+			Snap::Char => {},
+			Snap::Word => {
+				let kind = |y: u16, x: u16| class(win.buffer().get(y, x).map_or(' ', |c| c.ch));
+				let start_kind = kind(start.0, start.1);
+				while start.1 > 0 && kind(start.0, start.1 - 1) == start_kind {
+					start.1 -= 1;
+				}
+				let end_kind = kind(end.0, end.1);
+				while end.1 < last && kind(end.0, end.1 + 1) == end_kind {
+					end.1 += 1;
+				}
+			},
+			Snap::Line => {
+				start.1 = 0;
+				end.1 = last;
+			},
+		}
+		Some((start, end))
+	}
+	/// Whether the cell at (y, x) of `win` is selected.
+	pub fn contains(&self, win: &Window, y: u16, x: u16) -> bool {
+		self.range(win).is_some_and(|(start, end)| start <= (y, x) && (y, x) <= end)
+	}
+	/// The selected text, with trailing blanks cut off every line. Ready for `Screen::copy_to_clipboard`.
+	pub fn text(&self, win: &Window) -> String {
+		let ((y1, x1), (y2, x2)) = match self.range(win) {
+			Some(range) => range,
+			None => return String::new(),
+		};
+		let mut lines = Vec::new();
+		for y in y1..=y2 {
+			let from = if y == y1 { x1 } else { 0 };
+			let to = if y == y2 { x2 } else { win.get_width() - 1 };
+			let line: String = (from..=to)
+				.filter_map(|x| win.buffer().get(y, x).map(|c| c.ch))
+				.filter(|&ch| ch != CONTINUATION)
+				.collect();
+			lines.push(line.trim_end().to_string());
+		}
+		lines.join("\n")
+	}
+	/// Shows the selection on `win` by reversing the colors of the selected cells.
+	pub fn highlight(&self, win: &mut Window) {
+		let ((y1, x1), (y2, x2)) = match self.range(win) {
+			Some(range) => range,
+			None => return,
+		};
+		for y in y1..=y2 {
+			let from = if y == y1 { x1 } else { 0 };
+			let to = if y == y2 { x2 } else { win.get_width() - 1 };
+			win.restyle(Rect::new(y, from, 1, to - from + 1), |s| s.attrs |= Attr::REVERSE);
+		}
+	}
+}
+
+impl Default for Selection {
+	fn default() -> Selection {
+		Selection::new()
+	}
+}
+
+/// Internal: Which kind of character this is, a word is a run of the same kind
+fn class(ch: char) -> u8 {
+	if ch.is_whitespace() {
+		0
+	} else if ch.is_alphanumeric() || ch == '_' || ch == CONTINUATION {
+		1
+	} else {
+		2
+	}
+}
+
+/// Internal: The position in `area` closest to (y, x), relative to the area
+fn clamp(area: Rect, y: u16, x: u16) -> (u16, u16) {
+	let y = y.clamp(area.y, area.bottom().saturating_sub(1).max(area.y));
+	let x = x.clamp(area.x, area.right().saturating_sub(1).max(area.x));
+	(y - area.y, x - area.x)
+}
@@ -0,0 +1,91 @@
+//! Drawing from other threads.
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::sync::{Arc, Mutex};
+use ::window::Window;
+
+/// A cheap handle that any thread can draw windows through, see `Screen::draw_handle`.
+///
+/// Windows drawn through a handle are put on screen the next time the `Screen` refreshes. If the thread
+/// that owns the Screen is waiting in `read_event`, they're drawn onto it right away, ready for that.
+#[derive(Debug, Clone)]
+pub struct DrawHandle {
+	shared: Arc<Shared>,
+}
+
+impl DrawHandle {
+	/// Queues the window to be drawn by the Screen.
+	pub fn draw(&self, win: &Window) {
+		self.shared.pending.lock().unwrap().push(win.clone());
+		self.shared.wake();
+	}
+}
+
+/// The state shared between a Screen and its handles.
+#[derive(Debug)]
+pub struct Shared {
+	pending: Mutex<Vec<Window>>,
+	/// A pipe that gets a byte whenever something is queued, so the Screen can wait on it along with input
+	#[cfg(unix)]
+	wake_read: RawFd,
+	#[cfg(unix)]
+	wake_write: RawFd,
+}
+
+impl Shared {
+	/// If the pipe can't be made, returns None
+	pub fn new() -> Option<Arc<Shared>> {
+		#[cfg(unix)]
+		let mut fds = [0; 2];
+		#[cfg(unix)]
+		if unsafe { ::libc::pipe2(fds.as_mut_ptr(), ::libc::O_CLOEXEC | ::libc::O_NONBLOCK) } < 0 {
+			return None;
+		}
+		Some(Arc::new(Shared {
+			pending: Mutex::new(Vec::new()),
+			#[cfg(unix)]
+			wake_read: fds[0],
+			#[cfg(unix)]
+			wake_write: fds[1],
+		}))
+	}
+	/// Makes a new handle for drawing into this.
+	pub fn handle(this: &Arc<Shared>) -> DrawHandle {
+		DrawHandle { shared: this.clone() }
+	}
+	/// The end of the pipe to wait on.
+	#[cfg(unix)]
+	pub fn wake_fd(&self) -> RawFd {
+		self.wake_read
+	}
+	/// Whether any windows are queued.
+	pub fn has_pending(&self) -> bool {
+		!self.pending.lock().unwrap().is_empty()
+	}
+	/// Takes every queued window, oldest first.
+	pub fn take_pending(&self) -> Vec<Window> {
+		// Empty the pipe too, all of the wake ups are handled by this
+		#[cfg(unix)]
+		let mut bytes = [0u8; 64];
+		#[cfg(unix)]
+		while unsafe { ::libc::read(self.wake_read, bytes.as_mut_ptr() as *mut ::libc::c_void, bytes.len()) } > 0 {}
+		::std::mem::take(&mut *self.pending.lock().unwrap())
+	}
+	/// Internal: Wakes up whoever is waiting on the pipe
+	fn wake(&self) {
+		// If the pipe is full a wake up is already waiting, so failing is fine
+		#[cfg(unix)]
+		unsafe { ::libc::write(self.wake_write, b"w".as_ptr() as *const ::libc::c_void, 1); }
+	}
+}
+
+#[cfg(unix)]
+impl Drop for Shared {
+	fn drop(&mut self) {
+		unsafe {
+			::libc::close(self.wake_read);
+			::libc::close(self.wake_write);
+		}
+	}
+}
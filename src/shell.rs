@@ -0,0 +1,100 @@
+//! Shell integration marks (OSC 133), which shells like fish, zsh and bash (with the usual scripts)
+//! put around prompts and commands. Scanning a child shell's output for them tells where each command
+//! is and how it exited, e.g. for jumping between commands or showing badges for failed ones.
+
+// OSCs longer than this aren't marks, so they're skipped instead of kept
+const MAX_OSC: usize = 256;
+
+/// A shell integration mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+	/// A prompt is about to be drawn (`A`)
+	PromptStart,
+	/// The prompt is done, and what's typed after it is the command (`B`)
+	CommandStart,
+	/// The command was entered, and its output comes next (`C`)
+	CommandExecuted,
+	/// The command finished, with its exit status if the shell said (`D`)
+	CommandFinished(Option<i32>),
+}
+
+/// Internal: Where the scanner is in an escape sequence
+#[derive(Debug, Clone, PartialEq)]
+enum State {
+	Ground,
+	Escape,
+	Osc,
+	/// Saw an escape inside of an OSC, which ends it if a `\` comes next
+	OscEscape,
+}
+
+/// Finds marks in output from a shell, which can come in pieces of any size.
+#[derive(Debug, Clone)]
+pub struct MarkScanner {
+	state: State,
+	osc: Vec<u8>,
+}
+
+impl MarkScanner {
+	pub fn new() -> MarkScanner {
+		MarkScanner {
+			state: State::Ground,
+			osc: Vec::new(),
+		}
+	}
+	/// Scans the next piece of output for marks. The output itself is left alone, so it can go on to
+	/// whatever shows it.
+	/// Returns the marks, each with the offset in `bytes` just past the sequence that made it
+	pub fn scan(&mut self, bytes: &[u8]) -> Vec<(usize, Mark)> {
+		let mut marks = Vec::new();
+		for (i, &byte) in bytes.iter().enumerate() {
+			self.state = match (&self.state, byte) {
+				(&State::Ground, 0x1b) | (&State::Escape, 0x1b) => State::Escape,
+				(&State::Escape, b']') => {
+					self.osc.clear();
+					State::Osc
+				},
+				(&State::Osc, 0x07) => {
+					marks.extend(self.finish().map(|m| (i + 1, m)));
+					State::Ground
+				},
+				(&State::Osc, 0x1b) => State::OscEscape,
+				(&State::OscEscape, b'\\') => {
+					marks.extend(self.finish().map(|m| (i + 1, m)));
+					State::Ground
+				},
+				// Anything else cancels the OSC
+				(&State::OscEscape, _) => State::Ground,
+				(&State::Osc, byte) => {
+					if self.osc.len() <= MAX_OSC {
+						self.osc.push(byte);
+					}
+					State::Osc
+				},
+				_ => State::Ground,
+			};
+		}
+		marks
+	}
+	/// Internal: The mark the OSC that just ended makes, if it's one
+	fn finish(&mut self) -> Option<Mark> {
+		if self.osc.len() > MAX_OSC {
+			return None;
+		}
+		let text = ::std::str::from_utf8(&self.osc).ok()?;
+		let mut params = text.strip_prefix("133;")?.split(';');
+		Some(match params.next()? {
+			"A" => Mark::PromptStart,
+			"B" => Mark::CommandStart,
+			"C" => Mark::CommandExecuted,
+			"D" => Mark::CommandFinished(params.next().and_then(|s| s.parse().ok())),
+			_ => return None,
+		})
+	}
+}
+
+impl Default for MarkScanner {
+	fn default() -> MarkScanner {
+		MarkScanner::new()
+	}
+}
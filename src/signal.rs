@@ -0,0 +1,92 @@
+//! Turning signals into something that can be waited on along with input.
+//!
+//! Signal handlers can't do much safely, so the ones installed here only write the signal's number to a
+//! pipe, and the Screen reads it back out whenever the pipe has something in it.
+
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Once;
+
+static PIPE_READ: AtomicI32 = AtomicI32::new(-1);
+static PIPE_WRITE: AtomicI32 = AtomicI32::new(-1);
+static INIT: Once = Once::new();
+
+extern "C" fn handler(sig: ::libc::c_int) {
+	let fd = PIPE_WRITE.load(Ordering::Relaxed);
+	if fd >= 0 {
+		// If the pipe is full the signal gets lost, but there are plenty of others like it waiting already
+		let byte = sig as u8;
+		unsafe { ::libc::write(fd, &byte as *const u8 as *const ::libc::c_void, 1); }
+	}
+}
+
+/// Makes `sig` get written to the pipe whenever it's received. Returns how it was handled before, to put
+/// back with `restore`.
+/// If the pipe or the handler can't be set up, returns None
+pub fn catch(sig: ::libc::c_int) -> Option<::libc::sigaction> {
+	INIT.call_once(|| {
+		let mut fds = [0; 2];
+		if unsafe { ::libc::pipe2(fds.as_mut_ptr(), ::libc::O_CLOEXEC | ::libc::O_NONBLOCK) } == 0 {
+			PIPE_READ.store(fds[0], Ordering::Relaxed);
+			PIPE_WRITE.store(fds[1], Ordering::Relaxed);
+		}
+	});
+	if PIPE_READ.load(Ordering::Relaxed) < 0 {
+		return None;
+	}
+	unsafe {
+		let mut action: ::libc::sigaction = ::std::mem::zeroed();
+		action.sa_sigaction = handler as extern "C" fn(::libc::c_int) as ::libc::sighandler_t;
+		action.sa_flags = ::libc::SA_RESTART;
+		::libc::sigemptyset(&mut action.sa_mask);
+		let mut old: ::libc::sigaction = ::std::mem::zeroed();
+		if ::libc::sigaction(sig, &action, &mut old) < 0 {
+			return None;
+		}
+		Some(old)
+	}
+}
+
+/// Puts back the handling of `sig` from before `catch`.
+pub fn restore(sig: ::libc::c_int, old: &::libc::sigaction) {
+	unsafe { ::libc::sigaction(sig, old, ::std::ptr::null_mut()); }
+}
+
+/// Stops the process like SIGTSTP would if it wasn't caught, leaving its handler as it was once the
+/// process is continued.
+pub fn stop() {
+	unsafe {
+		let mut action: ::libc::sigaction = ::std::mem::zeroed();
+		action.sa_sigaction = ::libc::SIG_DFL;
+		::libc::sigemptyset(&mut action.sa_mask);
+		let mut old: ::libc::sigaction = ::std::mem::zeroed();
+		if ::libc::sigaction(::libc::SIGTSTP, &action, &mut old) < 0 {
+			return;
+		}
+		::libc::raise(::libc::SIGTSTP);
+		::libc::sigaction(::libc::SIGTSTP, &old, ::std::ptr::null_mut());
+	}
+}
+
+/// The end of the pipe to wait on, -1 if nothing has been caught yet.
+pub fn pipe_fd() -> RawFd {
+	PIPE_READ.load(Ordering::Relaxed)
+}
+
+/// Takes the signals that came in since last time, oldest first.
+pub fn take() -> Vec<::libc::c_int> {
+	let fd = pipe_fd();
+	let mut sigs = Vec::new();
+	if fd < 0 {
+		return sigs;
+	}
+	let mut bytes = [0u8; 64];
+	loop {
+		let n = unsafe { ::libc::read(fd, bytes.as_mut_ptr() as *mut ::libc::c_void, bytes.len()) };
+		if n <= 0 {
+			break;
+		}
+		sigs.extend(bytes[..n as usize].iter().map(|&b| b as ::libc::c_int));
+	}
+	sigs
+}
@@ -0,0 +1,96 @@
+//! Reading events asynchronously, with the `async` feature.
+
+use std::future::Future;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use ::futures_core::Stream;
+use ::tokio::io::unix::AsyncFd;
+use ::tokio::time::{sleep, Sleep};
+use ::event::Event;
+use ::screen::Screen;
+use ::signal;
+
+/// Stream of the events from a Screen, see `Screen::event_stream`.
+pub struct EventStream<'a> {
+	screen: &'a mut Screen,
+	fd: AsyncFd<RawFd>,
+	/// The signal pipe, for resizes
+	signals: Option<AsyncFd<RawFd>>,
+	/// Running while half of an escape sequence is waiting, once it's done it was just the ESC key
+	escape_timer: Option<Pin<Box<Sleep>>>,
+}
+
+impl Screen {
+	/// Gets the events as a `Stream`, so they can be awaited alongside other IO.
+	/// Has to be called from inside of a tokio runtime, otherwise returns None
+	///
+	/// NOTE: Windows drawn through a `DrawHandle` only show up on the next `refresh`.
+	pub fn event_stream(&mut self) -> Option<EventStream<'_>> {
+		if ::tokio::runtime::Handle::try_current().is_err() {
+			return None;
+		}
+		let fd = AsyncFd::new(self.input_fd()).ok()?;
+		let signals = if signal::pipe_fd() < 0 { None } else { AsyncFd::new(signal::pipe_fd()).ok() };
+		Some(EventStream {
+			screen: self,
+			fd,
+			signals,
+			escape_timer: None,
+		})
+	}
+}
+
+impl<'a> Stream for EventStream<'a> {
+	type Item = Event;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Event>> {
+		let this = self.get_mut();
+		loop {
+			if let Some(event) = this.screen.next_decoded(false) {
+				this.escape_timer = None;
+				return Poll::Ready(Some(event));
+			}
+			if let Some(Poll::Ready(Ok(mut guard))) = this.signals.as_ref().map(|fd| fd.poll_read_ready(cx)) {
+				// Reads until the pipe is empty
+				this.screen.handle_signals();
+				guard.clear_ready();
+				continue;
+			}
+			match this.fd.poll_read_ready(cx) {
+				Poll::Ready(Ok(mut guard)) => {
+					// The fd isn't non-blocking (that would change it for the shell too), so make sure a read won't block
+					if !has_input(this.screen.input_fd()) {
+						guard.clear_ready();
+						continue;
+					}
+					match this.screen.read_input() {
+						Some(0) | None => return Poll::Ready(None),
+						Some(_) => {},
+					}
+				},
+				Poll::Ready(Err(_)) => return Poll::Ready(None),
+				Poll::Pending => {
+					if !this.screen.has_partial_input() {
+						return Poll::Pending;
+					}
+					let timeout = this.screen.escape_timeout();
+					let timer = this.escape_timer.get_or_insert_with(|| Box::pin(sleep(timeout)));
+					if timer.as_mut().poll(cx).is_pending() {
+						return Poll::Pending;
+					}
+					this.escape_timer = None;
+					if let Some(event) = this.screen.next_decoded(true) {
+						return Poll::Ready(Some(event));
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Internal: Whether there's something to read from `fd` right now
+fn has_input(fd: RawFd) -> bool {
+	let mut fds = ::libc::pollfd { fd, events: ::libc::POLLIN, revents: 0 };
+	unsafe { ::libc::poll(&mut fds, 1, 0) > 0 }
+}
@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::{BitOr, BitOrAssign};
+use std::sync::{Mutex, OnceLock};
+
+/// The colors a terminal can show.
+///
+/// `Indexed` and `Rgb` colors are turned into the nearest color the terminal can show when drawn,
+/// see `ColorDepth`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Color {
+	/// Whatever the terminal's default is
+	#[default]
+	Default,
+	Black,
+	Red,
+	Green,
+	Yellow,
+	Blue,
+	Magenta,
+	Cyan,
+	White,
+	/// One of the 256 colors of the xterm palette: 0-15 are the basic colors and their bright versions,
+	/// 16-231 a 6x6x6 color cube, and 232-255 shades of grey
+	Indexed(u8),
+	/// A true color, as red, green, and blue
+	Rgb(u8, u8, u8),
+}
+impl Color {
+	/// What the color looks like as (red, green, blue), using xterm's palette for the basic and indexed colors.
+	/// `Default` has no color of its own, so returns None
+	pub fn to_rgb(&self) -> Option<(u8, u8, u8)> {
+		let index = match *self {
+			Color::Default => return None,
+			Color::Rgb(r, g, b) => return Some((r, g, b)),
+			Color::Indexed(i) => i,
+			basic => basic.basic_index().unwrap(),
+		};
+		Some(match index {
+			0..=15 => PALETTE[index as usize],
+			16..=231 => {
+				let i = index - 16;
+				(CUBE[(i / 36) as usize], CUBE[(i / 6 % 6) as usize], CUBE[(i % 6) as usize])
+			},
+			_ => {
+				let grey = 8 + 10 * (index - 232);
+				(grey, grey, grey)
+			},
+		})
+	}
+	/// The closest color that a terminal with `depth` colors can show.
+	pub fn downgrade(&self, depth: ColorDepth) -> Color {
+		match (*self, depth) {
+			(Color::Default, _) | (_, ColorDepth::TrueColor) => *self,
+			(Color::Indexed(_), ColorDepth::Indexed) => *self,
+			(Color::Rgb(r, g, b), ColorDepth::Indexed) => Color::Indexed(nearest_indexed(r, g, b)),
+			(Color::Indexed(i), ColorDepth::Basic) if i < 8 => BASIC[i as usize],
+			(color, ColorDepth::Basic) if color.basic_index().is_some() => color,
+			(color, ColorDepth::Basic) => {
+				let rgb = color.to_rgb().unwrap();
+				*BASIC.iter().min_by_key(|c| distance(c.to_rgb().unwrap(), rgb)).unwrap()
+			},
+			(color, ColorDepth::Indexed) => color,
+		}
+	}
+	/// Mixes in `t` (from 0.0 to 1.0) of `other`. If either is `Default` there's nothing to mix, so it's
+	/// whichever one `t` is closer to.
+	pub fn blend(&self, other: Color, t: f32) -> Color {
+		let t = t.clamp(0.0, 1.0);
+		match (self.to_rgb(), other.to_rgb()) {
+			(Some(a), Some(b)) => {
+				let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+				Color::Rgb(mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+			},
+			_ => if t < 0.5 { *self } else { other },
+		}
+	}
+	/// How much text in this color stands out against `other`, as a WCAG contrast ratio. That goes from
+	/// 1.0 (the same) to 21.0 (black on white), and 4.5 is the usual minimum for text to be readable.
+	/// If either is `Default`, which could be anything, returns None
+	pub fn contrast(&self, other: Color) -> Option<f32> {
+		let a = luminance(self.to_rgb()?);
+		let b = luminance(other.to_rgb()?);
+		Some((a.max(b) + 0.05) / (a.min(b) + 0.05))
+	}
+	/// The color moved towards black or white (whichever stands out more against `bg`) just far enough
+	/// for a contrast ratio of at least `min` against it, see `contrast`. Colors that already have it are
+	/// left alone, and if even black or white doesn't, that's what it ends up as.
+	/// If either is `Default`, returns the color as it is
+	pub fn with_contrast(&self, bg: Color, min: f32) -> Color {
+		let ratio = match self.contrast(bg) {
+			Some(ratio) if ratio < min => ratio,
+			_ => return *self,
+		};
+		let (black, white) = (Color::Rgb(0, 0, 0), Color::Rgb(255, 255, 255));
+		let target = if black.contrast(bg) > white.contrast(bg) { black } else { white };
+		if target.contrast(bg).is_some_and(|r| r <= ratio) {
+			return *self;
+		}
+		// Contrast goes up the further it's blended, so look for the least blending that's enough
+		let (mut low, mut high) = (0.0, 1.0);
+		for _ in 0..16 {
+			let mid = (low + high) / 2.0;
+			if self.blend(target, mid).contrast(bg).is_some_and(|r| r >= min) {
+				high = mid;
+			} else {
+				low = mid;
+			}
+		}
+		self.blend(target, high)
+	}
+	/// Internal: Which of the 8 basic colors this is
+	fn basic_index(&self) -> Option<u8> {
+		BASIC.iter().position(|c| c == self).map(|i| i as u8)
+	}
+	/// Internal: The SGR parameters for the color, as an underline color (SGR 58), which only takes
+	/// indexed and RGB colors
+	fn underline_sgr(&self) -> String {
+		match *self {
+			Color::Default => "59".to_string(),
+			Color::Rgb(r, g, b) => format!("58;2;{};{};{}", r, g, b),
+			Color::Indexed(i) => format!("58;5;{}", i),
+			basic => format!("58;5;{}", basic.basic_index().unwrap()),
+		}
+	}
+	/// Internal: The SGR parameters for the color, as a foreground or background
+	fn sgr(&self, background: bool) -> String {
+		let base = if background { 40 } else { 30 };
+		match *self {
+			Color::Default => format!("{}", base + 9),
+			Color::Indexed(i) => format!("{};5;{}", base + 8, i),
+			Color::Rgb(r, g, b) => format!("{};2;{};{};{}", base + 8, r, g, b),
+			basic => format!("{}", base + basic.basic_index().unwrap()),
+		}
+	}
+}
+
+/// How many colors a terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorDepth {
+	/// Only the 8 basic colors
+	Basic,
+	/// The 256 color palette
+	Indexed,
+	/// Any RGB color
+	TrueColor,
+}
+impl ColorDepth {
+	/// Guesses from the environment, the same way most other programs do: `COLORTERM` says whether there's
+	/// true color, and a `TERM` ending in `256color` means there's a palette.
+	pub fn detect() -> ColorDepth {
+		let colorterm = ::std::env::var("COLORTERM").unwrap_or_default();
+		if colorterm == "truecolor" || colorterm == "24bit" {
+			return ColorDepth::TrueColor;
+		}
+		if ::std::env::var("TERM").unwrap_or_default().contains("256color") {
+			return ColorDepth::Indexed;
+		}
+		ColorDepth::Basic
+	}
+}
+
+/// A smooth run of colors between stops, e.g. for heatmaps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+	stops: Vec<Color>,
+}
+impl Gradient {
+	/// Makes a gradient that goes through `stops` in order, evenly spaced.
+	pub fn new(stops: Vec<Color>) -> Gradient {
+		Gradient { stops }
+	}
+	/// The color `t` of the way through, from 0.0 to 1.0.
+	pub fn at(&self, t: f32) -> Color {
+		match self.stops.len() {
+			0 => Color::Default,
+			1 => self.stops[0],
+			n => {
+				let pos = t.clamp(0.0, 1.0) * (n - 1) as f32;
+				let i = (pos.floor() as usize).min(n - 2);
+				self.stops[i].blend(self.stops[i + 1], pos - i as f32)
+			},
+		}
+	}
+	/// `steps` evenly spaced colors from the start to the end.
+	pub fn steps(&self, steps: usize) -> Vec<Color> {
+		(0..steps).map(|i| self.at(if steps > 1 { i as f32 / (steps - 1) as f32 } else { 0.0 })).collect()
+	}
+}
+
+// The basic colors, in the order of their SGR codes
+const BASIC: [Color; 8] = [Color::Black, Color::Red, Color::Green, Color::Yellow, Color::Blue, Color::Magenta, Color::Cyan, Color::White];
+// xterm's colors for the first 16 palette entries
+const PALETTE: [(u8, u8, u8); 16] = [
+	(0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+	(127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0), (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+// The levels of each channel in the color cube
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Internal: The palette index closest to an RGB color, out of the color cube and the greys
+fn nearest_indexed(r: u8, g: u8, b: u8) -> u8 {
+	let level = |v: u8| (0..6).min_by_key(|&i| (CUBE[i] as i32 - v as i32).abs()).unwrap();
+	let (ri, gi, bi) = (level(r), level(g), level(b));
+	let cube = (CUBE[ri], CUBE[gi], CUBE[bi]);
+	let avg = (r as u32 + g as u32 + b as u32) / 3;
+	let grey_i = ((avg.saturating_sub(8) + 5) / 10).min(23) as u8;
+	let grey = 8 + 10 * grey_i;
+	if distance((grey, grey, grey), (r, g, b)) < distance(cube, (r, g, b)) {
+		232 + grey_i
+	} else {
+		16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+	}
+}
+
+/// Internal: How far apart two colors are (squared, since it's only compared)
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+	let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+	d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Whether a color is dark or light, e.g. the terminal's background (see `Screen::detect_background`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Luminance {
+	Dark,
+	Light,
+}
+impl Luminance {
+	/// Whether `color` is dark or light, going by which of black and white stands out more against it.
+	/// `Default` has no color of its own, so returns None
+	pub fn of(color: Color) -> Option<Luminance> {
+		// Where black and white contrast just as much
+		Some(if luminance(color.to_rgb()?) > 0.179 { Luminance::Light } else { Luminance::Dark })
+	}
+}
+
+/// Internal: How bright a color looks, from 0.0 to 1.0 (WCAG's relative luminance)
+fn luminance(rgb: (u8, u8, u8)) -> f32 {
+	let linear = |v: u8| {
+		let v = v as f32 / 255.0;
+		if v <= 0.03928 { v / 12.92 } else { ((v + 0.055) / 1.055).powf(2.4) }
+	};
+	0.2126 * linear(rgb.0) + 0.7152 * linear(rgb.1) + 0.0722 * linear(rgb.2)
+}
+
+/// A set of text attributes, combined with `|`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Attr(u16);
+impl Attr {
+	pub const NONE: Attr = Attr(0);
+	pub const BOLD: Attr = Attr(1);
+	pub const DIM: Attr = Attr(1 << 1);
+	pub const ITALIC: Attr = Attr(1 << 2);
+	pub const UNDERLINE: Attr = Attr(1 << 3);
+	pub const BLINK: Attr = Attr(1 << 4);
+	pub const REVERSE: Attr = Attr(1 << 5);
+	pub const HIDDEN: Attr = Attr(1 << 6);
+	pub const STRIKE: Attr = Attr(1 << 7);
+	/// A wavy underline, like spell checkers use. A Screen only draws it where the terminal is known to
+	/// have them (see `Screen::set_curly_underline_support`), otherwise it's a plain one
+	pub const CURLY: Attr = Attr(1 << 8);
+
+	/// Whether every attribute in `other` is set.
+	pub fn contains(&self, other: Attr) -> bool {
+		self.0 & other.0 == other.0
+	}
+	/// Whether no attributes are set.
+	pub fn is_empty(&self) -> bool {
+		self.0 == 0
+	}
+	/// Unsets every attribute in `other`.
+	pub fn remove(&mut self, other: Attr) {
+		self.0 &= !other.0;
+	}
+}
+impl BitOr for Attr {
+	type Output = Attr;
+	fn bitor(self, other: Attr) -> Attr {
+		Attr(self.0 | other.0)
+	}
+}
+impl BitOrAssign for Attr {
+	fn bitor_assign(&mut self, other: Attr) {
+		self.0 |= other.0;
+	}
+}
+
+// The SGR code for each attribute, in the same order as the bits
+const ATTR_CODES: [&str; 9] = ["1", "2", "3", "4", "5", "7", "8", "9", "4:3"];
+
+/// Internal: The SGR code for the attribute at bit `i`, see `ATTR_CODES`. Unless `curly`, curly
+/// underlines are plain ones, since terminals that don't know `4:3` take it as underline and italic
+fn attr_code(i: usize, curly: bool) -> &'static str {
+	if !curly && Attr(1 << i) == Attr::CURLY { "4" } else { ATTR_CODES[i] }
+}
+
+// Every URL a `Link` has been made for, a link is an index into this
+static LINKS: OnceLock<Mutex<Links>> = OnceLock::new();
+
+/// Internal: The URLs links have been made for, and where each one is
+#[derive(Default)]
+struct Links {
+	urls: Vec<String>,
+	ids: HashMap<String, u32>,
+}
+
+/// Internal: The URLs links have been made for
+fn links() -> ::std::sync::MutexGuard<'static, Links> {
+	LINKS.get_or_init(Mutex::default).lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A hyperlink to a URL, see `Style::link`.
+///
+/// NOTE: URLs are kept around for as long as the program runs, so links to the same one are cheap to
+/// make again but there shouldn't be an endless number of different ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Link(u32);
+impl Link {
+	/// A link to `url`. Control characters in it get percent-encoded, so it can't end the escape
+	/// sequence it's sent in early.
+	pub fn new(url: &str) -> Link {
+		let url = escape_url(url);
+		let mut links = links();
+		if let Some(&id) = links.ids.get(&url) {
+			return Link(id);
+		}
+		let id = links.urls.len() as u32;
+		links.urls.push(url.clone());
+		links.ids.insert(url, id);
+		Link(id)
+	}
+	pub fn url(&self) -> String {
+		links().urls[self.0 as usize].clone()
+	}
+	/// The escape sequence that starts the link (OSC 8), text after it is part of the link until `end`.
+	pub fn start(&self) -> String {
+		format!("\x1b]8;id={};{}\x1b\\", self.0, self.url())
+	}
+	/// The escape sequence that ends any link.
+	pub fn end() -> &'static str {
+		"\x1b]8;;\x1b\\"
+	}
+}
+
+/// Internal: `url` with its control characters (C0, DEL and C1) percent-encoded
+fn escape_url(url: &str) -> String {
+	let mut out = String::with_capacity(url.len());
+	for c in url.chars() {
+		if c.is_control() {
+			let mut bytes = [0; 4];
+			for b in c.encode_utf8(&mut bytes).bytes() {
+				write!(out, "%{:02X}", b).unwrap();
+			}
+		} else {
+			out.push(c);
+		}
+	}
+	out
+}
+
+/// How a cell looks: its colors and attributes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Style {
+	pub fg: Color,
+	pub bg: Color,
+	pub attrs: Attr,
+	/// Makes the text a hyperlink, on terminals that support them (see `Screen::set_link_support`)
+	pub link: Option<Link>,
+	/// The color of the underline, if there is one, on terminals that can color them separately
+	pub underline: Color,
+}
+impl Style {
+	/// The terminal's default look.
+	pub fn new() -> Style {
+		Style::default()
+	}
+	/// Returns the style with the foreground color changed.
+	pub fn fg(mut self, color: Color) -> Style {
+		self.fg = color;
+		self
+	}
+	/// Returns the style with the background color changed.
+	pub fn bg(mut self, color: Color) -> Style {
+		self.bg = color;
+		self
+	}
+	/// Returns the style with `attrs` added.
+	pub fn attrs(mut self, attrs: Attr) -> Style {
+		self.attrs |= attrs;
+		self
+	}
+	/// Returns the style with the underline color changed. It doesn't add an underline.
+	pub fn underline(mut self, color: Color) -> Style {
+		self.underline = color;
+		self
+	}
+	/// Returns the style with the text linking to `url`.
+	pub fn link(mut self, url: &str) -> Style {
+		self.link = Some(Link::new(url));
+		self
+	}
+	/// The escape sequence that switches the terminal to this style, from any other style.
+	///
+	/// NOTE: This doesn't include the link, see `Link::start`. Curly underlines are always `4:3`, which
+	/// terminals that don't have them can take for underline and italic.
+	pub fn sgr(&self) -> String {
+		self.sgr_for(ColorDepth::TrueColor)
+	}
+	/// Like `sgr`, but with the colors turned into ones a terminal with `depth` colors can show.
+	pub fn sgr_for(&self, depth: ColorDepth) -> String {
+		self.sgr_with(depth, true)
+	}
+	/// Internal: Like `sgr_for`, with curly underlines drawn as plain ones unless `curly`
+	pub(crate) fn sgr_with(&self, depth: ColorDepth, curly: bool) -> String {
+		// Always start from a reset, since attributes can't be turned off one by one everywhere
+		let mut out = String::from("\x1b[0");
+		for i in 0..ATTR_CODES.len() {
+			if self.attrs.contains(Attr(1 << i)) {
+				write!(out, ";{}", attr_code(i, curly)).unwrap();
+			}
+		}
+		if self.fg != Color::Default {
+			write!(out, ";{}", self.fg.downgrade(depth).sgr(false)).unwrap();
+		}
+		if self.bg != Color::Default {
+			write!(out, ";{}", self.bg.downgrade(depth).sgr(true)).unwrap();
+		}
+		let underlined = self.attrs.contains(Attr::UNDERLINE) || self.attrs.contains(Attr::CURLY);
+		if underlined && self.underline != Color::Default {
+			write!(out, ";{}", self.underline.downgrade(depth).underline_sgr()).unwrap();
+		}
+		out.push('m');
+		out
+	}
+	/// The escape sequence that switches the terminal from `from` to this style, with only what's
+	/// different in it where that can be done. Empty if they look the same with `depth` colors.
+	///
+	/// Turning attributes off (or changing away from an underline color) still starts from a reset, like
+	/// `sgr_for`.
+	pub fn sgr_change(&self, from: &Style, depth: ColorDepth) -> String {
+		self.sgr_change_with(from, depth, true)
+	}
+	/// Internal: Like `sgr_change`, with curly underlines drawn as plain ones unless `curly`
+	pub(crate) fn sgr_change_with(&self, from: &Style, depth: ColorDepth, curly: bool) -> String {
+		// The underline color only gets sent with an underline, so that's the only time it counts
+		let underline = |s: &Style| {
+			let underlined = s.attrs.contains(Attr::UNDERLINE) || s.attrs.contains(Attr::CURLY);
+			if underlined { s.underline.downgrade(depth) } else { Color::Default }
+		};
+		let (old, new) = (underline(from), underline(self));
+		if from.attrs.0 & !self.attrs.0 != 0 || (old != Color::Default && new != old) {
+			return self.sgr_with(depth, curly);
+		}
+		let mut codes = Vec::new();
+		for i in 0..ATTR_CODES.len() {
+			if self.attrs.contains(Attr(1 << i)) && !from.attrs.contains(Attr(1 << i)) {
+				codes.push(attr_code(i, curly).to_string());
+			}
+		}
+		// The default colors have codes of their own, so changing to them doesn't need a reset either
+		let (fg, bg) = (self.fg.downgrade(depth), self.bg.downgrade(depth));
+		if fg != from.fg.downgrade(depth) {
+			codes.push(fg.sgr(false));
+		}
+		if bg != from.bg.downgrade(depth) {
+			codes.push(bg.sgr(true));
+		}
+		if new != old {
+			codes.push(new.underline_sgr());
+		}
+		if codes.is_empty() {
+			return String::new();
+		}
+		format!("\x1b[{}m", codes.join(";"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn link_controls_escaped() {
+		let link = Link::new("http://x/\x1b]2;pwned\x07\u{9c}");
+		assert_eq!(link.url(), "http://x/%1B]2;pwned%07%C2%9C");
+		assert_eq!(Link::new("http://x/\x1b]2;pwned\x07\u{9c}"), link);
+		assert!(!link.start()[1..link.start().len() - 2].contains('\x1b'));
+	}
+
+	#[test]
+	fn curly_fallback() {
+		let curly = Style::new().attrs(Attr::CURLY | Attr::BOLD);
+		assert_eq!(curly.sgr_with(ColorDepth::TrueColor, true), "\x1b[0;1;4:3m");
+		assert_eq!(curly.sgr_with(ColorDepth::TrueColor, false), "\x1b[0;1;4m");
+		let bold = Style::new().attrs(Attr::BOLD);
+		assert_eq!(curly.sgr_change_with(&bold, ColorDepth::TrueColor, false), "\x1b[4m");
+		assert_eq!(curly.sgr_change_with(&bold, ColorDepth::TrueColor, true), "\x1b[4:3m");
+	}
+}
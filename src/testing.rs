@@ -0,0 +1,260 @@
+//! A screen that lives in memory instead of on a terminal, so apps built on rcurses can be unit tested.
+
+use std::cell::{Ref, RefCell};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+use std::str;
+#[cfg(unix)]
+use ::termios::Termios;
+use ::acs::LineDrawing;
+use ::backend::Backend;
+use ::buffer::{Buffer, Cell, CONTINUATION};
+use ::event::{Event, Key};
+use ::quirks::Quirks;
+use ::screen::Screen;
+use ::style::ColorDepth;
+use ::vt::Emulator;
+use ::window::Window;
+
+/// Internal: The pretend terminal behind a `TestBackend`, shared by its clones
+#[derive(Debug)]
+struct Terminal {
+	size: (u16, u16),
+	input: VecDeque<u8>,
+}
+
+/// A backend with no terminal behind it, for `Screen::with_backend` in tests: it's a set size, and its
+/// input is a script of bytes. Once the script runs out, that's the end of input.
+///
+/// Clones share the same terminal, so the script can be added to after handing one to a Screen.
+#[derive(Debug, Clone)]
+pub struct TestBackend(Rc<RefCell<Terminal>>);
+
+impl TestBackend {
+	/// A terminal of the given size, with nothing typed yet.
+	pub fn new(height: u16, width: u16) -> TestBackend {
+		TestBackend(Rc::new(RefCell::new(Terminal {
+			size: (height, width),
+			input: VecDeque::new(),
+		})))
+	}
+	/// Adds `input` to the end of the script, as if it was typed.
+	pub fn push_input(&self, input: &[u8]) {
+		self.0.borrow_mut().input.extend(input);
+	}
+	/// Changes the size the terminal says it is.
+	///
+	/// NOTE: Screens aren't told, see `TestScreen::push_resize` for that.
+	pub fn set_size(&self, height: u16, width: u16) {
+		self.0.borrow_mut().size = (height, width);
+	}
+}
+
+impl Backend for TestBackend {
+	fn size(&self) -> Option<(u16, u16)> {
+		Some(self.0.borrow().size)
+	}
+	fn set_raw(&mut self) -> Option<()> {
+		Some(())
+	}
+	fn set_signals(&mut self, _on: bool) -> Option<()> {
+		Some(())
+	}
+	fn reset(&mut self) -> Option<()> {
+		Some(())
+	}
+	fn suspend(&mut self) -> Option<()> {
+		Some(())
+	}
+	fn resume(&mut self) -> Option<()> {
+		Some(())
+	}
+	fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+		let mut terminal = self.0.borrow_mut();
+		let n = buf.len().min(terminal.input.len());
+		for (to, from) in buf.iter_mut().zip(terminal.input.drain(..n)) {
+			*to = from;
+		}
+		Some(n)
+	}
+	fn wait(&mut self, _timeout: i32) -> Option<bool> {
+		// There's either more of the script, or the end of it, right away
+		Some(true)
+	}
+	#[cfg(unix)]
+	fn input_fd(&self) -> RawFd {
+		-1
+	}
+	#[cfg(unix)]
+	fn original(&self) -> Option<(RawFd, Termios)> {
+		None
+	}
+	fn is_terminal(&self) -> bool {
+		false
+	}
+}
+
+/// Internal: Plays what a Screen writes on an emulator, a whole character at a time
+struct Output {
+	terminal: Rc<RefCell<Emulator>>,
+	/// The start of a character that's still being written
+	pending: Vec<u8>,
+}
+
+impl Write for Output {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.pending.extend_from_slice(buf);
+		let end = match str::from_utf8(&self.pending) {
+			Err(e) if e.error_len().is_none() => e.valid_up_to(),
+			_ => self.pending.len(),
+		};
+		let text: Vec<u8> = self.pending.drain(..end).collect();
+		self.terminal.borrow_mut().feed(&String::from_utf8_lossy(&text));
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Internal: Something to happen when reading from a `TestScreen`, in the order they were added
+enum Step {
+	Input(Vec<u8>),
+	Event(Event),
+	Resize(u16, u16),
+}
+
+/// Stand-in for a terminal in tests.
+///
+/// Windows get drawn through a real `Screen` (on a `TestBackend`), and what it sends is played on an
+/// emulator, whose cells can be checked afterwards. Input comes from a script of events instead of
+/// the keyboard, and goes through the Screen's decoding, filters and hit regions.
+pub struct TestScreen {
+	screen: Screen,
+	backend: TestBackend,
+	terminal: Rc<RefCell<Emulator>>,
+	script: VecDeque<Step>,
+}
+
+impl TestScreen {
+	/// Creates a blank screen of the given size.
+	pub fn new(height: u16, width: u16) -> TestScreen {
+		let backend = TestBackend::new(height, width);
+		let terminal = Rc::new(RefCell::new(Emulator::new(height, width)));
+		let output = Output { terminal: terminal.clone(), pending: Vec::new() };
+		// The size can always be told, so this can't fail
+		let mut screen = Screen::with_backend(output, Box::new(backend.clone())).unwrap();
+		// The same output wherever the tests run
+		screen.set_quirks(Quirks::none());
+		screen.set_color_depth(ColorDepth::TrueColor);
+		screen.set_line_drawing(LineDrawing::Unicode);
+		screen.set_link_support(true);
+		screen.set_curly_underline_support(true);
+		screen.set_synchronized_output(false);
+		TestScreen {
+			screen,
+			backend,
+			terminal,
+			script: VecDeque::new(),
+		}
+	}
+	/// The Screen being drawn through, for code that takes one (e.g. an `App`).
+	pub fn screen(&mut self) -> &mut Screen {
+		&mut self.screen
+	}
+	/// Get the height (amount of lines) of the screen
+	pub fn get_height(&self) -> u16 {
+		self.terminal.borrow().size().0
+	}
+	/// Get the width (amount of columns) of the screen
+	pub fn get_width(&self) -> u16 {
+		self.terminal.borrow().size().1
+	}
+	/// Copies the window's cells onto the screen, like `Screen::draw`. They show up on the next `refresh`.
+	pub fn draw(&mut self, win: &Window) {
+		self.screen.draw(win);
+	}
+	/// Sends what changed since the last refresh to the terminal, like `Screen::refresh`.
+	pub fn refresh(&mut self) {
+		self.screen.refresh();
+	}
+	/// Clears the whole screen right away, like `Screen::clear`.
+	pub fn clear(&mut self) {
+		self.screen.clear();
+		self.screen.flush();
+	}
+	/// Gets the cell at (y, x) on the terminal.
+	///
+	/// # Panics
+	/// If (y, x) is outside of the screen.
+	pub fn cell(&self, y: u16, x: u16) -> Cell {
+		match self.buffer().get(y, x) {
+			Some(&cell) => cell,
+			None => panic!("({}, {}) is outside of the {}x{} screen", y, x, self.get_height(), self.get_width()),
+		}
+	}
+	/// The characters on line `y`, including trailing blanks.
+	pub fn line(&self, y: u16) -> String {
+		(0..self.get_width()).map(|x| self.cell(y, x).ch).filter(|&ch| ch != CONTINUATION).collect()
+	}
+	/// The whole terminal as text, like `Screen::snapshot`.
+	pub fn snapshot(&self) -> String {
+		self.buffer().to_text()
+	}
+	/// The whole terminal as text with the styles as escape sequences, like `Screen::snapshot_ansi`.
+	pub fn snapshot_ansi(&self) -> String {
+		self.buffer().to_ansi()
+	}
+	/// All of the cells on the terminal.
+	pub fn buffer(&self) -> Ref<'_, Buffer> {
+		Ref::map(self.terminal.borrow(), Emulator::screen)
+	}
+	/// Adds an event to the end of the input script.
+	pub fn push_event(&mut self, event: Event) {
+		self.script.push_back(Step::Event(event));
+	}
+	/// Adds a key press to the end of the input script.
+	pub fn push_key(&mut self, key: Key) {
+		self.push_event(Event::Key(key));
+	}
+	/// Adds what a terminal sends to the end of the input script, e.g. `"hi\x1b[A"`.
+	pub fn push_input(&mut self, input: &str) {
+		self.script.push_back(Step::Input(input.as_bytes().to_vec()));
+	}
+	/// Adds the terminal changing size to the end of the input script. The screen changes size when the
+	/// `Event::Resize` is read, so a run of them can be scripted, e.g. shrinking and then growing again.
+	pub fn push_resize(&mut self, height: u16, width: u16) {
+		self.script.push_back(Step::Resize(height, width));
+	}
+	/// Changes the size of the screen right away, like the terminal being resized. What was drawn stays
+	/// where it is, cut off if it doesn't fit anymore, until something is drawn over it.
+	///
+	/// NOTE: No `Event::Resize` gets sent, see `push_resize` for that.
+	pub fn resize(&mut self, height: u16, width: u16) {
+		self.backend.set_size(height, width);
+		self.terminal.borrow_mut().resize(height, width);
+		self.screen.fit_terminal();
+	}
+	/// Takes the next event from the input script, like `Screen::read_event`.
+	/// Once the script runs out, returns None
+	pub fn read_event(&mut self) -> Option<Event> {
+		loop {
+			// Whatever the last step turned into comes first
+			if let Some(event) = self.screen.read_event() {
+				return Some(event);
+			}
+			match self.script.pop_front()? {
+				Step::Input(input) => self.backend.push_input(&input),
+				Step::Event(event) => self.screen.push_event(event),
+				Step::Resize(height, width) => {
+					self.backend.set_size(height, width);
+					self.terminal.borrow_mut().resize(height, width);
+					self.screen.resize();
+				},
+			}
+		}
+	}
+}
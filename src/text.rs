@@ -0,0 +1,113 @@
+//! Measuring and wrapping text by the number of cells it takes up.
+
+use ::unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// How `wrap` breaks lines that are too long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapMode {
+	/// Break between words, only breaking a word up if it doesn't fit on a line by itself
+	Word,
+	/// Break wherever the line is full
+	Char,
+}
+
+/// Where a line goes horizontally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+	Left,
+	Center,
+	Right,
+}
+
+/// Where a block of lines goes vertically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VAlign {
+	Top,
+	Middle,
+	Bottom,
+}
+
+/// How many cells a character takes up: 2 for wide characters (e.g. CJK), 0 for combining characters.
+pub fn char_width(ch: char) -> u16 {
+	ch.width().unwrap_or(0) as u16
+}
+
+/// How many cells a string takes up.
+pub fn str_width(text: &str) -> usize {
+	text.width()
+}
+
+/// Cuts text down to at most `width` cells, ending it with `…` if anything was cut off.
+pub fn truncate(text: &str, width: u16) -> String {
+	let width = width as usize;
+	if str_width(text) <= width {
+		return text.to_string();
+	}
+	let mut out = String::new();
+	let mut used = 0;
+	for ch in text.chars() {
+		let ch_w = char_width(ch) as usize;
+		// Leave room for the ellipsis
+		if used + ch_w + 1 > width {
+			break;
+		}
+		out.push(ch);
+		used += ch_w;
+	}
+	if width > 0 {
+		out.push('…');
+	}
+	out
+}
+
+/// Breaks text into lines that are at most `width` cells wide. Newlines in the text always break.
+pub fn wrap(text: &str, width: u16, mode: WrapMode) -> Vec<String> {
+	let mut lines = Vec::new();
+	if width == 0 {
+		return lines;
+	}
+	let width = width as usize;
+	for para in text.split('\n') {
+		let mut line = String::new();
+		let mut line_w = 0;
+		match mode {
+			WrapMode::Char => push_chars(para, width, &mut lines, &mut line, &mut line_w),
+			WrapMode::Word => for (i, word) in para.split(' ').enumerate() {
+				let word_w = str_width(word);
+				if line_w > 0 && line_w + 1 + word_w > width {
+					lines.push(::std::mem::take(&mut line));
+					line_w = 0;
+				} else if i > 0 && line_w < width {
+					line.push(' ');
+					line_w += 1;
+				}
+				push_chars(word, width, &mut lines, &mut line, &mut line_w);
+			},
+		}
+		lines.push(line);
+	}
+	lines
+}
+
+/// Internal: Adds characters to the line, starting new lines whenever it's full
+fn push_chars(text: &str, width: usize, lines: &mut Vec<String>, line: &mut String, line_w: &mut usize) {
+	for ch in text.chars() {
+		let ch_w = char_width(ch) as usize;
+		if *line_w + ch_w > width && *line_w > 0 {
+			lines.push(::std::mem::take(line));
+			*line_w = 0;
+		}
+		line.push(ch);
+		*line_w += ch_w;
+	}
+}
+
+/// Internal: How far in to start something `size` big in `space`, for an alignment
+pub(crate) fn align_offset(size: usize, space: u16, align: Align) -> u16 {
+	let free = (space as usize).saturating_sub(size) as u16;
+	match align {
+		Align::Left => 0,
+		Align::Center => free / 2,
+		Align::Right => free,
+	}
+}
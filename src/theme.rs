@@ -0,0 +1,459 @@
+//! Named styles, so an app's colors live in one place and can be changed without touching its code.
+//!
+//! Themes can be loaded from a file with a style per line, e.g.
+//!
+//! ```text
+//! # Comments start with a #
+//! title = bold yellow on blue
+//! error = red
+//! selected = reverse
+//! link = underline #3366ff on default
+//! ```
+//!
+//! Colors are the 8 basic names, `default`, a palette index (0-255), or `#rrggbb`. The one after `on`
+//! is the background. Attributes are `bold`, `dim`, `italic`, `underline`, `blink`, `reverse`,
+//! `hidden`, `strike` and `curly`.
+//!
+//! Names can also be selectors that style widgets, see `Selector`:
+//!
+//! ```text
+//! Table.header = bold
+//! Table:focused.selection = black on cyan
+//! Table#files:focused.selection = black on green
+//! ```
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use ::screen::Screen;
+use ::style::{Attr, Color, Luminance, Style};
+use ::text::str_width;
+use ::window::Window;
+
+// What gets printed in every style on the preview page
+const SAMPLE: &str = "Sphinx of black quartz, judge my vow";
+// Contrast ratios below this get flagged on the preview page
+const MIN_CONTRAST: f32 = 4.5;
+// The built in themes, see `Theme::builtin`
+const DARK: &str = "
+title = bold cyan
+selection = black on cyan
+error = bold red
+warning = yellow
+success = green
+highlight = bold yellow
+muted = dim
+border = blue
+link = underline 75
+status = white on 236
+";
+const LIGHT: &str = "
+title = bold blue
+selection = white on blue
+error = bold 160
+warning = 130
+success = 28
+highlight = bold magenta
+muted = 244
+border = 245
+link = underline 26
+status = black on 252
+";
+
+/// What a theme does about styles whose colors don't contrast enough to read, see `Theme::set_contrast_policy`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ContrastPolicy {
+	/// Nothing, styles are used as they are (default)
+	#[default]
+	Off,
+	/// Styles are used as they are, but ones below this contrast ratio are listed by
+	/// `Theme::contrast_warnings` (and logged with the `tracing` feature when they're looked up)
+	Warn(f32),
+	/// The text color of styles below this contrast ratio gets moved towards black or white until
+	/// they reach it, see `Color::with_contrast`
+	Adjust(f32),
+}
+
+/// A set of styles, looked up by name or by `Selector`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Theme {
+	/// In the order they were set, which breaks ties when cascading
+	styles: Vec<(String, Style)>,
+	contrast: ContrastPolicy,
+}
+
+impl Theme {
+	pub fn new() -> Theme {
+		Theme::default()
+	}
+	/// One of the themes that come with rcurses, `"dark"` or `"light"` for terminals with a dark or a
+	/// light background. Both have styles for `title`, `selection`, `error`, `warning`, `success`,
+	/// `highlight`, `muted`, `border`, `link` and `status`, for apps to build on.
+	/// If there's no theme by that name, returns None
+	pub fn builtin(name: &str) -> Option<Theme> {
+		match name {
+			"dark" => Theme::parse(DARK),
+			"light" => Theme::parse(LIGHT),
+			_ => None,
+		}
+	}
+	/// The built in theme that goes with a dark or light background, e.g. from `Screen::detect_background`.
+	pub fn for_background(luminance: Luminance) -> Theme {
+		let text = match luminance {
+			Luminance::Dark => DARK,
+			Luminance::Light => LIGHT,
+		};
+		Theme::parse(text).unwrap_or_default()
+	}
+	/// Parses a theme in the format described at the top of this module.
+	/// If any line can't be understood, returns None
+	pub fn parse(text: &str) -> Option<Theme> {
+		let mut theme = Theme::new();
+		for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+			let (name, spec) = line.split_once('=')?;
+			let name = name.trim();
+			if name.is_empty() {
+				return None;
+			}
+			theme.set(name, parse_style(spec)?);
+		}
+		Some(theme)
+	}
+	/// Reads and parses a theme file.
+	/// If it can't be read or parsed, returns None
+	pub fn load<P: AsRef<Path>>(path: P) -> Option<Theme> {
+		Theme::parse(&fs::read_to_string(path).ok()?)
+	}
+	/// Sets the style for `name`, replacing any it had.
+	pub fn set(&mut self, name: &str, style: Style) {
+		match self.styles.iter_mut().find(|(n, _)| n == name) {
+			Some(entry) => entry.1 = style,
+			None => self.styles.push((name.to_string(), style)),
+		}
+	}
+	/// The style for `name`, if there is one.
+	pub fn get(&self, name: &str) -> Option<Style> {
+		self.styles.iter().find(|(n, _)| n == name).map(|&(_, style)| self.check_contrast(&name, style))
+	}
+	/// The style for `name`, or the terminal's default look if there isn't one.
+	pub fn style(&self, name: &str) -> Style {
+		self.get(name).unwrap_or_default()
+	}
+	/// Every name that has a style, in alphabetical order.
+	pub fn names(&self) -> Vec<&str> {
+		let mut names: Vec<&str> = self.styles.iter().map(|(n, _)| n.as_str()).collect();
+		names.sort_unstable();
+		names
+	}
+	/// The style for a widget, cascaded from every style in the theme whose selector matches `target`.
+	/// More specific selectors (see `Selector`) win over less specific ones, and ones set later win
+	/// over ones set earlier that are just as specific. A part (e.g. `Table.header`) starts from the
+	/// style of the widget it's part of.
+	///
+	/// NOTE: Styles only override what they set, so a `default` color can't undo one that was set by
+	/// a less specific style.
+	pub fn resolve(&self, target: &Selector) -> Style {
+		let base = match target.part {
+			Some(_) => self.resolve(&Selector { part: None, ..target.clone() }),
+			None => Style::new(),
+		};
+		let mut matching: Vec<(Selector, Style)> = self.styles.iter()
+			.filter_map(|(name, style)| Some((Selector::parse(name)?, *style)))
+			.filter(|(selector, _)| selector.matches(target))
+			.collect();
+		// Stable, so equally specific ones stay in the order they were set
+		matching.sort_by_key(|(selector, _)| selector.specificity());
+		let style = matching.into_iter().fold(base, |style, (_, over)| cascade(style, over));
+		self.check_contrast(target, style)
+	}
+	/// Sets what happens to styles whose colors don't contrast enough when they're looked up (with
+	/// `get`, `style` or `resolve`), e.g. for when a theme meets a terminal with an unusual palette.
+	///
+	/// NOTE: Contrast can't be worked out for the terminal's default colors, so styles using them are
+	/// never adjusted or warned about.
+	pub fn set_contrast_policy(&mut self, policy: ContrastPolicy) {
+		self.contrast = policy;
+	}
+	pub fn contrast_policy(&self) -> ContrastPolicy {
+		self.contrast
+	}
+	/// Every style in the theme that's below the contrast ratio of the policy, with its ratio, sorted by
+	/// name. Without a policy (or with `Off`), that's the ones below 4.5, the usual minimum.
+	pub fn contrast_warnings(&self) -> Vec<(String, f32)> {
+		let min = match self.contrast {
+			ContrastPolicy::Warn(min) | ContrastPolicy::Adjust(min) => min,
+			ContrastPolicy::Off => MIN_CONTRAST,
+		};
+		let mut low: Vec<(String, f32)> = self.styles.iter()
+			.filter_map(|(name, style)| Some((name.clone(), style.fg.contrast(style.bg)?)))
+			.filter(|&(_, ratio)| ratio < min)
+			.collect();
+		low.sort_by(|a, b| a.0.cmp(&b.0));
+		low
+	}
+	/// Internal: `style` after the contrast policy has been applied to it, `name` being what it was looked up as
+	fn check_contrast(&self, name: &dyn fmt::Debug, style: Style) -> Style {
+		match self.contrast {
+			ContrastPolicy::Off => style,
+			ContrastPolicy::Warn(min) => {
+				#[cfg(feature = "tracing")]
+				{
+					if let Some(ratio) = style.fg.contrast(style.bg).filter(|&r| r < min) {
+						::tracing::warn!("style {:?} has a contrast ratio of {:.1}:1, below {:.1}:1", name, ratio, min);
+					}
+				}
+				style
+			},
+			ContrastPolicy::Adjust(min) => Style { fg: style.fg.with_contrast(style.bg, min), ..style },
+		}
+	}
+}
+
+/// Picks out which widgets a style in a theme applies to, written `Kind#id:state.part` where every
+/// piece is optional (but there has to be at least one):
+///
+/// - `Kind` is the type of widget, e.g. `Table`, or `*` for any
+/// - `#id` is the id the app gave that one widget. In a theme file a line starting with `#` is a comment,
+///   so write `*#id` for any kind of widget with that id
+/// - `:state` is something the widget has to be, like `:focused`, `:disabled` or `:selected`. There
+///   can be several, and all of them have to apply
+/// - `.part` is part of the widget with its own style, like the `.header` of a table
+///
+/// Selectors with an id are the most specific, then ones with more states, then ones with a kind.
+///
+/// The same type describes the widget a style is wanted for, see `Theme::resolve`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Selector {
+	pub kind: Option<String>,
+	pub id: Option<String>,
+	pub states: Vec<String>,
+	pub part: Option<String>,
+}
+
+impl Selector {
+	/// A selector for a kind of widget.
+	pub fn new(kind: &str) -> Selector {
+		Selector { kind: Some(kind.to_string()), ..Selector::default() }
+	}
+	/// Returns the selector with an id.
+	pub fn id(mut self, id: &str) -> Selector {
+		self.id = Some(id.to_string());
+		self
+	}
+	/// Returns the selector with a state added.
+	pub fn state(mut self, state: &str) -> Selector {
+		self.states.push(state.to_string());
+		self
+	}
+	/// Returns the selector for a part of the widget.
+	pub fn part(mut self, part: &str) -> Selector {
+		self.part = Some(part.to_string());
+		self
+	}
+	/// Parses a selector like `Table#files:focused.header`.
+	/// If it's empty or a piece of it is, returns None
+	pub fn parse(text: &str) -> Option<Selector> {
+		let text = text.trim();
+		let is_name = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+		let end = text.find(['#', ':', '.']).unwrap_or(text.len());
+		let mut selector = Selector::default();
+		match &text[..end] {
+			"" | "*" => {},
+			kind if is_name(kind) => selector.kind = Some(kind.to_string()),
+			_ => return None,
+		}
+		let mut rest = &text[end..];
+		while let Some(marker) = rest.chars().next() {
+			let len = rest[1..].find(['#', ':', '.']).map_or(rest.len(), |i| i + 1);
+			let name = &rest[1..len];
+			if !is_name(name) {
+				return None;
+			}
+			match marker {
+				'#' if selector.id.is_none() => selector.id = Some(name.to_string()),
+				':' => selector.states.push(name.to_string()),
+				'.' if selector.part.is_none() => selector.part = Some(name.to_string()),
+				_ => return None,
+			}
+			rest = &rest[len..];
+		}
+		if selector == Selector::default() && !text.starts_with('*') {
+			return None;
+		}
+		Some(selector)
+	}
+	/// Whether a style with this selector applies to `target`.
+	pub fn matches(&self, target: &Selector) -> bool {
+		let fits = |want: &Option<String>, have: &Option<String>| want.is_none() || want == have;
+		fits(&self.kind, &target.kind)
+			&& fits(&self.id, &target.id)
+			&& self.part == target.part
+			&& self.states.iter().all(|s| target.states.contains(s))
+	}
+	/// Internal: How specific the selector is, more specific ones sort last
+	fn specificity(&self) -> (bool, usize, bool) {
+		(self.id.is_some(), self.states.len(), self.kind.is_some())
+	}
+}
+
+/// Internal: `over` laid on top of `base`, keeping whatever `over` doesn't set
+pub(crate) fn cascade(base: Style, over: Style) -> Style {
+	Style {
+		fg: if over.fg == Color::Default { base.fg } else { over.fg },
+		bg: if over.bg == Color::Default { base.bg } else { over.bg },
+		attrs: base.attrs | over.attrs,
+		link: over.link.or(base.link),
+		underline: if over.underline == Color::Default { base.underline } else { over.underline },
+	}
+}
+
+/// Parses a single style, like the right hand side of a line in a theme file (e.g. `bold red on black`).
+/// If a word isn't a color or an attribute, returns None
+pub fn parse_style(spec: &str) -> Option<Style> {
+	let mut style = Style::new();
+	let mut background = false;
+	for word in spec.split_whitespace().map(str::to_lowercase) {
+		let attr = match word.as_str() {
+			"on" => {
+				background = true;
+				continue;
+			},
+			"bold" => Attr::BOLD,
+			"dim" => Attr::DIM,
+			"italic" => Attr::ITALIC,
+			"underline" => Attr::UNDERLINE,
+			"blink" => Attr::BLINK,
+			"reverse" => Attr::REVERSE,
+			"hidden" => Attr::HIDDEN,
+			"strike" => Attr::STRIKE,
+			"curly" => Attr::CURLY,
+			_ => {
+				let color = parse_color(&word)?;
+				if background {
+					style.bg = color;
+				} else {
+					style.fg = color;
+				}
+				continue;
+			},
+		};
+		style.attrs |= attr;
+	}
+	Some(style)
+}
+
+/// Internal: Parses a color name, palette index, or `#rrggbb`
+fn parse_color(word: &str) -> Option<Color> {
+	Some(match word {
+		"default" => Color::Default,
+		"black" => Color::Black,
+		"red" => Color::Red,
+		"green" => Color::Green,
+		"yellow" => Color::Yellow,
+		"blue" => Color::Blue,
+		"magenta" => Color::Magenta,
+		"cyan" => Color::Cyan,
+		"white" => Color::White,
+		hex if hex.starts_with('#') && hex.len() == 7 => {
+			let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+			Color::Rgb(channel(1)?, channel(3)?, channel(5)?)
+		},
+		index => Color::Indexed(index.parse().ok()?),
+	})
+}
+
+/// Watches a theme file, for reloading it while working on an app's colors.
+///
+/// There's no notification when a file changes, so `check` has to be called every so often, e.g. on
+/// an `Event::Tick` from `Screen::set_timer`.
+#[derive(Debug, Clone)]
+pub struct ThemeWatcher {
+	path: PathBuf,
+	/// When the file was last changed, as of the last check
+	modified: Option<SystemTime>,
+}
+
+impl ThemeWatcher {
+	/// Starts watching `path`. The theme as it is now counts as seen, see `load` for reading it.
+	pub fn new<P: AsRef<Path>>(path: P) -> ThemeWatcher {
+		let path = path.as_ref().to_path_buf();
+		let modified = modified(&path);
+		ThemeWatcher { path, modified }
+	}
+	/// Reads the theme as it is now.
+	/// If it can't be read or parsed, returns None
+	pub fn load(&self) -> Option<Theme> {
+		Theme::load(&self.path)
+	}
+	/// Reloads the theme if the file changed since the last check.
+	/// If it didn't change, or the new version can't be parsed, returns None
+	///
+	/// NOTE: A version that can't be parsed isn't tried again until the file changes again, so half
+	/// saved files just get skipped.
+	pub fn check(&mut self) -> Option<Theme> {
+		let now = modified(&self.path);
+		if now == self.modified {
+			return None;
+		}
+		self.modified = now;
+		self.load()
+	}
+}
+
+/// Internal: When the file at `path` was last changed, if it can be told
+fn modified(path: &Path) -> Option<SystemTime> {
+	fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl Screen {
+	/// Draws a page for looking over a theme: the 16 basic colors, and then every style in the theme
+	/// with a sample of text and how well its colors contrast. Styles whose contrast is too low to read
+	/// comfortably are marked with a `!`. Shows on the next refresh.
+	///
+	/// NOTE: Contrast can't be worked out for the terminal's default colors, that shows as `?`.
+	pub fn preview_theme(&mut self, theme: &Theme) {
+		let area = self.layout_area();
+		let mut win = Window::new(area.y, area.x, area.height, area.width);
+		win.set_style(Style::new().attrs(Attr::BOLD));
+		win.print(0, 0, "Theme preview");
+
+		win.set_style(Style::new());
+		win.print(2, 0, "Palette");
+		for i in 0..16u16 {
+			let x = 10 + i * 3;
+			win.set_style(Style::new().bg(Color::Indexed(i as u8)));
+			win.print(2, x, "   ");
+			win.set_style(Style::new().attrs(Attr::DIM));
+			win.print(3, x, &format!("{:>2}", i));
+		}
+
+		let names = theme.names();
+		let name_width = names.iter().map(|n| str_width(n)).max().unwrap_or(0).max(5) as u16;
+		let sample_x = name_width + 2;
+		let contrast_x = sample_x + str_width(SAMPLE) as u16 + 2;
+		win.set_style(Style::new().attrs(Attr::UNDERLINE));
+		win.print(5, 0, "Style");
+		win.print(5, sample_x, "Sample");
+		win.print(5, contrast_x, "Contrast");
+		for (row, name) in names.iter().enumerate() {
+			let y = 6 + row as u16;
+			if y >= win.get_height() {
+				break;
+			}
+			let style = theme.style(name);
+			win.set_style(Style::new());
+			win.print(y, 0, name);
+			win.set_style(style);
+			win.print(y, sample_x, SAMPLE);
+			win.set_style(Style::new());
+			let contrast = match style.fg.contrast(style.bg) {
+				Some(ratio) if ratio < MIN_CONTRAST => format!("{:.1}:1 !", ratio),
+				Some(ratio) => format!("{:.1}:1", ratio),
+				None => "?".to_string(),
+			};
+			win.print(y, contrast_x, &contrast);
+		}
+		self.draw(&win);
+	}
+}
@@ -0,0 +1,162 @@
+//! Converting between UTF-8 and whatever encoding the terminal uses, for environments that aren't UTF-8.
+//!
+//! Latin-1 is always available. Anything else (KOI8-R, Shift_JIS, ...) needs the `encoding` feature,
+//! which uses `encoding_rs`.
+
+use std::cell::RefCell;
+use std::env;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// Converts text to and from the terminal's encoding.
+pub trait Transcoder {
+	/// Converts bytes read from the terminal into text.
+	/// Bytes that are part of an unfinished character should be kept for the next call.
+	fn decode(&mut self, input: &[u8]) -> String;
+	/// Converts text into bytes for the terminal.
+	/// Characters the encoding can't represent should become '?'.
+	fn encode(&mut self, text: &str) -> Vec<u8>;
+}
+
+/// A transcoder that can be shared between the input and output side of a `Screen`.
+/// Holding `None` means the terminal is UTF-8 and nothing gets converted.
+pub type SharedTranscoder = Rc<RefCell<Option<Box<dyn Transcoder>>>>;
+
+/// ISO-8859-1, where every byte is the character with the same number.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Latin1;
+
+impl Transcoder for Latin1 {
+	fn decode(&mut self, input: &[u8]) -> String {
+		input.iter().map(|&b| b as char).collect()
+	}
+	fn encode(&mut self, text: &str) -> Vec<u8> {
+		text.chars().map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' }).collect()
+	}
+}
+
+/// Any encoding supported by `encoding_rs`.
+#[cfg(feature = "encoding")]
+pub struct Encoding {
+	decoder: ::encoding_rs::Decoder,
+	encoder: ::encoding_rs::Encoder,
+}
+
+#[cfg(feature = "encoding")]
+impl Encoding {
+	/// Looks up an encoding by name, e.g. "koi8-r".
+	/// If it isn't known, returns None
+	pub fn for_label(label: &str) -> Option<Encoding> {
+		let encoding = ::encoding_rs::Encoding::for_label(label.as_bytes())?;
+		Some(Encoding {
+			decoder: encoding.new_decoder_without_bom_handling(),
+			encoder: encoding.new_encoder(),
+		})
+	}
+}
+
+#[cfg(feature = "encoding")]
+impl Transcoder for Encoding {
+	fn decode(&mut self, input: &[u8]) -> String {
+		let len = self.decoder.max_utf8_buffer_length(input.len()).unwrap_or(input.len() * 3);
+		let mut out = String::with_capacity(len);
+		// The buffer is big enough for all of the input, so everything gets read
+		let _ = self.decoder.decode_to_string(input, &mut out, false);
+		out
+	}
+	fn encode(&mut self, text: &str) -> Vec<u8> {
+		use ::encoding_rs::EncoderResult;
+
+		let mut out = Vec::new();
+		let mut rest = text;
+		loop {
+			let len = self.encoder.max_buffer_length_from_utf8_without_replacement(rest.len()).unwrap_or(rest.len() * 4);
+			out.reserve(len + 1);
+			let (result, read) = self.encoder.encode_from_utf8_to_vec_without_replacement(rest, &mut out, false);
+			rest = &rest[read..];
+			match result {
+				EncoderResult::InputEmpty => return out,
+				EncoderResult::OutputFull => {},
+				EncoderResult::Unmappable(_) => out.push(b'?'),
+			}
+		}
+	}
+}
+
+/// Picks a transcoder based on the locale's codeset (`LC_ALL`, `LC_CTYPE`, or `LANG`).
+/// If the locale is UTF-8 (or the codeset isn't supported), returns None
+pub fn from_locale() -> Option<Box<dyn Transcoder>> {
+	let locale = ["LC_ALL", "LC_CTYPE", "LANG"].iter()
+		.filter_map(|v| env::var(v).ok())
+		.find(|v| !v.is_empty())?;
+	// Looks like "ru_RU.KOI8-R" or "de_DE.ISO-8859-1@euro"
+	let codeset = locale.split('.').nth(1)?.split('@').next()?;
+	match &codeset.to_ascii_lowercase().replace('_', "-")[..] {
+		"utf-8" | "utf8" => None,
+		"iso-8859-1" | "iso8859-1" | "latin1" => Some(Box::new(Latin1)),
+		_ => from_label(codeset),
+	}
+}
+
+#[cfg(feature = "encoding")]
+fn from_label(label: &str) -> Option<Box<dyn Transcoder>> {
+	Encoding::for_label(label).map(|e| Box::new(e) as Box<dyn Transcoder>)
+}
+#[cfg(not(feature = "encoding"))]
+fn from_label(label: &str) -> Option<Box<dyn Transcoder>> {
+	None
+}
+
+/// Writer that encodes the UTF-8 written to it with a (shared) transcoder before passing it on.
+pub struct TranscodingWriter<W: Write> {
+	inner: W,
+	transcoder: SharedTranscoder,
+	/// The start of a character that was split between writes
+	pending: Vec<u8>,
+}
+
+impl<W: Write> TranscodingWriter<W> {
+	pub fn new(inner: W, transcoder: SharedTranscoder) -> TranscodingWriter<W> {
+		TranscodingWriter {
+			inner,
+			transcoder,
+			pending: Vec::new(),
+		}
+	}
+}
+
+impl<W: Write> Write for TranscodingWriter<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let mut transcoder = self.transcoder.borrow_mut();
+		let transcoder = match *transcoder {
+			Some(ref mut t) => t,
+			None => return self.inner.write(buf),
+		};
+		self.pending.extend_from_slice(buf);
+		let mut out = Vec::new();
+		loop {
+			let (valid, bad) = match ::std::str::from_utf8(&self.pending) {
+				Ok(text) => (text.len(), None),
+				Err(e) => (e.valid_up_to(), e.error_len()),
+			};
+			out.extend(transcoder.encode(::std::str::from_utf8(&self.pending[..valid]).unwrap()));
+			match bad {
+				// Not UTF-8 at all, there's no character to convert
+				Some(len) => {
+					out.push(b'?');
+					self.pending.drain(..valid + len);
+				},
+				// Whatever is left is the start of a character, wait for the rest of it
+				None => {
+					self.pending.drain(..valid);
+					break;
+				}
+			}
+		}
+		self.inner.write_all(&out)?;
+		Ok(buf.len())
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		self.inner.flush()
+	}
+}
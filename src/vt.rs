@@ -0,0 +1,508 @@
+//! A small terminal emulator, which turns what a program wrote to a terminal back into cells. It knows
+//! enough of a VT100/xterm for what full-screen programs (this library included) usually send, e.g. for
+//! playing a recording back inside of a window.
+
+use std::mem;
+use ::acs;
+use ::buffer::{Buffer, Cell, CONTINUATION};
+use ::rect::Rect;
+use ::style::{Attr, Color, Link, Style};
+use ::text::char_width;
+
+// Control sequences and strings (OSCs) longer than this aren't real ones, the rest gets dropped
+const MAX_SEQUENCE: usize = 4096;
+// Tabs stop this far apart
+const TAB_WIDTH: u16 = 8;
+
+// The basic colors in the order SGR numbers them
+const BASIC_COLORS: [Color; 8] = [Color::Black, Color::Red, Color::Green, Color::Yellow, Color::Blue, Color::Magenta, Color::Cyan, Color::White];
+
+/// Internal: Where the parser is in an escape sequence
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+	Ground,
+	Escape,
+	/// ESC followed by an intermediate byte, like `(` to pick a character set
+	EscapeIntermediate(char),
+	Csi,
+	/// A string (OSC, DCS, APC...) that runs until BEL or ST, only OSCs are kept
+	Str { osc: bool },
+	/// Saw an escape inside of a string, which ends it if a `\` comes next
+	StrEscape { osc: bool },
+}
+
+/// A terminal's screen, as left by the output fed into it.
+#[derive(Debug, Clone)]
+pub struct Emulator {
+	buf: Buffer,
+	/// The main screen, while the alternate one is being shown
+	main: Option<Buffer>,
+	y: u16,
+	x: u16,
+	/// The last character went in the last column, so the next one goes on the next line
+	wrap_next: bool,
+	style: Style,
+	/// Saved with ESC 7 (or CSI s), as (y, x, style, line drawing)
+	saved: (u16, u16, Style, bool),
+	/// Whether the line drawing character set is picked
+	line_drawing: bool,
+	/// The lines that scroll, as (top, bottom) with the bottom one included
+	region: (u16, u16),
+	cursor_visible: bool,
+	title: String,
+	state: State,
+	/// The parameters of the control sequence (or the contents of the OSC) being parsed
+	sequence: String,
+}
+
+impl Emulator {
+	/// A blank terminal that's `height` by `width`.
+	pub fn new(height: u16, width: u16) -> Emulator {
+		Emulator {
+			buf: Buffer::new(height, width),
+			main: None,
+			y: 0,
+			x: 0,
+			wrap_next: false,
+			style: Style::default(),
+			saved: (0, 0, Style::default(), false),
+			line_drawing: false,
+			region: (0, height.saturating_sub(1)),
+			cursor_visible: true,
+			title: String::new(),
+			state: State::Ground,
+			sequence: String::new(),
+		}
+	}
+	/// The size of the terminal, as (height, width).
+	pub fn size(&self) -> (u16, u16) {
+		(self.buf.get_height(), self.buf.get_width())
+	}
+	/// What's on the screen.
+	pub fn screen(&self) -> &Buffer {
+		&self.buf
+	}
+	/// Where the cursor is, as (y, x).
+	pub fn cursor(&self) -> (u16, u16) {
+		(self.y, self.x)
+	}
+	/// Whether the program wants the cursor shown.
+	pub fn cursor_visible(&self) -> bool {
+		self.cursor_visible
+	}
+	/// The last title the program set for the terminal window.
+	pub fn title(&self) -> &str {
+		&self.title
+	}
+	/// Changes the size of the terminal, keeping what fits of the screen. Programs don't get told, so
+	/// this is for when they said they were resizing it (like a resize in a recording).
+	pub fn resize(&mut self, height: u16, width: u16) {
+		let mut buf = Buffer::new(height, width);
+		buf.copy_from(&self.buf, 0, 0);
+		self.buf = buf;
+		if let Some(ref mut main) = self.main {
+			let mut buf = Buffer::new(height, width);
+			buf.copy_from(main, 0, 0);
+			*main = buf;
+		}
+		self.region = (0, height.saturating_sub(1));
+		self.y = self.y.min(height.saturating_sub(1));
+		self.x = self.x.min(width.saturating_sub(1));
+		self.wrap_next = false;
+	}
+	/// Puts the terminal back the way `new` made it, keeping the size.
+	pub fn reset(&mut self) {
+		let (height, width) = self.size();
+		*self = Emulator::new(height, width);
+	}
+	/// Takes in output from the program, which can come in pieces of any size.
+	pub fn feed(&mut self, text: &str) {
+		for ch in text.chars() {
+			self.advance(ch);
+		}
+	}
+	/// Internal: Takes in a single character of output
+	fn advance(&mut self, ch: char) {
+		self.state = match (self.state, ch) {
+			(State::Ground, '\x1b') => State::Escape,
+			(State::Ground, ch) if ch < ' ' || ch == '\x7f' => {
+				self.control(ch);
+				State::Ground
+			},
+			(State::Ground, ch) => {
+				self.print(ch);
+				State::Ground
+			},
+			(State::Escape, ch) => self.escape(ch),
+			(State::EscapeIntermediate(kind), ch) => {
+				if kind == '(' {
+					self.line_drawing = ch == '0';
+				}
+				State::Ground
+			},
+			(State::Csi, '\x1b') => State::Escape,
+			(State::Csi, ch) if ch < ' ' => {
+				// Control characters still do their thing in the middle of a sequence
+				self.control(ch);
+				State::Csi
+			},
+			(State::Csi, ch @ '@'..='~') => {
+				self.csi(ch);
+				State::Ground
+			},
+			(State::Csi, ch) => {
+				if self.sequence.len() < MAX_SEQUENCE {
+					self.sequence.push(ch);
+				}
+				State::Csi
+			},
+			(State::Str { osc }, '\x07') => {
+				if osc {
+					self.osc();
+				}
+				State::Ground
+			},
+			(State::Str { osc }, '\x1b') => State::StrEscape { osc },
+			(State::Str { osc }, ch) => {
+				if osc && self.sequence.len() < MAX_SEQUENCE {
+					self.sequence.push(ch);
+				}
+				State::Str { osc }
+			},
+			(State::StrEscape { osc }, '\\') => {
+				if osc {
+					self.osc();
+				}
+				State::Ground
+			},
+			// Anything else cancels the string, and starts another escape sequence
+			(State::StrEscape { .. }, ch) => self.escape(ch),
+		};
+	}
+	/// Internal: Handles the character after an ESC.
+	/// Returns the state to go on in
+	fn escape(&mut self, ch: char) -> State {
+		match ch {
+			'[' => {
+				self.sequence.clear();
+				return State::Csi;
+			},
+			']' => {
+				self.sequence.clear();
+				return State::Str { osc: true };
+			},
+			'P' | 'X' | '^' | '_' => return State::Str { osc: false },
+			'(' | ')' | '*' | '+' | '#' | '%' => return State::EscapeIntermediate(ch),
+			'\x1b' => return State::Escape,
+			'7' => self.save_cursor(),
+			'8' => self.restore_cursor(),
+			'D' => self.index(),
+			'E' => {
+				self.x = 0;
+				self.index();
+			},
+			'M' => self.reverse_index(),
+			'c' => self.reset(),
+			_ => {},
+		}
+		State::Ground
+	}
+	/// Internal: Handles a control character
+	fn control(&mut self, ch: char) {
+		match ch {
+			'\r' => self.move_to(self.y, 0),
+			'\n' | '\x0b' | '\x0c' => {
+				self.wrap_next = false;
+				self.index();
+			},
+			'\x08' => self.move_to(self.y, self.x.saturating_sub(1)),
+			'\t' => self.move_to(self.y, (self.x / TAB_WIDTH + 1) * TAB_WIDTH),
+			_ => {},
+		}
+	}
+	/// Internal: Puts a character at the cursor, and moves it along
+	fn print(&mut self, ch: char) {
+		let (height, width) = self.size();
+		let ch = if self.line_drawing { acs::from_acs(ch).unwrap_or(ch) } else { ch };
+		let w = char_width(ch);
+		if w == 0 || height == 0 || width == 0 {
+			return;
+		}
+		if self.wrap_next || self.x + w > width {
+			self.x = 0;
+			self.wrap_next = false;
+			self.index();
+		}
+		let (y, x) = (self.y, self.x);
+		// Don't leave half of a wide character behind on either side
+		if x > 0 && self.buf.get(y, x).is_some_and(|c| c.ch == CONTINUATION) {
+			self.buf.set(y, x - 1, self.blank());
+		}
+		if self.buf.get(y, x + w).is_some_and(|c| c.ch == CONTINUATION) {
+			self.buf.set(y, x + w, self.blank());
+		}
+		let style = self.style;
+		self.buf.set(y, x, Cell { ch, style });
+		if w == 2 {
+			self.buf.set(y, x + 1, Cell { ch: CONTINUATION, style });
+		}
+		if x + w >= width {
+			self.wrap_next = true;
+		} else {
+			self.x = x + w;
+		}
+	}
+	/// Internal: Handles a control sequence ending in `last`
+	fn csi(&mut self, last: char) {
+		let sequence = mem::take(&mut self.sequence);
+		let private = sequence.starts_with('?');
+		// Other kinds (like `CSI > ...` and `CSI 2 SP q`) don't change what's on the screen
+		if sequence.starts_with(['>', '<', '=']) || sequence.contains([' ', '!', '"', '$', '\'']) {
+			return;
+		}
+		let fields: Vec<&str> = sequence.trim_start_matches('?').split(';').collect();
+		let num = |i: usize, default: u16| fields.get(i).and_then(|f| f.split(':').next()?.parse().ok()).filter(|&n| n != 0).unwrap_or(default);
+		let (height, width) = self.size();
+		// There's nothing to move around or erase, but the style and modes still change
+		if (height == 0 || width == 0) && !matches!(last, 'm' | 'h' | 'l') {
+			return;
+		}
+		let (y, x) = (self.y, self.x);
+		let n = num(0, 1);
+		match last {
+			'A' => self.move_to(y.saturating_sub(n), x),
+			'B' | 'e' => self.move_to(y.saturating_add(n), x),
+			'C' | 'a' => self.move_to(y, x.saturating_add(n)),
+			'D' => self.move_to(y, x.saturating_sub(n)),
+			'E' => self.move_to(y.saturating_add(n), 0),
+			'F' => self.move_to(y.saturating_sub(n), 0),
+			'G' | '`' => self.move_to(y, n - 1),
+			'd' => self.move_to(n - 1, x),
+			'H' | 'f' => self.move_to(n - 1, num(1, 1) - 1),
+			'J' => match num(0, 0) {
+				0 => {
+					self.erase(Rect::new(y, x, 1, width));
+					self.erase(Rect::new(y + 1, 0, height, width));
+				},
+				1 => {
+					self.erase(Rect::new(0, 0, y, width));
+					self.erase(Rect::new(y, 0, 1, x + 1));
+				},
+				_ => self.erase(Rect::new(0, 0, height, width)),
+			},
+			'K' => match num(0, 0) {
+				0 => self.erase(Rect::new(y, x, 1, width)),
+				1 => self.erase(Rect::new(y, 0, 1, x + 1)),
+				_ => self.erase(Rect::new(y, 0, 1, width)),
+			},
+			'X' => self.erase(Rect::new(y, x, 1, n)),
+			'@' | 'P' => {
+				let mut row = self.buf.row(y).to_vec();
+				let n = (n as usize).min(row.len() - x as usize);
+				let blanks = vec![self.blank(); n];
+				if last == '@' {
+					row.splice(x as usize..x as usize, blanks);
+					row.truncate(width as usize);
+				} else {
+					row.drain(x as usize..x as usize + n);
+					row.extend(blanks);
+				}
+				self.buf.blit(&row, Rect::new(y, 0, 1, width));
+			},
+			'L' if y >= self.region.0 && y <= self.region.1 => self.scroll_down(y, n),
+			'M' if y >= self.region.0 && y <= self.region.1 => self.scroll_up(y, n),
+			'S' => self.scroll_up(self.region.0, n),
+			'T' => self.scroll_down(self.region.0, n),
+			'm' => self.sgr(&fields),
+			'r' => {
+				let bottom = num(1, height).min(height);
+				if num(0, 1) < bottom {
+					self.region = (num(0, 1) - 1, bottom - 1);
+					self.move_to(0, 0);
+				}
+			},
+			's' => self.save_cursor(),
+			'u' => self.restore_cursor(),
+			'h' | 'l' if private => {
+				for field in &fields {
+					self.set_mode(field.parse().unwrap_or(0), last == 'h');
+				}
+			},
+			_ => {},
+		}
+	}
+	/// Internal: Turns a private mode (`CSI ? mode h`) on or off
+	fn set_mode(&mut self, mode: u16, on: bool) {
+		match mode {
+			25 => self.cursor_visible = on,
+			47 | 1047 | 1049 => {
+				if mode == 1049 && on {
+					self.save_cursor();
+				}
+				let (height, width) = self.size();
+				match (on, self.main.take()) {
+					(true, None) => self.main = Some(mem::replace(&mut self.buf, Buffer::new(height, width))),
+					(false, Some(main)) => self.buf = main,
+					(_, main) => self.main = main,
+				}
+				if mode == 1049 && !on {
+					self.restore_cursor();
+				}
+			},
+			_ => {},
+		}
+	}
+	/// Internal: Changes the style from the fields of an SGR sequence
+	fn sgr(&mut self, fields: &[&str]) {
+		let mut fields = fields.iter().map(|f| f.split(':').map(|n| n.parse().unwrap_or(0)).collect::<Vec<u16>>());
+		while let Some(codes) = fields.next() {
+			let style = &mut self.style;
+			match codes.first().cloned().unwrap_or(0) {
+				0 => *style = Style { link: style.link, ..Style::default() },
+				1 => style.attrs |= Attr::BOLD,
+				2 => style.attrs |= Attr::DIM,
+				3 => style.attrs |= Attr::ITALIC,
+				4 => {
+					style.attrs.remove(Attr::UNDERLINE | Attr::CURLY);
+					match codes.get(1) {
+						Some(0) => {},
+						Some(3) => style.attrs |= Attr::CURLY,
+						_ => style.attrs |= Attr::UNDERLINE,
+					}
+				},
+				5 => style.attrs |= Attr::BLINK,
+				7 => style.attrs |= Attr::REVERSE,
+				8 => style.attrs |= Attr::HIDDEN,
+				9 => style.attrs |= Attr::STRIKE,
+				21 => style.attrs |= Attr::UNDERLINE,
+				22 => style.attrs.remove(Attr::BOLD | Attr::DIM),
+				23 => style.attrs.remove(Attr::ITALIC),
+				24 => style.attrs.remove(Attr::UNDERLINE | Attr::CURLY),
+				25 => style.attrs.remove(Attr::BLINK),
+				27 => style.attrs.remove(Attr::REVERSE),
+				28 => style.attrs.remove(Attr::HIDDEN),
+				29 => style.attrs.remove(Attr::STRIKE),
+				n @ 30..=37 => style.fg = BASIC_COLORS[n as usize - 30],
+				n @ 40..=47 => style.bg = BASIC_COLORS[n as usize - 40],
+				n @ 90..=97 => style.fg = Color::Indexed(n as u8 - 90 + 8),
+				n @ 100..=107 => style.bg = Color::Indexed(n as u8 - 100 + 8),
+				39 => style.fg = Color::Default,
+				49 => style.bg = Color::Default,
+				59 => style.underline = Color::Default,
+				n @ 38 | n @ 48 | n @ 58 => {
+					let color = extended_color(&codes, &mut fields);
+					match n {
+						38 => style.fg = color,
+						48 => style.bg = color,
+						_ => style.underline = color,
+					}
+				},
+				_ => {},
+			}
+		}
+	}
+	/// Internal: Handles an OSC, once it's ended
+	fn osc(&mut self) {
+		let sequence = mem::take(&mut self.sequence);
+		let mut parts = sequence.splitn(2, ';');
+		match (parts.next(), parts.next()) {
+			(Some("0"), Some(title)) | (Some("2"), Some(title)) => self.title = title.to_string(),
+			(Some("8"), Some(rest)) => {
+				// `8 ; params ; url`, where an empty URL ends the link
+				let url = rest.split_once(';').map_or("", |(_, url)| url);
+				self.style.link = if url.is_empty() { None } else { Some(Link::new(url)) };
+			},
+			_ => {},
+		}
+	}
+	/// Internal: Moves the cursor down a line, scrolling if it's at the bottom of the scrolling region
+	fn index(&mut self) {
+		if self.y == self.region.1 {
+			self.scroll_up(self.region.0, 1);
+		} else if self.y + 1 < self.buf.get_height() {
+			self.y += 1;
+		}
+	}
+	/// Internal: Moves the cursor up a line, scrolling if it's at the top of the scrolling region
+	fn reverse_index(&mut self) {
+		if self.y == self.region.0 {
+			self.scroll_down(self.region.0, 1);
+		} else {
+			self.y = self.y.saturating_sub(1);
+		}
+	}
+	/// Internal: Moves the lines from `top` to the bottom of the scrolling region up by `n`, with blank
+	/// lines coming in at the bottom
+	fn scroll_up(&mut self, top: u16, n: u16) {
+		let (bottom, width) = (self.region.1, self.buf.get_width());
+		for y in top..=bottom {
+			match y.checked_add(n).filter(|&from| from <= bottom) {
+				Some(from) => {
+					let row = self.buf.row(from).to_vec();
+					self.buf.blit(&row, Rect::new(y, 0, 1, width));
+				},
+				None => self.erase(Rect::new(y, 0, 1, width)),
+			}
+		}
+	}
+	/// Internal: Moves the lines from `top` to the bottom of the scrolling region down by `n`, with blank
+	/// lines coming in at `top`
+	fn scroll_down(&mut self, top: u16, n: u16) {
+		let (bottom, width) = (self.region.1, self.buf.get_width());
+		for y in (top..=bottom).rev() {
+			match y.checked_sub(n).filter(|&from| from >= top) {
+				Some(from) => {
+					let row = self.buf.row(from).to_vec();
+					self.buf.blit(&row, Rect::new(y, 0, 1, width));
+				},
+				None => self.erase(Rect::new(y, 0, 1, width)),
+			}
+		}
+	}
+	/// Internal: Blanks out the cells inside of `rect`
+	fn erase(&mut self, rect: Rect) {
+		let blank = self.blank();
+		self.buf.fill(rect, blank);
+	}
+	/// Internal: What erased cells get, which keeps the background color like most terminals do
+	fn blank(&self) -> Cell {
+		Cell { ch: ' ', style: Style::new().bg(self.style.bg) }
+	}
+	/// Internal: Moves the cursor to (y, x), or as close as it can get
+	fn move_to(&mut self, y: u16, x: u16) {
+		self.y = y.min(self.buf.get_height().saturating_sub(1));
+		self.x = x.min(self.buf.get_width().saturating_sub(1));
+		self.wrap_next = false;
+	}
+	/// Internal: ESC 7
+	fn save_cursor(&mut self) {
+		self.saved = (self.y, self.x, self.style, self.line_drawing);
+	}
+	/// Internal: ESC 8
+	fn restore_cursor(&mut self) {
+		let (y, x, style, line_drawing) = self.saved;
+		self.move_to(y, x);
+		self.style = style;
+		self.line_drawing = line_drawing;
+	}
+}
+
+/// Internal: The color of an extended color SGR (38, 48 or 58), either given in `codes` with colons
+/// (`38:5:n`) or in the fields after it (`38;5;n`)
+fn extended_color<I: Iterator<Item = Vec<u16>>>(codes: &[u16], fields: &mut I) -> Color {
+	let mut args: Vec<u16> = codes[1..].to_vec();
+	if args.is_empty() {
+		let kind = fields.next().and_then(|f| f.first().cloned()).unwrap_or(0);
+		args.push(kind);
+		let count = if kind == 5 { 1 } else if kind == 2 { 3 } else { 0 };
+		args.extend(fields.take(count).map(|f| f.first().cloned().unwrap_or(0)));
+	} else if args[0] == 2 && args.len() == 5 {
+		// `38:2:colorspace:r:g:b` with the colorspace left empty
+		args.remove(1);
+	}
+	match args[..] {
+		[5, n, ..] => Color::Indexed(n as u8),
+		[2, r, g, b, ..] => Color::Rgb(r as u8, g as u8, b as u8),
+		_ => Color::Default,
+	}
+}
@@ -0,0 +1,47 @@
+//! Decorating ranges of text in text widgets, e.g. with a spell checker or a linter.
+//!
+//! A widget asks its `TextAnnotator` about each line it's about to draw, and lays the styles it gets
+//! back over the text. The widget doesn't need to know anything about what's doing the checking, and
+//! a closure taking the line's index and text does as an annotator.
+
+use std::ops::Range;
+use ::style::{Attr, Color, Style};
+
+/// A style laid over part of a line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+	/// The bytes of the line it covers
+	pub range: Range<usize>,
+	/// Laid over the style the text already has, so only what it sets changes
+	pub style: Style,
+}
+
+impl Annotation {
+	pub fn new(range: Range<usize>, style: Style) -> Annotation {
+		Annotation { range, style }
+	}
+	/// A curly underline in `color`, the usual look for spelling mistakes and warnings.
+	pub fn squiggle(color: Color) -> Style {
+		Style::new().attrs(Attr::CURLY).underline(color)
+	}
+}
+
+/// Something that decorates lines of text, see the module docs.
+pub trait TextAnnotator {
+	/// The annotations for a line that's about to be drawn. `line` is its index in the widget, so
+	/// results can be cached. Annotations can overlap, later ones are laid over earlier ones.
+	fn annotate(&mut self, line: usize, text: &str) -> Vec<Annotation>;
+}
+
+impl<F: FnMut(usize, &str) -> Vec<Annotation>> TextAnnotator for F {
+	fn annotate(&mut self, line: usize, text: &str) -> Vec<Annotation> {
+		self(line, text)
+	}
+}
+
+/// Internal: The style of the character at `byte`, with every annotation covering it laid over `base`
+pub(crate) fn style_at(annotations: &[Annotation], byte: usize, base: Style) -> Style {
+	annotations.iter()
+		.filter(|a| a.range.contains(&byte))
+		.fold(base, |style, a| ::theme::cascade(style, a.style))
+}
@@ -0,0 +1,210 @@
+//! Plays back a recording (see `record`) inside of a window, e.g. for a demo kiosk or for looking over
+//! a recorded session without leaving the app.
+
+use std::time::{Duration, Instant};
+use ::event::{Event, Key};
+use ::rect::Rect;
+use ::record::{Frame, Recording};
+use ::style::{Attr, Style};
+use ::theme::{Selector, Theme};
+use ::vt::Emulator;
+use ::window::Window;
+
+// How far Left and Right seek, in seconds
+const SEEK_STEP: f64 = 5.0;
+// How slow and fast it can play, as a multiple of the speed it was recorded at
+const MIN_SPEED: f64 = 0.125;
+const MAX_SPEED: f64 = 16.0;
+
+/// A recording being played back, with a status line under it.
+///
+/// It only moves along when `update` is called, so that should happen often while it's playing, e.g.
+/// on every tick of a timer (`next_frame_in` says when there's something new to show). Space pauses,
+/// Left and Right seek, + and - change the speed, and Home and End go to the start and the end.
+#[derive(Debug, Clone)]
+pub struct CastPlayer {
+	recording: Recording,
+	term: Emulator,
+	/// How many of the recording's frames have been fed to `term`
+	next: usize,
+	/// How far into the recording it is, in seconds
+	position: f64,
+	speed: f64,
+	paused: bool,
+	/// When `position` was last moved along, while playing
+	last_update: Option<Instant>,
+	status_style: Style,
+}
+
+impl CastPlayer {
+	/// A player at the start of `recording`, which starts playing on the first `update`.
+	pub fn new(recording: Recording) -> CastPlayer {
+		let (height, width) = recording.size();
+		CastPlayer {
+			recording,
+			term: Emulator::new(height, width),
+			next: 0,
+			position: 0.0,
+			speed: 1.0,
+			paused: false,
+			last_update: None,
+			status_style: Style::new().attrs(Attr::REVERSE),
+		}
+	}
+	pub fn recording(&self) -> &Recording {
+		&self.recording
+	}
+	/// The terminal the recording is played on, with what it shows at the current position.
+	pub fn terminal(&self) -> &Emulator {
+		&self.term
+	}
+	/// How far into the recording it is, in seconds.
+	pub fn position(&self) -> f64 {
+		self.position
+	}
+	/// Whether it's played all the way to the end.
+	pub fn is_finished(&self) -> bool {
+		self.next >= self.recording.frames().len()
+	}
+	/// Sets how fast it plays, as a multiple of the speed it was recorded at (1 by default).
+	pub fn set_speed(&mut self, speed: f64) {
+		self.update();
+		self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+	}
+	pub fn speed(&self) -> f64 {
+		self.speed
+	}
+	pub fn set_paused(&mut self, paused: bool) {
+		self.update();
+		self.paused = paused;
+		self.last_update = None;
+	}
+	pub fn is_paused(&self) -> bool {
+		self.paused
+	}
+	/// Jumps to `seconds` into the recording. Going backwards plays it again from the start, so it can
+	/// take a moment on long recordings.
+	pub fn seek(&mut self, seconds: f64) {
+		let seconds = seconds.clamp(0.0, self.recording.duration());
+		if seconds < self.position {
+			let (height, width) = self.recording.size();
+			self.term = Emulator::new(height, width);
+			self.next = 0;
+		}
+		self.position = seconds;
+		self.last_update = None;
+		self.play_frames();
+	}
+	/// Jumps `seconds` forwards, or backwards if it's negative.
+	pub fn seek_by(&mut self, seconds: f64) {
+		let position = self.position;
+		self.seek(position + seconds);
+	}
+	/// Moves along by however long it's been since last time, if it's playing.
+	/// Returns whether there's anything new to show
+	pub fn update(&mut self) -> bool {
+		let now = Instant::now();
+		if !self.paused {
+			if let Some(last) = self.last_update {
+				let elapsed = now.duration_since(last).as_secs_f64() * self.speed;
+				self.position = (self.position + elapsed).min(self.recording.duration());
+			}
+			self.last_update = Some(now);
+		}
+		self.play_frames()
+	}
+	/// How long until the next frame is due, at the current speed.
+	/// If it's paused or finished, returns None
+	pub fn next_frame_in(&self) -> Option<Duration> {
+		if self.paused {
+			return None;
+		}
+		let &(time, _) = self.recording.frames().get(self.next)?;
+		let played = self.last_update.map_or(0.0, |last| last.elapsed().as_secs_f64() * self.speed);
+		// A frame too far off to wait for is as good as never coming
+		let wait = ((time - self.position - played) / self.speed).max(0.0);
+		Some(Duration::try_from_secs_f64(wait).unwrap_or(Duration::MAX))
+	}
+	/// Sets the style of the status line (reversed by default).
+	pub fn set_status_style(&mut self, style: Style) {
+		self.status_style = style;
+	}
+	/// Takes the status line's style from the `status` part of `selector` in `theme`, e.g.
+	/// `CastPlayer.status`. It's left alone if the theme has nothing for it.
+	pub fn apply_theme(&mut self, theme: &Theme, selector: &Selector) {
+		let status = theme.resolve(&selector.clone().part("status"));
+		if status != Style::default() {
+			self.status_style = status;
+		}
+	}
+	/// Handles the playback keys.
+	/// Returns whether the event was used
+	pub fn handle(&mut self, event: &Event) -> bool {
+		let key = match *event {
+			Event::Key(key) => key,
+			_ => return false,
+		};
+		match key {
+			Key::Char(' ') => {
+				let paused = !self.paused;
+				self.set_paused(paused);
+			},
+			Key::Left => self.seek_by(-SEEK_STEP),
+			Key::Right => self.seek_by(SEEK_STEP),
+			Key::Char('+') | Key::Char('=') => {
+				let speed = self.speed * 2.0;
+				self.set_speed(speed);
+			},
+			Key::Char('-') => {
+				let speed = self.speed / 2.0;
+				self.set_speed(speed);
+			},
+			Key::Home => self.seek(0.0),
+			Key::End => {
+				let end = self.recording.duration();
+				self.seek(end);
+			},
+			_ => return false,
+		}
+		true
+	}
+	/// Draws the terminal at the current position, with the status line on the bottom row of the window.
+	/// A recording bigger than the window gets cut off on the right and the bottom.
+	pub fn draw(&mut self, win: &mut Window) {
+		win.clear();
+		let height = win.get_height();
+		win.buffer_mut().copy_from(self.term.screen(), 0, 0);
+		if height < 2 {
+			return;
+		}
+		let status = format!(" {} {} / {}  {}x", if self.paused { "||" } else { ">" },
+			clock(self.position), clock(self.recording.duration()), self.speed);
+		let width = win.get_width();
+		win.fill(Rect::new(height - 1, 0, 1, width), ' ', self.status_style);
+		win.set_style(self.status_style);
+		win.print(height - 1, 0, &status);
+		win.set_style(Style::new());
+	}
+	/// Internal: Feeds the terminal every frame up to the current position.
+	/// Returns whether there were any
+	fn play_frames(&mut self) -> bool {
+		let start = self.next;
+		while let Some(&(time, ref frame)) = self.recording.frames().get(self.next) {
+			if time > self.position {
+				break;
+			}
+			match *frame {
+				Frame::Output(ref text) => self.term.feed(text),
+				Frame::Resize(height, width) => self.term.resize(height, width),
+			}
+			self.next += 1;
+		}
+		self.next > start
+	}
+}
+
+/// Internal: `seconds` as minutes and seconds, like `1:05`
+fn clock(seconds: f64) -> String {
+	let seconds = seconds as u64;
+	format!("{}:{:02}", seconds / 60, seconds % 60)
+}
@@ -0,0 +1,455 @@
+//! A form of labeled fields, for settings screens, logins and the like.
+
+use std::fmt;
+use std::rc::Rc;
+use ::event::{Event, Key};
+use ::style::{Attr, Color, Style};
+use ::text::{self, char_width, str_width};
+use ::theme::{Selector, Theme};
+use ::window::Window;
+
+/// Checks what's in a field, returning what's wrong with it as the error to show, see `Field::validate`.
+pub type Validator = Rc<dyn Fn(&Value) -> Result<(), String>>;
+
+/// What a field holds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+	/// What's typed in a text or password field
+	Text(String),
+	/// Whether a checkbox is ticked
+	Checked(bool),
+	/// Which option of a select is picked
+	Choice(usize),
+}
+
+/// What sort of field it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldKind {
+	Text,
+	/// Text that's drawn as `*`s
+	Password,
+	Checkbox,
+	/// One of a list of options, changed with Left and Right (or Space)
+	Select(Vec<String>),
+}
+
+/// How the user left a form, see `Form::handle`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormResult {
+	/// Enter was pressed, and every field is valid
+	Submitted,
+	/// Esc was pressed
+	Cancelled,
+}
+
+/// A labeled field of a `Form`.
+#[derive(Clone)]
+pub struct Field {
+	label: String,
+	kind: FieldKind,
+	value: Value,
+	/// Where the cursor is in the text, in characters
+	cursor: usize,
+	validator: Option<Validator>,
+	/// What the validator said last time it was run, if it didn't like the value
+	error: Option<String>,
+}
+
+impl Field {
+	/// A field to type text in.
+	pub fn text(label: &str) -> Field {
+		Field::new(label, FieldKind::Text, Value::Text(String::new()))
+	}
+	/// A field to type text in that doesn't show it.
+	pub fn password(label: &str) -> Field {
+		Field::new(label, FieldKind::Password, Value::Text(String::new()))
+	}
+	/// A box that's ticked or not, toggled with Space.
+	pub fn checkbox(label: &str) -> Field {
+		Field::new(label, FieldKind::Checkbox, Value::Checked(false))
+	}
+	/// A pick of one of `options`, the first one to begin with.
+	pub fn select(label: &str, options: &[&str]) -> Field {
+		let options = options.iter().map(|o| o.to_string()).collect();
+		Field::new(label, FieldKind::Select(options), Value::Choice(0))
+	}
+	/// Internal: A field with nothing in it yet
+	fn new(label: &str, kind: FieldKind, value: Value) -> Field {
+		Field {
+			label: label.to_string(),
+			kind,
+			value,
+			cursor: 0,
+			validator: None,
+			error: None,
+		}
+	}
+	/// Returns the field with its text (for text and password fields) changed.
+	pub fn value(mut self, text: &str) -> Field {
+		if let Value::Text(ref mut value) = self.value {
+			*value = text.to_string();
+			self.cursor = text.chars().count();
+		}
+		self
+	}
+	/// Returns the field with its box (for checkboxes) ticked or not.
+	pub fn checked(mut self, checked: bool) -> Field {
+		if let Value::Checked(ref mut value) = self.value {
+			*value = checked;
+		}
+		self
+	}
+	/// Returns the field with another option (for selects) picked. Ones past the end pick the last one.
+	pub fn choice(mut self, choice: usize) -> Field {
+		if let FieldKind::Select(ref options) = self.kind {
+			self.value = Value::Choice(choice.min(options.len().saturating_sub(1)));
+		}
+		self
+	}
+	/// Returns the field with `validator` checking it, which is run when the field is left and when the
+	/// form is submitted. The error it returns is shown under the field until it's fixed.
+	pub fn validate<F: Fn(&Value) -> Result<(), String> + 'static>(mut self, validator: F) -> Field {
+		self.validator = Some(Rc::new(validator));
+		self
+	}
+	pub fn label(&self) -> &str {
+		&self.label
+	}
+	pub fn kind(&self) -> &FieldKind {
+		&self.kind
+	}
+	pub fn get(&self) -> &Value {
+		&self.value
+	}
+	/// What's typed in a text or password field, an empty string for other fields.
+	pub fn text_value(&self) -> &str {
+		match self.value {
+			Value::Text(ref text) => text,
+			_ => "",
+		}
+	}
+	/// What the validator said was wrong with the field, as of the last time it was run.
+	pub fn error(&self) -> Option<&str> {
+		self.error.as_deref()
+	}
+	/// Runs the validator, if there is one, and keeps the error it returns.
+	/// Returns whether the field is valid
+	pub fn check(&mut self) -> bool {
+		self.error = self.validator.as_ref().and_then(|v| v(&self.value).err());
+		self.error.is_none()
+	}
+	/// Internal: Handles a key for the field itself.
+	/// Returns whether it was used
+	fn handle_key(&mut self, key: Key) -> bool {
+		match (&self.kind, &mut self.value) {
+			(FieldKind::Checkbox, Value::Checked(checked)) => match key {
+				Key::Char(' ') => *checked = !*checked,
+				_ => return false,
+			},
+			(FieldKind::Select(options), Value::Choice(choice)) => {
+				let count = options.len().max(1);
+				match key {
+					Key::Left => *choice = (*choice + count - 1) % count,
+					Key::Right | Key::Char(' ') => *choice = (*choice + 1) % count,
+					_ => return false,
+				}
+			},
+			(_, Value::Text(text)) => {
+				let mut chars: Vec<char> = text.chars().collect();
+				let cursor = self.cursor.min(chars.len());
+				self.cursor = match key {
+					Key::Char(c) => {
+						chars.insert(cursor, c);
+						cursor + 1
+					},
+					Key::Backspace | Key::Ctrl('h') if cursor > 0 => {
+						chars.remove(cursor - 1);
+						cursor - 1
+					},
+					Key::Delete | Key::Ctrl('d') if cursor < chars.len() => {
+						chars.remove(cursor);
+						cursor
+					},
+					Key::Ctrl('u') => {
+						chars.drain(..cursor);
+						0
+					},
+					Key::Left => cursor.saturating_sub(1),
+					Key::Right => (cursor + 1).min(chars.len()),
+					Key::Home | Key::Ctrl('a') => 0,
+					Key::End | Key::Ctrl('e') => chars.len(),
+					_ => return false,
+				};
+				*text = chars.into_iter().collect();
+			},
+			_ => return false,
+		}
+		true
+	}
+	/// Internal: How the value is drawn, with where the cursor goes in it (in cells)
+	fn shown(&self) -> (String, usize) {
+		match self.value {
+			Value::Text(ref text) => {
+				let chars: Vec<char> = match self.kind {
+					FieldKind::Password => text.chars().map(|_| '*').collect(),
+					_ => text.chars().collect(),
+				};
+				let cursor = chars[..self.cursor.min(chars.len())].iter().map(|&c| char_width(c) as usize).sum();
+				(chars.into_iter().collect(), cursor)
+			},
+			Value::Checked(checked) => ((if checked { "[x]" } else { "[ ]" }).to_string(), 1),
+			Value::Choice(choice) => match self.kind {
+				FieldKind::Select(ref options) => (format!("< {} >", options.get(choice).map_or("", |o| &o[..])), 0),
+				_ => (String::new(), 0),
+			},
+		}
+	}
+}
+
+impl fmt::Debug for Field {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// Passwords stay out of logs
+		let value = match self.kind {
+			FieldKind::Password => Value::Text("***".to_string()),
+			_ => self.value.clone(),
+		};
+		write!(f, "Field({:?}, {:?}, {:?})", self.label, self.kind, value)
+	}
+}
+
+/// Fields drawn one per row with their labels lined up on the left, and the focused one highlighted.
+///
+/// Tab and Down go to the next field, Shift+Tab and Up to the one before. Enter submits the form if
+/// every field is valid (otherwise the first one that isn't gets focus), and Esc cancels it. The other
+/// keys go to the focused field: typing and the usual editing keys for text, Space for checkboxes, and
+/// Left and Right for selects.
+#[derive(Debug, Clone)]
+pub struct Form {
+	fields: Vec<Field>,
+	focused: usize,
+	/// The first row that's shown
+	offset: usize,
+	/// Where the cursor goes, in the window's coordinates, as of the last draw
+	cursor: Option<(u16, u16)>,
+	label_style: Style,
+	field_style: Style,
+	focused_style: Style,
+	error_style: Style,
+}
+
+impl Form {
+	pub fn new() -> Form {
+		Form {
+			fields: Vec::new(),
+			focused: 0,
+			offset: 0,
+			cursor: None,
+			label_style: Style::new(),
+			field_style: Style::new().attrs(Attr::UNDERLINE),
+			focused_style: Style::new().attrs(Attr::REVERSE),
+			error_style: Style::new().fg(Color::Red),
+		}
+	}
+	/// Returns the form with `field` added at the bottom.
+	pub fn field(mut self, field: Field) -> Form {
+		self.fields.push(field);
+		self
+	}
+	/// Adds a field at the bottom.
+	pub fn push(&mut self, field: Field) {
+		self.fields.push(field);
+	}
+	pub fn fields(&self) -> &[Field] {
+		&self.fields
+	}
+	/// The field with `label`, if there is one.
+	pub fn get(&self, label: &str) -> Option<&Field> {
+		self.fields.iter().find(|f| f.label == label)
+	}
+	/// The field with `label`, for changing it. If there's no such field, returns None
+	pub fn get_mut(&mut self, label: &str) -> Option<&mut Field> {
+		self.fields.iter_mut().find(|f| f.label == label)
+	}
+	/// Every field's value, in order.
+	pub fn values(&self) -> Vec<Value> {
+		self.fields.iter().map(|f| f.value.clone()).collect()
+	}
+	/// Which field has focus.
+	pub fn focused(&self) -> usize {
+		self.focused
+	}
+	/// Moves the focus to a field. Ones past the end focus the last field.
+	pub fn focus(&mut self, field: usize) {
+		self.focused = field.min(self.fields.len().saturating_sub(1));
+	}
+	/// Runs every field's validator.
+	/// Returns whether they're all valid
+	pub fn check(&mut self) -> bool {
+		// Every one gets checked, so they all show their errors
+		self.fields.iter_mut().map(Field::check).filter(|&valid| !valid).count() == 0
+	}
+	/// Where to put the terminal's cursor (in the window's coordinates) as of the last draw, when a text
+	/// field has focus.
+	pub fn cursor(&self) -> Option<(u16, u16)> {
+		self.cursor
+	}
+	/// Sets the style of the labels, plain by default.
+	pub fn set_label_style(&mut self, style: Style) {
+		self.label_style = style;
+	}
+	/// Sets the style of the fields, underlined by default.
+	pub fn set_field_style(&mut self, style: Style) {
+		self.field_style = style;
+	}
+	/// Sets the style of the focused field, reversed by default.
+	pub fn set_focused_style(&mut self, style: Style) {
+		self.focused_style = style;
+	}
+	/// Sets the style of validation errors, red by default.
+	pub fn set_error_style(&mut self, style: Style) {
+		self.error_style = style;
+	}
+	/// Takes the styles from the `label`, `field`, `focused` and `error` parts of `selector` in `theme`,
+	/// e.g. `Form#login.error`. Styles the theme has nothing for are left alone.
+	pub fn apply_theme(&mut self, theme: &Theme, selector: &Selector) {
+		let parts = [
+			("label", &mut self.label_style),
+			("field", &mut self.field_style),
+			("focused", &mut self.focused_style),
+			("error", &mut self.error_style),
+		];
+		for (part, style) in parts {
+			let themed = theme.resolve(&selector.clone().part(part));
+			if themed != Style::default() {
+				*style = themed;
+			}
+		}
+	}
+	/// Handles a key.
+	/// Returns how the form was left, or None if it's still being filled in
+	pub fn handle(&mut self, event: &Event) -> Option<FormResult> {
+		let key = match *event {
+			Event::Key(key) => key,
+			_ => return None,
+		};
+		let count = self.fields.len();
+		match key {
+			Key::Esc => return Some(FormResult::Cancelled),
+			Key::Enter => {
+				if self.check() {
+					return Some(FormResult::Submitted);
+				}
+				if let Some(i) = self.fields.iter().position(|f| f.error.is_some()) {
+					self.focused = i;
+				}
+			},
+			_ if count == 0 => {},
+			Key::Tab | Key::Down => self.move_focus(1),
+			Key::BackTab | Key::Up => self.move_focus(count - 1),
+			key => {
+				self.fields[self.focused].handle_key(key);
+			},
+		}
+		None
+	}
+	/// Draws the form over the whole of `win`, scrolled so the focused field is in view.
+	pub fn draw(&mut self, win: &mut Window) {
+		let (height, width) = (win.get_height() as usize, win.get_width());
+		let style = win.get_style();
+		win.clear();
+		self.cursor = None;
+		// Labels get as much room as the longest one needs, up to half of the width
+		let label_w = self.fields.iter().map(|f| str_width(&f.label)).max().unwrap_or(0).min(width as usize / 2) as u16;
+		let field_x = if label_w > 0 { label_w + 2 } else { 0 };
+		let field_w = width.saturating_sub(field_x);
+
+		// Each field takes a row, and another for its error if it has one
+		let rows: Vec<usize> = self.fields.iter().scan(0, |row, f| {
+			let start = *row;
+			*row += if f.error.is_some() { 2 } else { 1 };
+			Some(start)
+		}).collect();
+		if let Some(&top) = rows.get(self.focused) {
+			let bottom = top + if self.fields[self.focused].error.is_some() { 2 } else { 1 };
+			if top < self.offset {
+				self.offset = top;
+			} else if bottom > self.offset + height {
+				self.offset = bottom.saturating_sub(height);
+			}
+		}
+
+		for (i, (field, &row)) in self.fields.iter().zip(&rows).enumerate() {
+			if row < self.offset || row >= self.offset + height {
+				continue;
+			}
+			let y = (row - self.offset) as u16;
+			win.set_style(self.label_style);
+			let label = text::truncate(&field.label, label_w);
+			win.print(y, label_w - str_width(&label) as u16, &label);
+			if field_w == 0 {
+				// No room left for the field itself
+				continue;
+			}
+
+			let (shown, cursor) = field.shown();
+			// Keep the cursor in view, with room for it after the end of the text
+			let mut skip = 0;
+			let mut before = cursor;
+			for c in shown.chars() {
+				if before < field_w as usize || before == 0 {
+					break;
+				}
+				skip += c.len_utf8();
+				before = before.saturating_sub(char_width(c) as usize);
+			}
+			let focused = i == self.focused;
+			win.set_style(if focused { self.focused_style } else { self.field_style });
+			let padded = match field.kind {
+				// Text fields show how much room there is
+				FieldKind::Text | FieldKind::Password => field_w,
+				_ => str_width(&shown).min(field_w as usize) as u16,
+			};
+			win.print(y, field_x, &" ".repeat(padded as usize));
+			win.print(y, field_x, &text::truncate(&shown[skip..], field_w));
+			if focused && matches!(field.value, Value::Text(_)) {
+				self.cursor = Some((y, field_x + before.min(field_w.saturating_sub(1) as usize) as u16));
+			}
+
+			if let Some(ref error) = field.error {
+				if row + 1 < self.offset + height {
+					win.set_style(self.error_style);
+					win.print(y + 1, field_x, &text::truncate(error, field_w));
+				}
+			}
+		}
+		win.set_style(style);
+	}
+	/// Internal: Moves the focus `by` fields forwards, going round, and checks the field that's left
+	fn move_focus(&mut self, by: usize) {
+		self.fields[self.focused].check();
+		self.focused = (self.focused + by) % self.fields.len();
+	}
+}
+
+impl Default for Form {
+	fn default() -> Form {
+		Form::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn draw_narrow() {
+		for width in 0..8 {
+			let mut form = Form::new().field(Field::text("Name").value("ab")).field(Field::checkbox("Remember"));
+			form.handle(&Event::Key(Key::Home));
+			let mut win = Window::new(0, 0, 5, width);
+			form.draw(&mut win);
+			form.handle(&Event::Key(Key::End));
+			form.draw(&mut win);
+		}
+	}
+}
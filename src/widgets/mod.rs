@@ -0,0 +1,15 @@
+//! Ready-made components that draw into a `Window` and handle their own events.
+
+pub mod annotate;
+pub mod cast_player;
+pub mod form;
+pub mod overflow;
+pub mod table;
+pub mod text_view;
+
+pub use self::annotate::{Annotation, TextAnnotator};
+pub use self::cast_player::CastPlayer;
+pub use self::form::{Field, FieldKind, Form, FormResult, Value};
+pub use self::overflow::{OverflowHints, Overflowing};
+pub use self::table::{Column, Table};
+pub use self::text_view::TextView;
@@ -0,0 +1,136 @@
+//! Hints at the edges of a scrolled view that there's more to see, like "▲ more" above a list.
+
+use ::rect::Rect;
+use ::style::{Attr, Style};
+use ::text::{self, str_width};
+use ::theme::{Selector, Theme};
+use ::window::Window;
+
+/// Which sides of a view have more content past them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Overflowing {
+	pub up: bool,
+	pub down: bool,
+	pub left: bool,
+	pub right: bool,
+}
+
+impl Overflowing {
+	/// Whether there's more past any side.
+	pub fn any(&self) -> bool {
+		self.up || self.down || self.left || self.right
+	}
+}
+
+/// What scrolling widgets (`TextView`, `Table`) draw at their edges when their content doesn't fit,
+/// see `set_overflow_hints` on them.
+///
+/// The hints for above and below go at the right end of the top and bottom rows, and the ones for
+/// the sides in the middle of the left and right columns, over whatever is there. With fading on, the
+/// rows and columns along those edges are dimmed too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverflowHints {
+	up: String,
+	down: String,
+	left: String,
+	right: String,
+	style: Style,
+	fade: bool,
+}
+
+impl OverflowHints {
+	/// "▲ more" and "▼ more" above and below, and arrows on the sides, dimmed.
+	pub fn new() -> OverflowHints {
+		OverflowHints {
+			up: "▲ more".to_string(),
+			down: "▼ more".to_string(),
+			left: "◀".to_string(),
+			right: "▶".to_string(),
+			style: Style::new().attrs(Attr::DIM),
+			fade: false,
+		}
+	}
+	/// Returns the hints with the one shown when there's more above changed. Empty shows none.
+	pub fn up(mut self, text: &str) -> OverflowHints {
+		self.up = text.to_string();
+		self
+	}
+	/// Returns the hints with the one shown when there's more below changed. Empty shows none.
+	pub fn down(mut self, text: &str) -> OverflowHints {
+		self.down = text.to_string();
+		self
+	}
+	/// Returns the hints with the one shown when there's more to the left changed. Empty shows none.
+	pub fn left(mut self, text: &str) -> OverflowHints {
+		self.left = text.to_string();
+		self
+	}
+	/// Returns the hints with the one shown when there's more to the right changed. Empty shows none.
+	pub fn right(mut self, text: &str) -> OverflowHints {
+		self.right = text.to_string();
+		self
+	}
+	/// Returns the hints with their style changed.
+	pub fn style(mut self, style: Style) -> OverflowHints {
+		self.style = style;
+		self
+	}
+	/// Returns the hints with fading the edges turned on or off.
+	pub fn fade(mut self, fade: bool) -> OverflowHints {
+		self.fade = fade;
+		self
+	}
+	/// Takes the style from the `overflow` part of `selector` in `theme`, e.g. `Table.overflow`. It's
+	/// left alone if the theme has nothing for it.
+	pub fn apply_theme(&mut self, theme: &Theme, selector: &Selector) {
+		let style = theme.resolve(&selector.clone().part("overflow"));
+		if style != Style::default() {
+			self.style = style;
+		}
+	}
+	/// Draws the hints for the sides in `more` along the edges of `area` (in `win`'s coordinates).
+	pub fn draw(&self, win: &mut Window, area: Rect, more: Overflowing) {
+		let area = match area.intersect(&Rect::new(0, 0, win.get_height(), win.get_width())) {
+			Some(area) => area,
+			None => return,
+		};
+		if self.fade {
+			let edges = [
+				(more.up, Rect::new(area.y, area.x, 1, area.width)),
+				(more.down, Rect::new(area.bottom() - 1, area.x, 1, area.width)),
+				(more.left, Rect::new(area.y, area.x, area.height, 1)),
+				(more.right, Rect::new(area.y, area.right() - 1, area.height, 1)),
+			];
+			for &(_, rect) in edges.iter().filter(|e| e.0) {
+				win.shade(rect);
+			}
+		}
+		let style = win.get_style();
+		win.set_style(self.style);
+		let middle = area.y + area.height / 2;
+		if more.up {
+			self.put(win, area.y, area.right(), &self.up, area.width);
+		}
+		if more.down {
+			self.put(win, area.bottom() - 1, area.right(), &self.down, area.width);
+		}
+		if more.left {
+			win.print(middle, area.x, &text::truncate(&self.left, area.width));
+		}
+		if more.right {
+			self.put(win, middle, area.right(), &self.right, area.width);
+		}
+		win.set_style(style);
+	}
+	/// Internal: Prints `hint` ending just before column `right` on row `y`, cut down to `width`
+	fn put(&self, win: &mut Window, y: u16, right: u16, hint: &str, width: u16) {
+		let hint = text::truncate(hint, width);
+		win.print(y, right - str_width(&hint) as u16, &hint);
+	}
+}
+
+impl Default for OverflowHints {
+	fn default() -> OverflowHints {
+		OverflowHints::new()
+	}
+}
@@ -0,0 +1,291 @@
+//! A table of text, with a header and a selected row.
+
+use ::acs::Glyph;
+use ::event::{Event, Key, MouseButton, MouseKind};
+use ::layout::{Constraint, Layout};
+use ::rect::Rect;
+use ::style::{Attr, Style};
+use ::text::{self, Align};
+use ::theme::{Selector, Theme};
+use ::window::Window;
+use super::overflow::{OverflowHints, Overflowing};
+
+// How many rows a turn of the mouse wheel scrolls by
+const SCROLL_ROWS: usize = 3;
+
+/// A column of a `Table`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+	pub title: String,
+	/// How much of the table's width it gets, worked out the same way as a `Layout`'s panes
+	pub width: Constraint,
+	/// The narrowest it can be, whatever the constraint works out to
+	pub min: u16,
+	/// The widest it can be, whatever the constraint works out to
+	pub max: u16,
+	/// Where text goes inside of it, for the header and every row
+	pub align: Align,
+}
+
+impl Column {
+	pub fn new(title: &str, width: Constraint) -> Column {
+		Column {
+			title: title.to_string(),
+			width,
+			min: 0,
+			max: u16::MAX,
+			align: Align::Left,
+		}
+	}
+	/// Returns the column with its narrowest width changed.
+	pub fn min(mut self, min: u16) -> Column {
+		self.min = min;
+		self
+	}
+	/// Returns the column with its widest width changed.
+	pub fn max(mut self, max: u16) -> Column {
+		self.max = max;
+		self
+	}
+	/// Returns the column with its text lined up differently.
+	pub fn align(mut self, align: Align) -> Column {
+		self.align = align;
+		self
+	}
+}
+
+/// Rows of text split into columns, drawn with a header above them and lines between the columns.
+///
+/// One row can be selected, with the arrow keys, Page Up/Down, Home and End, or by clicking on it or
+/// dragging over it, and the rows scroll to keep it in view. Text that doesn't fit its column is cut
+/// off with a `…`.
+#[derive(Debug, Clone)]
+pub struct Table {
+	columns: Vec<Column>,
+	rows: Vec<Vec<String>>,
+	selected: Option<usize>,
+	/// The row that was double clicked, until it's taken
+	activated: Option<usize>,
+	/// The first row that's shown
+	offset: usize,
+	/// How many rows fit, as of the last draw
+	visible: usize,
+	header_style: Style,
+	selected_style: Style,
+	overflow: Option<OverflowHints>,
+}
+
+impl Table {
+	pub fn new(columns: Vec<Column>) -> Table {
+		Table {
+			columns,
+			rows: Vec::new(),
+			selected: None,
+			activated: None,
+			offset: 0,
+			visible: 1,
+			header_style: Style::new().attrs(Attr::BOLD),
+			selected_style: Style::new().attrs(Attr::REVERSE),
+			overflow: None,
+		}
+	}
+	/// Replaces every row. Cells past the last column are ignored, missing ones are left blank.
+	pub fn set_rows(&mut self, rows: Vec<Vec<String>>) {
+		self.rows = rows;
+		self.select(self.selected);
+	}
+	/// Adds a row at the bottom.
+	pub fn push_row(&mut self, row: Vec<String>) {
+		self.rows.push(row);
+	}
+	pub fn rows(&self) -> &[Vec<String>] {
+		&self.rows
+	}
+	pub fn columns(&self) -> &[Column] {
+		&self.columns
+	}
+	/// The selected row, if any.
+	pub fn selected(&self) -> Option<usize> {
+		self.selected
+	}
+	/// The row that was double clicked since the last call, if any. Double clicks are only reported
+	/// while gestures are on, see `Screen::set_gestures`.
+	pub fn take_activated(&mut self) -> Option<usize> {
+		self.activated.take()
+	}
+	/// Selects a row, or none. Rows past the end select the last row.
+	pub fn select(&mut self, row: Option<usize>) {
+		self.selected = match row {
+			Some(_) if self.rows.is_empty() => None,
+			Some(row) => Some(row.min(self.rows.len() - 1)),
+			None => None,
+		};
+		self.scroll_to_selected();
+	}
+	/// Sets the style of the header, bold by default.
+	pub fn set_header_style(&mut self, style: Style) {
+		self.header_style = style;
+	}
+	/// Sets the style of the selected row, reversed by default.
+	pub fn set_selected_style(&mut self, style: Style) {
+		self.selected_style = style;
+	}
+	/// Takes the header and selected row styles from the `header` and `selection` parts of `selector`
+	/// in `theme`, e.g. `Table:focused.selection` for `Selector::new("Table").state("focused")`.
+	/// Styles the theme has nothing for are left alone.
+	pub fn apply_theme(&mut self, theme: &Theme, selector: &Selector) {
+		let header = theme.resolve(&selector.clone().part("header"));
+		if header != Style::default() {
+			self.header_style = header;
+		}
+		let selected = theme.resolve(&selector.clone().part("selection"));
+		if selected != Style::default() {
+			self.selected_style = selected;
+		}
+		if let Some(ref mut hints) = self.overflow {
+			hints.apply_theme(theme, selector);
+		}
+	}
+	/// Sets the hints drawn above and below the rows when there are more past them, or None for none
+	/// (the default).
+	pub fn set_overflow_hints(&mut self, hints: Option<OverflowHints>) {
+		self.overflow = hints;
+	}
+	/// Which sides there are more rows past, as of the last draw.
+	pub fn overflowing(&self) -> Overflowing {
+		Overflowing {
+			up: self.offset > 0,
+			down: self.offset < self.max_offset(),
+			..Overflowing::default()
+		}
+	}
+	/// How wide each column is when the table is `width` cells wide, leaving a cell between columns
+	/// for the line that separates them.
+	pub fn widths(&self, width: u16) -> Vec<u16> {
+		let separators = self.columns.len().saturating_sub(1) as u16;
+		let layout = self.columns.iter().fold(Layout::horizontal(), |layout, c| layout.pane(c.width));
+		let area = Rect::new(0, 0, 1, width.saturating_sub(separators));
+		layout.split_area(area).iter().zip(&self.columns).map(|(r, c)| r.width.clamp(c.min, c.max.max(c.min))).collect()
+	}
+	/// Moves the selection or scrolls for keys and mouse events on `win` (the one the table is drawn in).
+	/// Returns whether anything changed, including a row being double clicked (see `take_activated`)
+	pub fn handle(&mut self, event: &Event, win: &Window) -> bool {
+		let before = (self.selected, self.offset);
+		let last = self.rows.len().saturating_sub(1);
+		let current = self.selected.unwrap_or(0);
+		match *event {
+			Event::Key(Key::Up) => self.select(Some(current.saturating_sub(1))),
+			Event::Key(Key::Down) => self.select(Some(if self.selected.is_some() { current + 1 } else { 0 })),
+			Event::Key(Key::PageUp) => self.select(Some(current.saturating_sub(self.visible))),
+			Event::Key(Key::PageDown) => self.select(Some(current + self.visible)),
+			Event::Key(Key::Home) => self.select(Some(0)),
+			Event::Key(Key::End) => self.select(Some(last)),
+			Event::Mouse(mouse) if win.rect().contains(mouse.y, mouse.x) => {
+				// The header and the line under it come first
+				let top = win.get_y() + 2;
+				match mouse.kind {
+					MouseKind::Press(MouseButton::Left) | MouseKind::Drag(MouseButton::Left) | MouseKind::DragMove(MouseButton::Left) if mouse.y >= top => {
+						let row = self.offset + (mouse.y - top) as usize;
+						if row < self.rows.len() {
+							self.select(Some(row));
+						}
+					},
+					MouseKind::DoubleClick(MouseButton::Left) if mouse.y >= top => {
+						let row = self.offset + (mouse.y - top) as usize;
+						if row < self.rows.len() {
+							self.activated = Some(row);
+							return true;
+						}
+					},
+					MouseKind::ScrollUp => self.scroll_by(-1),
+					MouseKind::ScrollDown => self.scroll_by(1),
+					MouseKind::Scroll(notches) => self.scroll_by(notches as isize),
+					_ => {},
+				}
+			},
+			_ => {},
+		}
+		(self.selected, self.offset) != before
+	}
+	/// Draws the table over the whole of `win`.
+	pub fn draw(&mut self, win: &mut Window) {
+		let (height, width) = (win.get_height(), win.get_width());
+		self.visible = (height as usize).saturating_sub(2).max(1);
+		self.offset = self.offset.min(self.max_offset());
+		let widths = self.widths(width);
+		let style = win.get_style();
+		win.clear();
+
+		win.set_style(self.header_style);
+		let titles: Vec<&str> = self.columns.iter().map(|c| c.title.as_str()).collect();
+		self.draw_row(win, 0, &titles, &widths);
+		win.set_style(style);
+		win.hline(1, 0, width);
+		for x in separators(&widths) {
+			win.put_glyph(1, x, Glyph::Cross);
+		}
+
+		for (i, row) in self.rows.iter().enumerate().skip(self.offset).take(self.visible) {
+			let y = (2 + i - self.offset) as u16;
+			if y >= height {
+				break;
+			}
+			if self.selected == Some(i) {
+				win.set_style(self.selected_style);
+				win.print(y, 0, &" ".repeat(width as usize));
+			}
+			let cells: Vec<&str> = row.iter().map(String::as_str).collect();
+			self.draw_row(win, y, &cells, &widths);
+			win.set_style(style);
+		}
+		if let Some(ref hints) = self.overflow {
+			let rows = Rect::new(2, 0, self.visible.min(u16::MAX as usize) as u16, width);
+			hints.draw(win, rows, self.overflowing());
+		}
+	}
+	/// Internal: Draws a line of cells, with the lines between the columns
+	fn draw_row(&self, win: &mut Window, y: u16, cells: &[&str], widths: &[u16]) {
+		let mut x = 0;
+		for (i, (column, &w)) in self.columns.iter().zip(widths).enumerate() {
+			if i > 0 {
+				win.put_glyph(y, x, Glyph::VLine);
+				x += 1;
+			}
+			let cell = text::truncate(cells.get(i).cloned().unwrap_or(""), w);
+			let offset = text::align_offset(text::str_width(&cell), w, column.align);
+			win.print(y, x + offset, &cell);
+			x += w;
+		}
+	}
+	/// Internal: Scrolls so that the selected row is in view
+	fn scroll_to_selected(&mut self) {
+		if let Some(selected) = self.selected {
+			if selected < self.offset {
+				self.offset = selected;
+			} else if selected >= self.offset + self.visible {
+				self.offset = selected + 1 - self.visible;
+			}
+		}
+	}
+	/// Internal: Scrolls by `notches` turns of the mouse wheel, down if positive
+	fn scroll_by(&mut self, notches: isize) {
+		let rows = notches.unsigned_abs() * SCROLL_ROWS;
+		self.offset = if notches < 0 { self.offset.saturating_sub(rows) } else { (self.offset + rows).min(self.max_offset()) };
+	}
+	/// Internal: How far down the rows can be scrolled, with the last one at the bottom
+	fn max_offset(&self) -> usize {
+		self.rows.len().saturating_sub(self.visible)
+	}
+}
+
+/// Internal: The columns the lines between columns of these widths go in
+fn separators(widths: &[u16]) -> Vec<u16> {
+	let mut x = 0;
+	let mut out = Vec::new();
+	for &w in widths.iter().take(widths.len().saturating_sub(1)) {
+		x += w;
+		out.push(x);
+		x += 1;
+	}
+	out
+}
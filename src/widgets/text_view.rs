@@ -0,0 +1,390 @@
+//! Scrollable lines of text with search, e.g. for a log viewer or a pager.
+
+use std::collections::VecDeque;
+use ::event::{Event, Key, MouseKind};
+use ::rect::Rect;
+use ::style::{Attr, Style};
+use ::text::{char_width, str_width};
+use ::theme::{Selector, Theme};
+use ::window::Window;
+use super::annotate::{self, TextAnnotator};
+use super::overflow::{OverflowHints, Overflowing};
+
+// How many rows a turn of the mouse wheel scrolls by
+const SCROLL_ROWS: usize = 3;
+// Tabs are expanded to stops this far apart
+const TAB_WIDTH: usize = 8;
+
+/// Lines of text in a window that can be scrolled in both directions and searched.
+///
+/// It holds up to a set number of lines, dropping the oldest as new ones are pushed, so it can be fed
+/// a log forever. In follow mode it stays scrolled to the bottom as lines come in, like `tail -f`;
+/// scrolling up leaves follow mode and End goes back to it.
+#[derive(Debug, Clone)]
+pub struct TextView {
+	lines: VecDeque<String>,
+	/// How many lines are kept, 0 for no limit
+	max_lines: usize,
+	/// The first line shown
+	top: usize,
+	/// How many cells of every line are scrolled off to the left
+	left: usize,
+	follow: bool,
+	/// The window's (height, width) as of the last draw
+	size: (usize, usize),
+	search: Option<Search>,
+	match_style: Style,
+	current_style: Style,
+	overflow: Option<OverflowHints>,
+}
+
+/// Internal: What's being searched for, and where it was found
+#[derive(Debug, Clone)]
+struct Search {
+	pattern: String,
+	/// Whether it's matched regardless of case, which is when it's all lowercase
+	ignore_case: bool,
+	/// Every match as (line, start byte, end byte), in order
+	matches: Vec<(usize, usize, usize)>,
+	/// Which match was last jumped to
+	current: Option<usize>,
+}
+
+impl TextView {
+	/// A view that keeps at most `max_lines` lines, or all of them if it's 0.
+	pub fn new(max_lines: usize) -> TextView {
+		TextView {
+			lines: VecDeque::new(),
+			max_lines,
+			top: 0,
+			left: 0,
+			follow: false,
+			size: (1, 1),
+			search: None,
+			match_style: Style::new().attrs(Attr::REVERSE),
+			current_style: Style::new().attrs(Attr::REVERSE | Attr::BOLD),
+			overflow: None,
+		}
+	}
+	/// Adds text at the bottom, a line for each line in it. Tabs are expanded to spaces.
+	pub fn push(&mut self, text: &str) {
+		for line in text.split('\n') {
+			self.push_line(expand_tabs(line.trim_end_matches('\r')));
+		}
+	}
+	/// Removes every line.
+	pub fn clear(&mut self) {
+		self.lines.clear();
+		self.top = 0;
+		self.left = 0;
+		if let Some(ref mut search) = self.search {
+			search.matches.clear();
+			search.current = None;
+		}
+	}
+	pub fn len(&self) -> usize {
+		self.lines.len()
+	}
+	pub fn is_empty(&self) -> bool {
+		self.lines.is_empty()
+	}
+	/// A line of the text, counting from the oldest that's still kept.
+	pub fn line(&self, index: usize) -> Option<&str> {
+		self.lines.get(index).map(String::as_str)
+	}
+	/// The first line that's shown.
+	pub fn top(&self) -> usize {
+		self.top
+	}
+	/// Turns follow mode on or off. Turning it on scrolls to the bottom.
+	pub fn set_follow(&mut self, follow: bool) {
+		self.follow = follow;
+		if follow {
+			self.top = self.max_top();
+		}
+	}
+	/// Whether it stays scrolled to the bottom as lines are pushed.
+	pub fn is_following(&self) -> bool {
+		self.follow
+	}
+	/// Scrolls so that `line` is the first one shown, as far as there are lines below it.
+	pub fn scroll_to(&mut self, line: usize) {
+		self.top = line.min(self.max_top());
+		self.follow = self.follow && self.top == self.max_top();
+	}
+	/// Scrolls down by `rows` (up, if negative).
+	pub fn scroll_by(&mut self, rows: isize) {
+		let top = self.top as isize + rows;
+		self.scroll_to(top.max(0) as usize);
+	}
+	/// Scrolls right by `cells` (left, if negative).
+	pub fn scroll_horizontally(&mut self, cells: isize) {
+		self.left = (self.left as isize + cells).clamp(0, self.max_left() as isize) as usize;
+	}
+	/// Sets the style of search matches, and of the one that was last jumped to.
+	pub fn set_match_styles(&mut self, matches: Style, current: Style) {
+		self.match_style = matches;
+		self.current_style = current;
+	}
+	/// Takes the match styles from the `match` and `current-match` parts of `selector` in `theme`, e.g.
+	/// `TextView.match` for `Selector::new("TextView")`. Styles the theme has nothing for are left alone.
+	pub fn apply_theme(&mut self, theme: &Theme, selector: &Selector) {
+		let matches = theme.resolve(&selector.clone().part("match"));
+		if matches != Style::default() {
+			self.match_style = matches;
+		}
+		let current = theme.resolve(&selector.clone().part("current-match"));
+		if current != Style::default() {
+			self.current_style = current;
+		}
+		if let Some(ref mut hints) = self.overflow {
+			hints.apply_theme(theme, selector);
+		}
+	}
+	/// Sets the hints drawn at the edges when there's more text past them, or None for none (the default).
+	pub fn set_overflow_hints(&mut self, hints: Option<OverflowHints>) {
+		self.overflow = hints;
+	}
+	/// Which sides there's more text past, as of the last draw.
+	pub fn overflowing(&self) -> Overflowing {
+		Overflowing {
+			up: self.top > 0,
+			down: self.top < self.max_top(),
+			left: self.left > 0,
+			right: self.left < self.max_left(),
+		}
+	}
+	/// Searches for `pattern`, and jumps to the first match at or below the top of the view. Meant to be
+	/// called again as the pattern is typed. It's matched regardless of case if it's all lowercase.
+	/// Returns how many matches there are
+	pub fn search(&mut self, pattern: &str) -> usize {
+		if pattern.is_empty() {
+			self.search = None;
+			return 0;
+		}
+		let ignore_case = !pattern.chars().any(char::is_uppercase);
+		let mut matches = Vec::new();
+		for (i, line) in self.lines.iter().enumerate() {
+			matches.extend(find_all(line, pattern, ignore_case).into_iter().map(|(start, end)| (i, start, end)));
+		}
+		let count = matches.len();
+		let top = self.top;
+		self.search = Some(Search {
+			pattern: pattern.to_string(),
+			ignore_case,
+			matches,
+			current: None,
+		});
+		if let Some(first) = self.search.as_ref().and_then(|s| s.matches.iter().position(|&(line, _, _)| line >= top)) {
+			self.jump_to(first);
+		}
+		count
+	}
+	/// Stops searching, and highlighting the matches.
+	pub fn clear_search(&mut self) {
+		self.search = None;
+	}
+	/// Jumps to the match after the last one jumped to, wrapping around to the first.
+	/// Returns the line it's on, or None if there are no matches
+	pub fn next_match(&mut self) -> Option<usize> {
+		let search = self.search.as_ref()?;
+		let count = search.matches.len();
+		if count == 0 {
+			return None;
+		}
+		let next = search.current.map_or(0, |c| (c + 1) % count);
+		Some(self.jump_to(next))
+	}
+	/// Jumps to the match before the last one jumped to, wrapping around to the last.
+	/// Returns the line it's on, or None if there are no matches
+	pub fn prev_match(&mut self) -> Option<usize> {
+		let search = self.search.as_ref()?;
+		let count = search.matches.len();
+		if count == 0 {
+			return None;
+		}
+		let prev = search.current.map_or(count - 1, |c| (c + count - 1) % count);
+		Some(self.jump_to(prev))
+	}
+	/// Scrolls for keys and mouse wheel events on `win` (the one the view is drawn in).
+	/// Returns whether it scrolled
+	pub fn handle(&mut self, event: &Event, win: &Window) -> bool {
+		let before = (self.top, self.left, self.follow);
+		let page = self.size.0.max(1) as isize;
+		match *event {
+			Event::Key(Key::Up) => self.scroll_by(-1),
+			Event::Key(Key::Down) => self.scroll_by(1),
+			Event::Key(Key::PageUp) => self.scroll_by(-page),
+			Event::Key(Key::PageDown) => self.scroll_by(page),
+			Event::Key(Key::Left) => self.scroll_horizontally(-1),
+			Event::Key(Key::Right) => self.scroll_horizontally(1),
+			Event::Key(Key::Home) => self.scroll_to(0),
+			Event::Key(Key::End) => self.set_follow(true),
+			Event::Mouse(mouse) if win.rect().contains(mouse.y, mouse.x) => match mouse.kind {
+				MouseKind::ScrollUp => self.scroll_by(-(SCROLL_ROWS as isize)),
+				MouseKind::ScrollDown => self.scroll_by(SCROLL_ROWS as isize),
+				MouseKind::Scroll(notches) => self.scroll_by(notches as isize * SCROLL_ROWS as isize),
+				_ => {},
+			},
+			_ => {},
+		}
+		(self.top, self.left, self.follow) != before
+	}
+	/// Draws the lines that are scrolled into view over the whole of `win`, with search matches highlighted.
+	pub fn draw(&mut self, win: &mut Window) {
+		self.draw_lines(win, None);
+	}
+	/// Like `draw`, but with the lines decorated by `annotator`. Search matches are drawn over annotations.
+	pub fn draw_annotated(&mut self, win: &mut Window, annotator: &mut dyn TextAnnotator) {
+		self.draw_lines(win, Some(annotator));
+	}
+	/// Internal: Draws the lines in view, asking `annotator` about each of them
+	fn draw_lines(&mut self, win: &mut Window, mut annotator: Option<&mut dyn TextAnnotator>) {
+		self.size = (win.get_height() as usize, win.get_width() as usize);
+		self.top = if self.follow { self.max_top() } else { self.top.min(self.max_top()) };
+		let style = win.get_style();
+		win.clear();
+		for row in 0..self.size.0 {
+			let index = self.top + row;
+			let line = match self.lines.get(index) {
+				Some(line) => line,
+				None => break,
+			};
+			let annotations = annotator.as_mut().map_or_else(Vec::new, |a| a.annotate(index, line));
+			let mut col = 0;
+			for (byte, ch) in line.char_indices() {
+				let w = char_width(ch) as usize;
+				// Characters that are partly scrolled off to the left get left out
+				if col < self.left {
+					col += w;
+					continue;
+				}
+				let x = col - self.left;
+				if x + w > self.size.1 {
+					break;
+				}
+				let annotated = annotate::style_at(&annotations, byte, style);
+				win.set_style(self.style_at(index, byte).unwrap_or(annotated));
+				win.put_char(row as u16, x as u16, ch);
+				col += w;
+			}
+			win.set_style(style);
+		}
+		if let Some(ref hints) = self.overflow {
+			let area = Rect::new(0, 0, win.get_height(), win.get_width());
+			hints.draw(win, area, self.overflowing());
+		}
+	}
+	/// Internal: Adds a line at the bottom, dropping the oldest one if there are too many
+	fn push_line(&mut self, line: String) {
+		if let Some(ref mut search) = self.search {
+			let index = self.lines.len();
+			let found = find_all(&line, &search.pattern, search.ignore_case);
+			search.matches.extend(found.into_iter().map(|(start, end)| (index, start, end)));
+		}
+		self.lines.push_back(line);
+		if self.max_lines > 0 && self.lines.len() > self.max_lines {
+			self.lines.pop_front();
+			self.top = self.top.saturating_sub(1);
+			if let Some(ref mut search) = self.search {
+				// Everything moved up a line, and matches on the one that was dropped are gone
+				let gone = search.matches.iter().take_while(|&&(line, _, _)| line == 0).count();
+				search.matches.drain(..gone);
+				for m in &mut search.matches {
+					m.0 -= 1;
+				}
+				search.current = search.current.and_then(|c| c.checked_sub(gone));
+			}
+		}
+		if self.follow {
+			self.top = self.max_top();
+		}
+	}
+	/// Internal: Makes a match the current one and scrolls it into view.
+	/// Returns the line it's on
+	fn jump_to(&mut self, index: usize) -> usize {
+		let (line, start, end) = {
+			let search = self.search.as_mut().unwrap();
+			search.current = Some(index);
+			search.matches[index]
+		};
+		let (height, width) = self.size;
+		if line < self.top || line >= self.top + height {
+			// Put it in the middle, so there's some context around it
+			self.scroll_to(line.saturating_sub(height / 2));
+		}
+		let text = &self.lines[line];
+		let (from, to) = (str_width(&text[..start]), str_width(&text[..end]));
+		if from < self.left {
+			self.left = from;
+		} else if to > self.left + width {
+			self.left = to.saturating_sub(width);
+		}
+		line
+	}
+	/// Internal: The style the character at `byte` of `line` gets from the search, if it's part of a match
+	fn style_at(&self, line: usize, byte: usize) -> Option<Style> {
+		let search = self.search.as_ref()?;
+		// Matches are in order, so look at the ones on this line only
+		let first = search.matches.partition_point(|&(l, _, _)| l < line);
+		search.matches[first..].iter()
+			.enumerate()
+			.take_while(|&(_, &(l, _, _))| l == line)
+			.find(|&(_, &(_, start, end))| start <= byte && byte < end)
+			.map(|(i, _)| if search.current == Some(first + i) { self.current_style } else { self.match_style })
+	}
+	/// Internal: How far down it can scroll, with the last line at the bottom
+	fn max_top(&self) -> usize {
+		self.lines.len().saturating_sub(self.size.0)
+	}
+	/// Internal: How far right it can scroll, with the end of the longest line at the right edge
+	fn max_left(&self) -> usize {
+		self.lines.iter().map(|l| str_width(l)).max().unwrap_or(0).saturating_sub(self.size.1)
+	}
+}
+
+/// Internal: Replaces tabs with spaces up to the next tab stop
+fn expand_tabs(line: &str) -> String {
+	if !line.contains('\t') {
+		return line.to_string();
+	}
+	let mut out = String::new();
+	let mut col = 0;
+	for ch in line.chars() {
+		if ch == '\t' {
+			let spaces = TAB_WIDTH - col % TAB_WIDTH;
+			out.extend(::std::iter::repeat_n(' ', spaces));
+			col += spaces;
+		} else {
+			out.push(ch);
+			col += char_width(ch) as usize;
+		}
+	}
+	out
+}
+
+/// Internal: Where `pattern` is in `line`, as (start byte, end byte), without overlaps
+fn find_all(line: &str, pattern: &str, ignore_case: bool) -> Vec<(usize, usize)> {
+	let same = |a: char, b: char| a == b || (ignore_case && a.to_lowercase().eq(b.to_lowercase()));
+	let mut found = Vec::new();
+	let mut from = 0;
+	for (start, _) in line.char_indices() {
+		if start < from {
+			continue;
+		}
+		let mut rest = line[start..].char_indices();
+		let mut end = start;
+		let matched = pattern.chars().all(|p| match rest.next() {
+			Some((i, ch)) if same(ch, p) => {
+				end = start + i + ch.len_utf8();
+				true
+			},
+			_ => false,
+		});
+		if matched {
+			found.push((start, end));
+			from = end;
+		}
+	}
+	found
+}
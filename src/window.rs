@@ -0,0 +1,637 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use ::acs::Glyph;
+use ::buffer::{Buffer, Cell, CONTINUATION};
+use ::rect::Rect;
+use ::style::{Attr, Color, Style};
+use ::text::{self, Align, VAlign, WrapMode};
+use ::theme::cascade;
+
+// How far apart tab stops are, unless changed with `set_tab_width`
+const DEFAULT_TAB_WIDTH: u16 = 8;
+
+/// A rectangular area of the screen that can be drawn into.
+///
+/// Drawing only changes the window's own cells, nothing is shown until it's
+/// handed to `Screen::draw` and the screen is refreshed.
+#[derive(Debug, Clone)]
+pub struct Window {
+	y: u16,
+	x: u16,
+	cur_y: u16,
+	cur_x: u16,
+	buf: Buffer,
+	/// The style newly printed text gets
+	style: Style,
+	/// What blanked cells get, and what printed text falls back on, see `set_default_style`
+	default_style: Style,
+	/// Clickable regions, relative to the window
+	regions: Vec<(Rect, usize)>,
+	/// Whether `put_str` scrolls the window when it runs out of lines, see `set_scrollok`
+	scrollok: bool,
+	/// How far apart tab stops are past the ones in `tab_stops`
+	tab_width: u16,
+	/// Columns with a tab stop set with `set_tab_stops`, in order
+	tab_stops: Vec<u16>,
+}
+
+impl Window {
+	/// Creates a blank window with its top-left corner at (y, x) on the screen.
+	pub fn new(y: u16, x: u16, height: u16, width: u16) -> Window {
+		Window {
+			y,
+			x,
+			cur_y: 0,
+			cur_x: 0,
+			buf: Buffer::new(height, width),
+			style: Style::default(),
+			default_style: Style::default(),
+			regions: Vec::new(),
+			scrollok: false,
+			tab_width: DEFAULT_TAB_WIDTH,
+			tab_stops: Vec::new(),
+		}
+	}
+	/// Get the line of the screen the window starts at
+	pub fn get_y(&self) -> u16 {
+		self.y
+	}
+	/// Get the column of the screen the window starts at
+	pub fn get_x(&self) -> u16 {
+		self.x
+	}
+	/// Get the height (amount of lines) of the window
+	pub fn get_height(&self) -> u16 {
+		self.buf.get_height()
+	}
+	/// Get the width (amount of columns) of the window
+	pub fn get_width(&self) -> u16 {
+		self.buf.get_width()
+	}
+	/// The cells of the window.
+	pub fn buffer(&self) -> &Buffer {
+		&self.buf
+	}
+	/// What's in a cell of the window: its character and style, like ncurses' `inch`.
+	/// If the position is outside of the window, returns None
+	pub fn cell_at(&self, y: u16, x: u16) -> Option<&Cell> {
+		self.buf.get(y, x)
+	}
+	/// The characters in `width` cells of row `y`, starting at column `x` (or up to the end of the row),
+	/// like ncurses' `innstr`. The second halves of wide characters take up a cell but aren't in it.
+	/// If the position is outside of the window, returns None
+	pub fn text_at(&self, y: u16, x: u16, width: u16) -> Option<String> {
+		self.buf.get(y, x)?;
+		let end = x.saturating_add(width).min(self.get_width());
+		Some((x..end).filter_map(|x| self.buf.get(y, x)).map(|c| c.ch).filter(|&ch| ch != CONTINUATION).collect())
+	}
+	/// Everything in the window as plain text, a line for each row (see `Buffer::to_text`).
+	pub fn contents(&self) -> String {
+		self.buf.to_text()
+	}
+	/// Internal: The cells of the window, for widgets that fill lots of them at once
+	pub(crate) fn buffer_mut(&mut self) -> &mut Buffer {
+		&mut self.buf
+	}
+	/// Sets the style that text printed from now on gets.
+	pub fn set_style(&mut self, style: Style) {
+		self.style = style;
+	}
+	/// The style that printed text gets.
+	pub fn get_style(&self) -> Style {
+		self.style
+	}
+	/// Sets the window's background, like ncurses' `bkgd`: cells that get blanked take on `style`, and
+	/// text is printed in it wherever its own style (see `set_style`) leaves the colors as the default.
+	/// Attributes are added to the text's own.
+	///
+	/// Whatever's already in the window changes over too, so e.g. a status bar can be given a dark
+	/// background after it's been drawn. Colors that were set on purpose stay as they are.
+	///
+	/// NOTE: Cells don't remember which parts of their style came from the old default, so it's guessed:
+	/// a color (or link) that's the same as the old default's is taken to be from it and gets replaced,
+	/// even if the text asked for that color itself. The old default's attributes are taken off of every
+	/// cell the same way, so e.g. bold text loses its bold when going from a bold background to a plain one.
+	pub fn set_default_style(&mut self, style: Style) {
+		let old = self.default_style;
+		let area = Rect::new(0, 0, self.get_height(), self.get_width());
+		self.buf.restyle(area, |s| {
+			// What the cell has of its own, without the old default
+			let mut own = *s;
+			if own.fg == old.fg {
+				own.fg = Color::Default;
+			}
+			if own.bg == old.bg {
+				own.bg = Color::Default;
+			}
+			if own.underline == old.underline {
+				own.underline = Color::Default;
+			}
+			if own.link == old.link {
+				own.link = None;
+			}
+			own.attrs.remove(old.attrs);
+			*s = cascade(style, own);
+		});
+		self.default_style = style;
+	}
+	/// The window's background, see `set_default_style`.
+	pub fn default_style(&self) -> Style {
+		self.default_style
+	}
+	/// Whether `put_str` scrolls the window up when text runs past the bottom line, like ncurses'
+	/// `scrollok`. Off by default.
+	pub fn set_scrollok(&mut self, scrollok: bool) {
+		self.scrollok = scrollok;
+	}
+	pub fn scrollok(&self) -> bool {
+		self.scrollok
+	}
+	/// Puts a tab stop every `width` columns for `put_str`, past any set with `set_tab_stops`. The default is 8.
+	pub fn set_tab_width(&mut self, width: u16) {
+		self.tab_width = width.max(1);
+	}
+	/// Puts tab stops at these columns for `put_str`, e.g. to line up a table. Past the last one, they're
+	/// every `set_tab_width` columns again.
+	pub fn set_tab_stops(&mut self, stops: &[u16]) {
+		self.tab_stops = stops.to_vec();
+		self.tab_stops.sort_unstable();
+		self.tab_stops.dedup();
+	}
+	/// Where the window's cursor is, as (y, x).
+	pub fn cursor(&self) -> (u16, u16) {
+		(self.cur_y, self.cur_x)
+	}
+	/// Moves the window's cursor, which is where the next character will be put.
+	/// If the position is outside of the window, returns None
+	pub fn move_cursor(&mut self, y: u16, x: u16) -> Option<()> {
+		self.buf.get(y, x)?;
+		self.cur_y = y;
+		self.cur_x = x;
+		Some(())
+	}
+	/// Puts a single character at (y, x). Wide characters take up two cells.
+	/// If the character doesn't fit in the window at that position, returns None
+	///
+	/// NOTE: Zero width (combining) characters are skipped, since they can't have a cell of their own.
+	pub fn put_char(&mut self, y: u16, x: u16, ch: char) -> Option<()> {
+		let width = text::char_width(ch);
+		if width == 0 {
+			return if y < self.get_height() && x < self.get_width() { Some(()) } else { None };
+		}
+		let style = cascade(self.default_style, self.style);
+		self.set_cell(y, x, Cell { ch, style })?;
+		self.cur_y = y;
+		self.cur_x = x + width;
+		Some(())
+	}
+	/// Prints text in `style` starting at (y, x), e.g. `win.print_styled(0, 0, "Failed", theme.style("error"))`.
+	/// The window's style is left alone. See `print` for the rest.
+	pub fn print_styled(&mut self, y: u16, x: u16, text: &str, style: Style) -> Option<()> {
+		let saved = self.style;
+		self.style = style;
+		let out = self.print(y, x, text);
+		self.style = saved;
+		out
+	}
+	/// Prints text starting at (y, x). Text past the right edge of the window is cut off.
+	/// If the starting position is outside of the window, returns None
+	pub fn print(&mut self, y: u16, x: u16, text: &str) -> Option<()> {
+		self.move_cursor(y, x)?;
+		for ch in text.chars() {
+			let col = self.cur_x;
+			if self.put_char(y, col, ch).is_none() {
+				break;
+			}
+		}
+		Some(())
+	}
+	/// Prints formatted text starting at (y, x), like `print` but without making a String first. See also
+	/// `mvprintw!`, which does the `format_args!` part.
+	/// If the starting position is outside of the window, returns None
+	///
+	/// NOTE: Newlines go to the start of the next line, like they do with ncurses' `mvwprintw`.
+	pub fn printf(&mut self, y: u16, x: u16, args: fmt::Arguments) -> Option<()> {
+		self.move_cursor(y, x)?;
+		self.print_fmt(args);
+		Some(())
+	}
+	/// Prints formatted text at the cursor, see `printf` and `wprintw!`.
+	pub fn print_fmt(&mut self, args: fmt::Arguments) {
+		// Only fails once there's no room left, and then there's nothing left to do anyway
+		fmt::Write::write_fmt(self, args).unwrap_or(());
+	}
+	/// Prints text starting on the cursor's line, wrapped to the width of the window.
+	/// Returns how many lines the text took, including any that didn't fit below the window
+	pub fn print_wrapped(&mut self, text: &str, mode: WrapMode) -> usize {
+		let lines = text::wrap(text, self.get_width(), mode);
+		let top = self.cur_y;
+		self.print_lines(top, &lines, Align::Left)
+	}
+	/// Prints text wrapped to the width of the window, lined up inside of the whole window.
+	/// Returns how many lines the text took, including any that didn't fit
+	pub fn print_aligned(&mut self, text: &str, mode: WrapMode, align: Align, valign: VAlign) -> usize {
+		let lines = text::wrap(text, self.get_width(), mode);
+		let valign = match valign {
+			VAlign::Top => Align::Left,
+			VAlign::Middle => Align::Center,
+			VAlign::Bottom => Align::Right,
+		};
+		let top = text::align_offset(lines.len(), self.get_height(), valign);
+		self.print_lines(top, &lines, align)
+	}
+	/// Internal: Prints lines one below the other from `top`, leaving the cursor at the start of the next line
+	fn print_lines(&mut self, top: u16, lines: &[String], align: Align) -> usize {
+		let mut y = top;
+		for line in lines {
+			if y >= self.get_height() {
+				break;
+			}
+			let x = text::align_offset(text::str_width(line), self.get_width(), align);
+			self.print(y, x, line);
+			y += 1;
+		}
+		self.move_cursor(y, 0);
+		lines.len()
+	}
+	/// Writes text at the cursor like a teletype would: it wraps at the right edge, `\n` goes to the start
+	/// of the next line, `\r` to the start of this one, and `\t` on to the next tab stop (see
+	/// `set_tab_stops`). Past the bottom line the window scrolls up if `scrollok` is on.
+	/// If the text runs out of room and the window can't scroll, returns None and the rest is left out
+	///
+	/// NOTE: Other control characters are skipped.
+	pub fn put_str(&mut self, text: &str) -> Option<()> {
+		for ch in text.chars() {
+			match ch {
+				'\n' => self.line_feed()?,
+				'\r' => self.cur_x = 0,
+				'\t' => {
+					let stop = self.next_tab_stop(self.cur_x);
+					if stop >= self.get_width() {
+						self.line_feed()?;
+						continue;
+					}
+					let blank = Cell { ch: ' ', style: cascade(self.default_style, self.style) };
+					self.buf.fill(Rect::new(self.cur_y, self.cur_x, 1, stop - self.cur_x), blank);
+					self.cur_x = stop;
+				},
+				_ if ch.is_control() => {},
+				_ => {
+					let width = text::char_width(ch);
+					// Wrapping waits until there's something to put on the next line, so a full line
+					// followed by a newline doesn't leave an empty one
+					if width > 0 && self.cur_x + width > self.get_width() {
+						self.line_feed()?;
+					}
+					let (y, x) = (self.cur_y, self.cur_x);
+					self.put_char(y, x, ch);
+				},
+			}
+		}
+		Some(())
+	}
+	/// Writes text and then a newline, see `put_str`.
+	pub fn put_line(&mut self, text: &str) -> Option<()> {
+		self.put_str(text)?;
+		self.put_str("\n")
+	}
+	/// Moves everything in the window up `lines` lines, with blank lines coming in at the bottom. The
+	/// cursor stays where it is.
+	pub fn scroll(&mut self, lines: u16) {
+		let (height, width) = (self.get_height(), self.get_width());
+		let lines = lines.min(height);
+		let old = self.buf.clone();
+		let blank = self.blank();
+		self.buf.fill(Rect::new(0, 0, height, width), blank);
+		for y in lines..height {
+			for x in 0..width {
+				self.buf.set(y - lines, x, *old.get(y, x).unwrap());
+			}
+		}
+	}
+	/// Internal: Goes to the start of the next line, scrolling if it's past the bottom and that's allowed.
+	/// If it can't, returns None
+	fn line_feed(&mut self) -> Option<()> {
+		if self.cur_y + 1 < self.get_height() {
+			self.cur_y += 1;
+		} else if self.scrollok && self.get_height() > 0 {
+			self.scroll(1);
+		} else {
+			return None;
+		}
+		self.cur_x = 0;
+		Some(())
+	}
+	/// Internal: The first tab stop after column `x`
+	fn next_tab_stop(&self, x: u16) -> u16 {
+		match self.tab_stops.iter().find(|&&stop| stop > x) {
+			Some(&stop) => stop,
+			None => (x / self.tab_width + 1).saturating_mul(self.tab_width),
+		}
+	}
+	/// Internal: Puts `cell` at (y, x), and its right half after it if it's wide.
+	/// If it doesn't fit, returns None
+	fn set_cell(&mut self, y: u16, x: u16, cell: Cell) -> Option<()> {
+		let width = text::char_width(cell.ch).max(1);
+		if x.checked_add(width - 1)? >= self.get_width() || y >= self.get_height() {
+			return None;
+		}
+		// Don't leave half of a wide character behind on either side
+		if x > 0 && self.buf.get(y, x)?.ch == CONTINUATION {
+			self.buf.set(y, x - 1, self.blank());
+		}
+		if self.buf.get(y, x + width).is_some_and(|cell| cell.ch == CONTINUATION) {
+			self.buf.set(y, x + width, self.blank());
+		}
+		self.buf.set(y, x, cell)?;
+		if width == 2 {
+			self.buf.set(y, x + 1, Cell { ch: CONTINUATION, style: cell.style })?;
+		}
+		Some(())
+	}
+	/// Internal: A blank cell in the window's background
+	fn blank(&self) -> Cell {
+		Cell { ch: ' ', style: self.default_style }
+	}
+	/// Blanks out the whole window and moves the cursor to the top-left corner.
+	pub fn clear(&mut self) {
+		let blank = self.blank();
+		self.buf.fill(Rect::new(0, 0, self.get_height(), self.get_width()), blank);
+		self.cur_y = 0;
+		self.cur_x = 0;
+	}
+	/// Blanks out the line the cursor is on.
+	pub fn clear_line(&mut self) {
+		let blank = self.blank();
+		self.buf.fill(Rect::new(self.cur_y, 0, 1, self.get_width()), blank);
+	}
+	/// Blanks out from the cursor to the end of its line.
+	pub fn clear_to_eol(&mut self) {
+		let blank = self.blank();
+		let width = self.get_width().saturating_sub(self.cur_x);
+		self.buf.fill(Rect::new(self.cur_y, self.cur_x, 1, width), blank);
+	}
+	/// Blanks out from the cursor to the bottom of the window.
+	pub fn clear_to_eos(&mut self) {
+		self.clear_to_eol();
+		let blank = self.blank();
+		let below = self.cur_y.saturating_add(1);
+		self.buf.fill(Rect::new(below, 0, self.get_height().saturating_sub(below), self.get_width()), blank);
+	}
+	/// Fills `rect` (relative to the window) with `ch` in `style`, cut off at the edges of the window.
+	/// The cursor and the window's style are left alone.
+	pub fn fill(&mut self, rect: Rect, ch: char, style: Style) {
+		let area = match rect.intersect(&Rect::new(0, 0, self.get_height(), self.get_width())) {
+			Some(area) => area,
+			None => return,
+		};
+		let saved = (self.cur_y, self.cur_x, self.style);
+		self.style = style;
+		let step = text::char_width(ch).max(1);
+		for y in area.y..area.bottom() {
+			let mut x = area.x;
+			while x + step <= area.right() {
+				self.put_char(y, x, ch);
+				x += step;
+			}
+		}
+		(self.cur_y, self.cur_x, self.style) = saved;
+	}
+	/// Blanks out `rect` (relative to the window).
+	pub fn clear_rect(&mut self, rect: Rect) {
+		self.fill(rect, ' ', Style::default());
+	}
+	/// Dims the cells inside of `rect` (relative to the window), leaving the text alone, e.g. for things
+	/// that are disabled. See `restyle` for other changes.
+	pub fn shade(&mut self, rect: Rect) {
+		self.restyle(rect, |s| s.attrs |= Attr::DIM);
+	}
+	/// Puts a line drawing piece at (y, x).
+	/// If the position is outside of the window, returns None
+	///
+	/// NOTE: Line drawing is always stored as Unicode, the Screen swaps it for whatever the terminal can
+	/// show (see `Screen::set_line_drawing`).
+	pub fn put_glyph(&mut self, y: u16, x: u16, glyph: Glyph) -> Option<()> {
+		self.put_char(y, x, glyph.ch())
+	}
+	/// Draws a horizontal line `len` cells long, starting at (y, x) and going right. It's cut off at the
+	/// edge of the window.
+	pub fn hline(&mut self, y: u16, x: u16, len: u16) {
+		for i in 0..len {
+			if self.put_glyph(y, x.saturating_add(i), Glyph::HLine).is_none() {
+				break;
+			}
+		}
+	}
+	/// Draws a vertical line `len` cells long, starting at (y, x) and going down. It's cut off at the
+	/// edge of the window.
+	pub fn vline(&mut self, y: u16, x: u16, len: u16) {
+		for i in 0..len {
+			if self.put_glyph(y.saturating_add(i), x, Glyph::VLine).is_none() {
+				break;
+			}
+		}
+	}
+	/// Draws a box around the edges of `rect` (relative to the window).
+	/// If the box doesn't fit in the window, or is smaller than 2x2, returns None
+	pub fn draw_box(&mut self, rect: Rect) -> Option<()> {
+		if rect.height < 2 || rect.width < 2 || rect.bottom() > self.get_height() || rect.right() > self.get_width() {
+			return None;
+		}
+		let (bottom, right) = (rect.bottom() - 1, rect.right() - 1);
+		self.hline(rect.y, rect.x + 1, rect.width - 2);
+		self.hline(bottom, rect.x + 1, rect.width - 2);
+		self.vline(rect.y + 1, rect.x, rect.height - 2);
+		self.vline(rect.y + 1, right, rect.height - 2);
+		self.put_glyph(rect.y, rect.x, Glyph::TopLeft)?;
+		self.put_glyph(rect.y, right, Glyph::TopRight)?;
+		self.put_glyph(bottom, rect.x, Glyph::BottomLeft)?;
+		self.put_glyph(bottom, right, Glyph::BottomRight)
+	}
+	/// Draws a box around the edges of the window.
+	pub fn border(&mut self) -> Option<()> {
+		let (height, width) = (self.get_height(), self.get_width());
+		self.draw_box(Rect::new(0, 0, height, width))
+	}
+	/// Marks `rect` (relative to the window) as clickable.
+	///
+	/// Once the window is drawn, using the mouse inside of it gets reported as an `Event::Region` with `id`.
+	/// Regions registered later are on top of earlier ones.
+	pub fn register_clickable(&mut self, rect: Rect, id: usize) {
+		self.regions.push((rect, id));
+	}
+	/// Removes every clickable region.
+	pub fn clear_regions(&mut self) {
+		self.regions.clear();
+	}
+	/// The clickable regions of the window, relative to the window.
+	pub fn regions(&self) -> &[(Rect, usize)] {
+		&self.regions
+	}
+	/// The area of the screen the window covers.
+	pub fn rect(&self) -> Rect {
+		Rect::new(self.y, self.x, self.get_height(), self.get_width())
+	}
+	/// Changes the style of the cells inside of `rect` (relative to the window), leaving the text alone.
+	pub fn restyle<F: FnMut(&mut Style)>(&mut self, rect: Rect, f: F) {
+		self.buf.restyle(rect, f);
+	}
+	/// Copies this window's text onto `dst` where the two overlap on the screen, leaving out blanks so
+	/// that what's in `dst` shows through them. Like ncurses' `overlay`.
+	pub fn overlay(&self, dst: &mut Window) {
+		self.copy_overlap(dst, true);
+	}
+	/// Copies this window's cells onto `dst` where the two overlap on the screen, blanks and all. Like
+	/// ncurses' `overwrite`.
+	pub fn overwrite(&self, dst: &mut Window) {
+		self.copy_overlap(dst, false);
+	}
+	/// Copies the cells inside of `src_rect` (relative to this window) onto `dst`, with their top-left
+	/// corner at `dst_origin` (y, x) in `dst`, wherever the windows are on the screen. Anything hanging
+	/// off of either window is cut off. Like ncurses' `copywin`, e.g. for putting a component that was
+	/// drawn off screen into the layout without drawing it again.
+	///
+	/// NOTE: Wide characters that would get cut in half are left out.
+	pub fn copy_to(&self, dst: &mut Window, src_rect: Rect, dst_origin: (u16, u16)) {
+		self.copy_cells(dst, src_rect, dst_origin, false);
+	}
+	/// Internal: Copies whatever overlaps `dst` on the screen, see `overlay` and `overwrite`
+	fn copy_overlap(&self, dst: &mut Window, transparent: bool) {
+		let area = match self.rect().intersect(&dst.rect()) {
+			Some(area) => area,
+			None => return,
+		};
+		let src_rect = Rect::new(area.y - self.y, area.x - self.x, area.height, area.width);
+		self.copy_cells(dst, src_rect, (area.y - dst.y, area.x - dst.x), transparent);
+	}
+	/// Internal: Copies the cells inside of `src_rect` to `origin` in `dst`, leaving out blanks if
+	/// `transparent`. Wide characters that would get cut in half become blanks (or are left out)
+	fn copy_cells(&self, dst: &mut Window, src_rect: Rect, origin: (u16, u16), transparent: bool) {
+		let src_rect = match src_rect.intersect(&Rect::new(0, 0, self.get_height(), self.get_width())) {
+			Some(rect) => rect,
+			None => return,
+		};
+		let height = src_rect.height.min(dst.get_height().saturating_sub(origin.0));
+		let width = src_rect.width.min(dst.get_width().saturating_sub(origin.1));
+		for dy in 0..height {
+			for dx in 0..width {
+				let cell = *self.buf.get(src_rect.y + dy, src_rect.x + dx).unwrap();
+				let (y, x) = (origin.0 + dy, origin.1 + dx);
+				let halved = match cell.ch {
+					// Copied along with the left half
+					CONTINUATION => dx == 0,
+					ch => text::char_width(ch) == 2 && dx + 1 >= width,
+				};
+				if halved {
+					if !transparent {
+						let blank = dst.blank();
+						dst.set_cell(y, x, blank);
+					}
+				} else if cell.ch != CONTINUATION && !(transparent && cell.ch == ' ') {
+					dst.set_cell(y, x, cell);
+				}
+			}
+		}
+	}
+	/// Moves the window to (y, x) on the screen.
+	pub fn move_to(&mut self, y: u16, x: u16) {
+		self.y = y;
+		self.x = x;
+	}
+	/// Changes the size of the window, keeping whatever fits of what was drawn in it.
+	pub fn resize(&mut self, height: u16, width: u16) {
+		let mut buf = Buffer::new(height, width);
+		buf.fill(Rect::new(0, 0, height, width), self.blank());
+		buf.copy_from(&self.buf, 0, 0);
+		self.buf = buf;
+		self.cur_y = self.cur_y.min(height.saturating_sub(1));
+		self.cur_x = self.cur_x.min(width.saturating_sub(1));
+	}
+	/// Runs `f` as a single drawing transaction.
+	///
+	/// If `f` returns an error (or panics), every change it made to the window is rolled back,
+	/// so a half-drawn window can never make it to the screen.
+	///
+	/// NOTE: The whole window, every cell included, is copied before `f` runs so there's something to
+	/// roll back to. That's about as much work as filling the window once, so for big windows it's best
+	/// kept to drawing that can actually fail.
+	pub fn transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+		where F: FnOnce(&mut Transaction) -> Result<T, E>
+	{
+		let mut tx = Transaction {
+			snapshot: Some(self.clone()),
+			win: self,
+		};
+		let out = f(&mut tx);
+		if out.is_ok() {
+			tx.snapshot = None;
+		}
+		out
+	}
+}
+
+/// Prints at the cursor, cutting lines off at the right edge. Newlines go to the start of the next
+/// line, and running out of lines is an error.
+impl fmt::Write for Window {
+	fn write_str(&mut self, text: &str) -> fmt::Result {
+		for ch in text.chars() {
+			if ch == '\n' {
+				let next = self.cur_y + 1;
+				self.move_cursor(next, 0).ok_or(fmt::Error)?;
+				continue;
+			}
+			let (y, x) = (self.cur_y, self.cur_x);
+			// Anything past the right edge doesn't fit, and gets left out
+			self.put_char(y, x, ch);
+		}
+		Ok(())
+	}
+}
+
+/// Prints formatted text at a window's cursor, with its current style, e.g. `wprintw!(win, "{} left", n)`.
+/// See `Window::print_fmt`.
+#[macro_export]
+macro_rules! wprintw {
+	($win:expr, $($arg:tt)*) => {
+		$win.print_fmt(format_args!($($arg)*))
+	};
+}
+
+/// Prints formatted text at (y, x) in a window, with its current style, e.g.
+/// `mvprintw!(win, 0, 2, "{:>3}%", done)`. Returns None if (y, x) is outside of the window, see
+/// `Window::printf`.
+#[macro_export]
+macro_rules! mvprintw {
+	($win:expr, $y:expr, $x:expr, $($arg:tt)*) => {
+		$win.printf($y, $x, format_args!($($arg)*))
+	};
+}
+
+/// A window in the middle of a transaction, see `Window::transaction`.
+///
+/// Derefs to the `Window` so all of the usual drawing methods can be used.
+pub struct Transaction<'a> {
+	win: &'a mut Window,
+	snapshot: Option<Window>,
+}
+
+impl<'a> Deref for Transaction<'a> {
+	type Target = Window;
+	fn deref(&self) -> &Window {
+		self.win
+	}
+}
+impl<'a> DerefMut for Transaction<'a> {
+	fn deref_mut(&mut self) -> &mut Window {
+		self.win
+	}
+}
+
+impl<'a> Drop for Transaction<'a> {
+	fn drop(&mut self) {
+		// Only still set if the transaction failed or panicked
+		if let Some(snapshot) = self.snapshot.take() {
+			*self.win = snapshot;
+		}
+	}
+}
@@ -0,0 +1,206 @@
+//! Getting around the panes of a layout and the popups on top of them from the keyboard, like in a
+//! tiling window manager, see `WindowManager`.
+
+use ::event::Event;
+use ::keymap::Keymap;
+use ::layout::Layout;
+use ::rect::Rect;
+use ::screen::Screen;
+
+/// Something the window manager can do, see `WindowManager::perform`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WmAction {
+	/// Focuses the pane to the left of the focused one
+	FocusLeft,
+	FocusRight,
+	FocusUp,
+	FocusDown,
+	/// Focuses the next pane in pane order, going round to the first after the last
+	FocusNext,
+	FocusPrev,
+	/// Brings the bottom popup to the top
+	CyclePopups,
+	/// Sends the top popup to the bottom
+	CyclePopupsBack,
+	/// Makes the focused pane take up the whole area, or puts it back
+	ToggleMaximize,
+}
+
+/// The usual bindings, after vim's: `ctrl-w` then `h`/`j`/`k`/`l` (or the arrows) to focus the pane
+/// in that direction, `w`/`W` for the next and previous pane, `z` to maximize or restore the focused
+/// pane, and `tab`/`backtab` to cycle the popups.
+pub fn default_keymap() -> Keymap<WmAction> {
+	let mut out = Keymap::new();
+	let bindings = [
+		("ctrl-w h", WmAction::FocusLeft),
+		("ctrl-w left", WmAction::FocusLeft),
+		("ctrl-w l", WmAction::FocusRight),
+		("ctrl-w right", WmAction::FocusRight),
+		("ctrl-w k", WmAction::FocusUp),
+		("ctrl-w up", WmAction::FocusUp),
+		("ctrl-w j", WmAction::FocusDown),
+		("ctrl-w down", WmAction::FocusDown),
+		("ctrl-w w", WmAction::FocusNext),
+		("ctrl-w W", WmAction::FocusPrev),
+		("ctrl-w z", WmAction::ToggleMaximize),
+		("ctrl-w tab", WmAction::CyclePopups),
+		("ctrl-w backtab", WmAction::CyclePopupsBack),
+	];
+	for &(keys, action) in bindings.iter() {
+		out.bind(keys, action).unwrap_or(());
+	}
+	out
+}
+
+/// Keeps track of which pane of a `Layout` has focus, and whether it's maximized, and carries out
+/// `WmAction`s on them (and on the screen's popups) as their keys are pressed.
+///
+/// Draw each pane at `get`, which takes maximizing into account, and give the focused one the keys
+/// that `handle` doesn't use.
+#[derive(Debug, Clone)]
+pub struct WindowManager {
+	layout: Layout,
+	keymap: Keymap<WmAction>,
+	/// The area from the last `compute`
+	area: Rect,
+	focused: usize,
+	maximized: bool,
+}
+
+impl WindowManager {
+	/// A window manager for the panes of `layout`, with the first one focused and the bindings from
+	/// `default_keymap`.
+	pub fn new(layout: Layout) -> WindowManager {
+		WindowManager {
+			layout,
+			keymap: default_keymap(),
+			area: Rect::new(0, 0, 0, 0),
+			focused: 0,
+			maximized: false,
+		}
+	}
+	pub fn layout(&self) -> &Layout {
+		&self.layout
+	}
+	/// The layout, for changing it. Call `compute` afterwards.
+	pub fn layout_mut(&mut self) -> &mut Layout {
+		&mut self.layout
+	}
+	/// The bindings for the actions, for changing them.
+	pub fn keymap_mut(&mut self) -> &mut Keymap<WmAction> {
+		&mut self.keymap
+	}
+	/// Works out where every pane goes inside of `area`, see `Layout::compute`.
+	pub fn compute(&mut self, area: Rect) {
+		self.area = area;
+		self.layout.compute(area);
+		let count = self.layout.rects().len();
+		if self.focused >= count {
+			self.focused = count.saturating_sub(1);
+		}
+	}
+	/// Where a pane is drawn: the whole area for the focused pane while it's maximized.
+	/// If there's no such pane or it's hidden behind a maximized one, returns None
+	pub fn get(&self, pane: usize) -> Option<Rect> {
+		let rect = self.layout.get(pane)?;
+		if !self.maximized {
+			Some(rect)
+		} else if pane == self.focused {
+			Some(self.area)
+		} else {
+			None
+		}
+	}
+	pub fn focused(&self) -> usize {
+		self.focused
+	}
+	/// Focuses `pane`, which stays maximized if the one before was.
+	/// If there's no such pane, returns None
+	pub fn set_focused(&mut self, pane: usize) -> Option<()> {
+		if pane >= self.layout.rects().len() {
+			return None;
+		}
+		self.focused = pane;
+		Some(())
+	}
+	pub fn is_maximized(&self) -> bool {
+		self.maximized
+	}
+	pub fn set_maximized(&mut self, maximized: bool) {
+		self.maximized = maximized;
+	}
+	/// Carries out `action`. Cycling the popups changes their order on `screen`.
+	/// Returns whether anything changed
+	pub fn perform(&mut self, screen: &mut Screen, action: WmAction) -> bool {
+		let count = self.layout.rects().len();
+		let pane = match action {
+			WmAction::FocusLeft | WmAction::FocusRight | WmAction::FocusUp | WmAction::FocusDown => self.neighbor(action),
+			WmAction::FocusNext if count > 1 => Some((self.focused + 1) % count),
+			WmAction::FocusPrev if count > 1 => Some((self.focused + count - 1) % count),
+			WmAction::FocusNext | WmAction::FocusPrev => None,
+			WmAction::CyclePopups | WmAction::CyclePopupsBack => {
+				let popups = screen.popups();
+				if popups.len() < 2 {
+					return false;
+				}
+				match action {
+					WmAction::CyclePopups => screen.raise_popup(popups[0]),
+					_ => screen.lower_popup(popups[popups.len() - 1]),
+				};
+				return true;
+			},
+			WmAction::ToggleMaximize => {
+				self.maximized = !self.maximized;
+				return true;
+			},
+		};
+		match pane {
+			Some(pane) => self.set_focused(pane).is_some(),
+			None => false,
+		}
+	}
+	/// Recomputes the layout for the whole screen on `Event::Resize` (like `Layout::handle`), and
+	/// carries out the action for keys that are bound to one.
+	/// Returns whether the event was used, which includes the first keys of a binding
+	pub fn handle(&mut self, screen: &mut Screen, event: &Event) -> bool {
+		if let Event::Resize { height, width } = *event {
+			self.compute(Rect::new(0, 0, height, width));
+			return true;
+		}
+		match self.keymap.process(event) {
+			Some(action) => {
+				self.perform(screen, action);
+				true
+			},
+			None => self.keymap.is_pending(),
+		}
+	}
+	/// Internal: The closest pane in the direction of `action` from the focused one. Panes that are
+	/// level with it win over ones that are off to the side, even if they're further away
+	fn neighbor(&self, action: WmAction) -> Option<usize> {
+		let rects = self.layout.rects();
+		let cur = *rects.get(self.focused)?;
+		let candidates = rects.iter().enumerate().filter(|&(i, r)| i != self.focused && !r.is_empty());
+		candidates.filter_map(|(i, r)| {
+			// How far away it is, and how far off to the side
+			let (gap, off) = match action {
+				WmAction::FocusLeft => (cur.x.checked_sub(r.right())?, apart(cur.y, cur.bottom(), r.y, r.bottom())),
+				WmAction::FocusRight => (r.x.checked_sub(cur.right())?, apart(cur.y, cur.bottom(), r.y, r.bottom())),
+				WmAction::FocusUp => (cur.y.checked_sub(r.bottom())?, apart(cur.x, cur.right(), r.x, r.right())),
+				_ => (r.y.checked_sub(cur.bottom())?, apart(cur.x, cur.right(), r.x, r.right())),
+			};
+			Some(((off, gap), i))
+		}).min().map(|(_, i)| i)
+	}
+}
+
+/// Internal: How far apart the spans from `a` to `a_end` and from `b` to `b_end` are, zero if they overlap
+fn apart(a: u16, a_end: u16, b: u16, b_end: u16) -> u16 {
+	if b_end <= a {
+		a - b_end + 1
+	} else if a_end <= b {
+		b - a_end + 1
+	} else {
+		0
+	}
+}